@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use log::warn;
+use log::{debug, warn};
 use sdl2::{
     image::ImageRWops,
     mixer::*,
@@ -17,15 +17,26 @@ use super::{
     bundle_parser::raw_assets_from_bundle,
     raw_asset::{RawAsset, Representation, Type},
     text_parser::raw_assets_from_text,
-    AssetSource, AssetSourceType, Data,
+    AssetSource, AssetSourceType, Data, Prefab,
 };
 
+/// What an animation's `frame_duration` is counted in: sixtieths of a
+/// second, whatever the step rate
+pub const ANIMATION_TICKS_PER_SECOND: u32 = 60;
+
 pub struct Animation {
-    pub frame_duration: u32, // duration in frames
+    pub frame_duration: u32, // duration in ticks
     pub frames_count: usize,
     pub texture_id: String,
 }
 
+impl Animation {
+    /// How long each frame is held
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_secs(self.frame_duration as u64) / ANIMATION_TICKS_PER_SECOND
+    }
+}
+
 /// Stable handle to a loaded texture.
 ///
 /// Resolving a texture by name costs a string hash, which a renderer
@@ -44,12 +55,16 @@ pub struct TextureInfo {
 
 #[derive(Default)]
 pub struct AssetManager<'a> {
-    textures: Vec<Texture<'a>>,
+    /// `None` where the manager was set up headless: the texture's size is
+    /// still known, there is just nothing to draw it with.
+    textures: Vec<Option<Texture<'a>>>,
+    texture_sizes: Vec<SizeU32>,
     texture_ids: HashMap<String, TextureId>,
     colors: HashMap<String, Color>,
     animations: HashMap<String, Animation>,
     binaries: HashMap<String, Data>,
     audio_chunks: HashMap<String, Chunk>,
+    prefabs: HashMap<String, Prefab>,
 }
 
 impl<'a> AssetManager<'a> {
@@ -68,6 +83,28 @@ impl<'a> AssetManager<'a> {
                 Type::Color => self.add_color(asset)?,
                 Type::VerticalGradient => self.add_vertical_gradient(asset, texture_creator)?,
                 Type::SoundChunk => self.add_audio_chunk(asset)?,
+                Type::Prefab => self.add_prefab(asset)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the assets without a renderer or an audio device.
+    ///
+    /// Textures are decoded only to learn their size, so renderer systems can
+    /// still lay out a frame, and sound chunks are skipped: the mixer is not
+    /// open, and nothing is going to be played anyway.
+    pub fn setup_headless(&mut self, source: &AssetSource) -> EngineResult<()> {
+        let raw_assets = load_assets(source)?;
+        for asset in &raw_assets {
+            match asset.asset_type {
+                Type::Texture => self.add_texture_info(asset)?,
+                Type::Animation => self.add_animation(asset)?,
+                Type::Binary => self.add_binary(asset)?,
+                Type::Color => self.add_color(asset)?,
+                Type::VerticalGradient => self.add_vertical_gradient_info(asset)?,
+                Type::SoundChunk => debug!("headless: skipping sound chunk '{}'", asset.id),
+                Type::Prefab => self.add_prefab(asset)?,
             }
         }
         Ok(())
@@ -79,22 +116,9 @@ impl<'a> AssetManager<'a> {
         texture_creator: &'a TextureCreator<WindowContext>,
         max_texture: SizeU32,
     ) -> EngineResult<()> {
-        let Representation::Binary { value } = &raw_asset.representation else {
-            return Err(EngineError::UnexpectedState(format!(
-                "Binary data not found for asset with id '{}'",
-                raw_asset.id
-            )));
-        };
         // decode to a surface first, so an image the renderer cannot hold
         // can be shrunk rather than simply refused
-        let image = RWops::from_bytes(value)
-            .and_then(|rwops| rwops.load())
-            .map_err(|err| {
-                EngineError::ResourceParseError(format!(
-                    "Failed to decode image for asset with id '{}': {err}",
-                    raw_asset.id
-                ))
-            })?;
+        let image = decode_image(raw_asset)?;
         let image = fit_within(image, max_texture, &raw_asset.id)?;
         let texture = texture_creator
             .create_texture_from_surface(&image)
@@ -104,7 +128,18 @@ impl<'a> AssetManager<'a> {
                     raw_asset.id
                 ))
             })?;
-        self.put_texture(&raw_asset.id, texture);
+        let size = texture_size(&texture);
+        self.put_texture(&raw_asset.id, Some(texture), size);
+        Ok(())
+    }
+
+    fn add_texture_info(&mut self, raw_asset: &RawAsset) -> EngineResult<()> {
+        let image = decode_image(raw_asset)?;
+        let size = SizeU32 {
+            width: image.width(),
+            height: image.height(),
+        };
+        self.put_texture(&raw_asset.id, None, size);
         Ok(())
     }
 
@@ -180,32 +215,44 @@ impl<'a> AssetManager<'a> {
                 raw_asset.id
             )));
         };
-        let tokens = value.split_whitespace().collect::<Vec<&str>>();
-        let Some(height) = tokens.get(1).and_then(|&val| val.parse::<u32>().ok()) else {
-            return Err(EngineError::ResourceParseError(format!(
-                "Gradient height not found or invalid '{}'",
-                raw_asset.id
-            )));
-        };
-        let (from, to) = parse_gradient(tokens.first().unwrap())?;
+        let (from, to, height) = parse_vertical_gradient(raw_asset, value)?;
         let Ok(texture) = create_gradient_texture(texture_creator, from, to, height) else {
             return Err(EngineError::ResourceParseError(format!(
                 "Failed to create texture gradient '{value}'"
             )));
         };
-        self.put_texture(&raw_asset.id, texture);
+        let size = texture_size(&texture);
+        self.put_texture(&raw_asset.id, Some(texture), size);
+        Ok(())
+    }
+
+    fn add_vertical_gradient_info(&mut self, raw_asset: &RawAsset) -> EngineResult<()> {
+        let Representation::Text { value } = &raw_asset.representation else {
+            return Err(EngineError::UnexpectedState(format!(
+                "Text data not found for asset with id '{}'",
+                raw_asset.id
+            )));
+        };
+        let (_, _, height) = parse_vertical_gradient(raw_asset, value)?;
+        // the gradient texture is one pixel wide, see create_gradient_texture
+        let size = SizeU32 { width: 1, height };
+        self.put_texture(&raw_asset.id, None, size);
         Ok(())
     }
 
     /// Stores a texture under `key`, replacing any texture already there so
     /// that a handle handed out earlier keeps pointing at the right asset.
-    fn put_texture(&mut self, key: &str, texture: Texture<'a>) {
+    fn put_texture(&mut self, key: &str, texture: Option<Texture<'a>>, size: SizeU32) {
         match self.texture_ids.get(key) {
-            Some(&TextureId(index)) => self.textures[index] = texture,
+            Some(&TextureId(index)) => {
+                self.textures[index] = texture;
+                self.texture_sizes[index] = size;
+            }
             None => {
                 self.texture_ids
                     .insert(key.to_string(), TextureId(self.textures.len()));
                 self.textures.push(texture);
+                self.texture_sizes.push(size);
             }
         }
     }
@@ -222,8 +269,20 @@ impl<'a> AssetManager<'a> {
         Ok(())
     }
 
+    fn add_prefab(&mut self, raw_asset: &RawAsset) -> EngineResult<()> {
+        let Representation::Text { value } = &raw_asset.representation else {
+            return Err(EngineError::UnexpectedState(format!(
+                "Text data not found for asset with id '{}'",
+                raw_asset.id
+            )));
+        };
+        let prefab = Prefab::parse(&raw_asset.id, value)?;
+        self.prefabs.insert(raw_asset.id.clone(), prefab);
+        Ok(())
+    }
+
     pub fn texture(&self, id: TextureId) -> Option<&Texture<'_>> {
-        self.textures.get(id.0)
+        self.textures.get(id.0)?.as_ref()
     }
 
     pub fn texture_id(&self, key: &str) -> Option<TextureId> {
@@ -246,6 +305,10 @@ impl<'a> AssetManager<'a> {
         self.audio_chunks.get(key)
     }
 
+    pub fn prefab(&self, key: &str) -> Option<&Prefab> {
+        self.prefabs.get(key)
+    }
+
     pub fn texture_names(&self) -> Vec<String> {
         self.texture_ids.keys().cloned().collect::<Vec<String>>()
     }
@@ -257,14 +320,11 @@ impl<'a> AssetManager<'a> {
         output: &mut HashMap<String, TextureInfo>,
     ) -> EngineResult<()> {
         for (name, &id) in &self.texture_ids {
-            let Some(texture) = self.texture(id) else {
+            let Some(&size) = self.texture_sizes.get(id.0) else {
                 let msg = format!("[AssetManager] texture id: {}", name);
                 return Err(EngineError::TextureNotFound(msg));
             };
-            let info = TextureInfo {
-                id,
-                size: texture_size(texture),
-            };
+            let info = TextureInfo { id, size };
             output.insert(name.clone(), info);
         }
         Ok(())
@@ -296,6 +356,36 @@ fn parse_color(value: &str) -> EngineResult<Color> {
     Ok(Color::RGBA(r, g, b, a))
 }
 
+fn decode_image(raw_asset: &RawAsset) -> EngineResult<Surface<'static>> {
+    let Representation::Binary { value } = &raw_asset.representation else {
+        return Err(EngineError::UnexpectedState(format!(
+            "Binary data not found for asset with id '{}'",
+            raw_asset.id
+        )));
+    };
+    RWops::from_bytes(value)
+        .and_then(|rwops| rwops.load())
+        .map_err(|err| {
+            EngineError::ResourceParseError(format!(
+                "Failed to decode image for asset with id '{}': {err}",
+                raw_asset.id
+            ))
+        })
+}
+
+/// Colors and height of a `vertical_gradient` asset: `<from>-<to> <height>`.
+fn parse_vertical_gradient(raw_asset: &RawAsset, value: &str) -> EngineResult<(Color, Color, u32)> {
+    let tokens = value.split_whitespace().collect::<Vec<&str>>();
+    let Some(height) = tokens.get(1).and_then(|&val| val.parse::<u32>().ok()) else {
+        return Err(EngineError::ResourceParseError(format!(
+            "Gradient height not found or invalid '{}'",
+            raw_asset.id
+        )));
+    };
+    let (from, to) = parse_gradient(tokens.first().unwrap())?;
+    Ok((from, to, height))
+}
+
 fn parse_gradient(value: &str) -> EngineResult<(Color, Color)> {
     let Some((from, to)) = value.split_once('-').and_then(|(from, to)| {
         let Ok(f) = parse_color(from) else {
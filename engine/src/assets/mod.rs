@@ -1,8 +1,10 @@
 mod bundle_parser;
 mod manager;
+pub(crate) mod prefab;
 mod text_parser;
 pub use bundle_parser::raw_assets_from_bundle;
 pub use manager::*;
+pub use prefab::Prefab;
 pub use text_parser::raw_assets_from_text;
 
 pub type Data = Vec<u8>;
@@ -49,6 +51,7 @@ pub mod raw_asset {
     pub const TYPE_ID_COLOR: TypeID = 3;
     pub const TYPE_ID_VERTICAL_GRADIENT: TypeID = 4;
     pub const TYPE_ID_SOUND_CHUNK: TypeID = 5;
+    pub const TYPE_ID_PREFAB: TypeID = 6;
 
     #[derive(Debug)]
     pub enum Representation {
@@ -73,6 +76,7 @@ pub mod raw_asset {
         Color,
         VerticalGradient,
         SoundChunk,
+        Prefab,
     }
 
     impl From<Type> for TypeID {
@@ -85,6 +89,7 @@ pub mod raw_asset {
                 Color => TYPE_ID_COLOR,
                 VerticalGradient => TYPE_ID_VERTICAL_GRADIENT,
                 SoundChunk => TYPE_ID_SOUND_CHUNK,
+                Prefab => TYPE_ID_PREFAB,
             }
         }
     }
@@ -100,6 +105,7 @@ pub mod raw_asset {
                 TYPE_ID_COLOR => Ok(Self::Color),
                 TYPE_ID_VERTICAL_GRADIENT => Ok(Self::VerticalGradient),
                 TYPE_ID_SOUND_CHUNK => Ok(Self::SoundChunk),
+                TYPE_ID_PREFAB => Ok(Self::Prefab),
                 _ => {
                     let msg = format!("unexpected asset type {}", value);
                     Err(EngineError::ResourceParseError(msg))
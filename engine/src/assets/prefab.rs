@@ -0,0 +1,90 @@
+use std::any::{Any, TypeId};
+
+use crate::{EngineError, EngineResult, EntityBundle};
+
+/// An entity template from the asset registry:
+///
+/// ```text
+/// prefab  prefab_npc_soldier  npc health=100 velocity=4.3 bounding_box=0.7,0.7
+/// ```
+///
+/// Each entry names a component, with the text its deserializer parses
+/// after `=`; an entry without a value is a marker. The components are only
+/// made when an entity is spawned, by the deserializers registered with
+/// [`ComponentStorage::register_prefab_component`](crate::ComponentStorage::register_prefab_component).
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    id: String,
+    entries: Vec<(String, String)>,
+}
+
+impl Prefab {
+    pub fn parse(id: impl Into<String>, text: &str) -> EngineResult<Self> {
+        let id = id.into();
+        let mut entries = Vec::new();
+        for token in text.split_whitespace() {
+            let (name, value) = token.split_once('=').unwrap_or((token, ""));
+            if name.is_empty() {
+                let msg = format!("prefab '{id}': component name missing in '{token}'");
+                return Err(EngineError::ResourceParseError(msg));
+            }
+            if entries.iter().any(|(x, _)| x == name) {
+                let msg = format!("prefab '{id}': component '{name}' given twice");
+                return Err(EngineError::ResourceParseError(msg));
+            }
+            entries.push((name.to_string(), value.to_string()));
+        }
+        Ok(Self { id, entries })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Component names and their values, in the order they were given
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+type ParseFn = Box<dyn Fn(&str) -> EngineResult<Box<dyn Any>>>;
+
+/// Makes a component from its prefab entry
+pub(crate) struct PrefabComponent {
+    pub(crate) name: &'static str,
+    type_id: TypeId,
+    parse: ParseFn,
+}
+
+impl PrefabComponent {
+    pub(crate) fn new<T: Any>(name: &'static str, parse: fn(&str) -> EngineResult<T>) -> Self {
+        Self {
+            name,
+            type_id: TypeId::of::<T>(),
+            parse: Box::new(move |text| Ok(Box::new(parse(text)?))),
+        }
+    }
+}
+
+pub(crate) fn bundle(
+    prefab: &Prefab,
+    components: &[PrefabComponent],
+) -> EngineResult<EntityBundle> {
+    let mut bundle = EntityBundle::new();
+    for (name, value) in prefab.entries() {
+        let Some(component) = components.iter().find(|x| x.name == name) else {
+            let msg = format!("prefab '{}': unknown component '{name}'", prefab.id);
+            return Err(EngineError::ResourceParseError(msg));
+        };
+        let value = (component.parse)(value).map_err(|err| match err {
+            EngineError::ResourceParseError(msg) => {
+                EngineError::ResourceParseError(format!("prefab '{}', {name}: {msg}", prefab.id))
+            }
+            err => err,
+        })?;
+        bundle.components.insert(component.type_id, value);
+    }
+    Ok(bundle)
+}
@@ -41,6 +41,7 @@ const ASSET_KEY_VERTICAL_GRADIENT: &str = "vertical_gradient";
 const ASSET_KEY_ANIMATION: &str = "animation";
 const ASSET_KEY_BINARY: &str = "binary";
 const ASSET_KEY_SOUND_CHUNK: &str = "sound";
+const ASSET_KEY_PREFAB: &str = "prefab";
 
 impl TryFrom<&str> for RawAsset {
     type Error = EngineError;
@@ -57,6 +58,7 @@ impl TryFrom<&str> for RawAsset {
             ASSET_KEY_COLOR => RawAsset::color(&tokens),
             ASSET_KEY_VERTICAL_GRADIENT => RawAsset::vertical_gradient(&tokens),
             ASSET_KEY_SOUND_CHUNK => RawAsset::sound_chunk(&tokens),
+            ASSET_KEY_PREFAB => RawAsset::prefab(&tokens),
             _ => {
                 let msg = format!("Unknown type {id}");
                 Err(EngineError::ResourceParseError(msg))
@@ -90,6 +92,10 @@ impl RawAsset {
         Self::raw_text(tokens, Type::VerticalGradient)
     }
 
+    fn prefab(tokens: &[&str]) -> EngineResult<Self> {
+        Self::raw_text(tokens, Type::Prefab)
+    }
+
     fn raw_binary(tokens: &[&str], asset_type: Type) -> EngineResult<Self> {
         let (Some(&id), Some(&path)) = (tokens.get(1), tokens.get(2)) else {
             return Err(EngineError::ResourceParseError(format!(
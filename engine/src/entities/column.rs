@@ -0,0 +1,162 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell, RefMut},
+    ops::{Deref, DerefMut},
+};
+
+/// One entity's component, with the steps it was added and last changed on.
+pub(crate) struct Slot<T> {
+    value: RefCell<T>,
+    added: usize,
+    changed: Cell<usize>,
+}
+
+impl<T> Slot<T> {
+    pub(crate) fn value(&self) -> &RefCell<T> {
+        &self.value
+    }
+
+    pub(crate) fn added(&self) -> usize {
+        self.added
+    }
+
+    pub(crate) fn changed(&self) -> usize {
+        self.changed.get()
+    }
+
+    /// Borrows the value mutably; writing through the guard marks the
+    /// component changed on `tick`
+    pub(crate) fn try_borrow_mut(&self, tick: usize) -> Option<Mut<'_, T>> {
+        let value = self.value.try_borrow_mut().ok()?;
+        Some(Mut {
+            value,
+            changed: &self.changed,
+            tick,
+        })
+    }
+}
+
+/// All values of one component type, indexed by entity index.
+///
+/// Each cell has a `RefCell` of its own, so a system may hold one entity's
+/// component mutably while reading the same component of others.
+pub struct Column<T> {
+    cells: Vec<Option<Slot<T>>>,
+}
+
+impl<T: Any> Column<T> {
+    pub(crate) fn new(len: usize, capacity: usize) -> Self {
+        let mut cells = Vec::with_capacity(capacity.max(len));
+        cells.resize_with(len, || None);
+        Self { cells }
+    }
+
+    pub(crate) fn slot(&self, index: usize) -> Option<&Slot<T>> {
+        self.cells.get(index)?.as_ref()
+    }
+
+    /// Stores or drops the value on `tick`. Replacing a value counts as a
+    /// change, not an addition. True if there was a value before.
+    pub(crate) fn put(&mut self, index: usize, value: Option<T>, tick: usize) -> bool {
+        let cell = &mut self.cells[index];
+        let existed = cell.is_some();
+        let added = cell.as_ref().map_or(tick, |slot| slot.added);
+        *cell = value.map(|value| Slot {
+            value: RefCell::new(value),
+            added,
+            changed: Cell::new(tick),
+        });
+        existed
+    }
+}
+
+/// Mutable access to a component, as returned by
+/// [`ComponentStorage::get_mut`](crate::ComponentStorage::get_mut) and `&mut T`
+/// queries. Reading through it leaves the component as it was; writing
+/// marks it changed on the current step.
+pub struct Mut<'a, T> {
+    value: RefMut<'a, T>,
+    changed: &'a Cell<usize>,
+    tick: usize,
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed.set(self.tick);
+        &mut self.value
+    }
+}
+
+/// A [`Column`] with its component type erased, so that columns of all
+/// types fit in one map. The storage downcasts it once per lookup or query,
+/// never per entity.
+pub(crate) trait AnyColumn {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Grows the column to hold `len` entities
+    fn ensure_len(&mut self, len: usize);
+
+    /// True if there was a value to drop
+    fn clear(&mut self, index: usize) -> bool;
+
+    /// `value` must be a `Box<T>` of the column's component type
+    fn put_boxed(&mut self, index: usize, value: Box<dyn Any>, tick: usize);
+
+    fn added_at(&self, index: usize) -> Option<usize>;
+
+    fn changed_at(&self, index: usize) -> Option<usize>;
+
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T: Any> AnyColumn for Column<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn ensure_len(&mut self, len: usize) {
+        if self.cells.len() < len {
+            self.cells.resize_with(len, || None);
+        }
+    }
+
+    fn clear(&mut self, index: usize) -> bool {
+        self.cells
+            .get_mut(index)
+            .and_then(|cell| cell.take())
+            .is_some()
+    }
+
+    fn put_boxed(&mut self, index: usize, value: Box<dyn Any>, tick: usize) {
+        let value = value
+            .downcast::<T>()
+            .expect("[Column] value type doesn't match the column");
+        self.put(index, Some(*value), tick);
+    }
+
+    fn added_at(&self, index: usize) -> Option<usize> {
+        self.slot(index).map(Slot::added)
+    }
+
+    fn changed_at(&self, index: usize) -> Option<usize> {
+        self.slot(index).map(Slot::changed)
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
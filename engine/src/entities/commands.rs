@@ -0,0 +1,113 @@
+//! Structural changes queued while the storage is borrowed.
+//!
+//! A system walking [`ComponentStorage::iter`] holds the storage shared, so
+//! it can't spawn, despawn or attach components on the spot. It queues them
+//! instead:
+//!
+//! ```ignore
+//! for (id, (position, _)) in storage.iter::<(&Position, &Corpse)>() {
+//!     storage.commands().remove::<Corpse>(id);
+//!     storage.commands().spawn(EntityBundle::new().put(Decal).put(*position));
+//! }
+//! ```
+//!
+//! The commands run in the order they were queued when the storage applies
+//! them; a [`GameScene`](crate::game_scene::GameScene) does that after every
+//! system's update, so the next system sees the result.
+
+use std::any::Any;
+
+use crate::{ComponentStorage, EngineResult, EntityBundle, EntityID};
+
+type DeferredFn = Box<dyn FnOnce(&mut ComponentStorage) -> EngineResult<()>>;
+
+pub(crate) enum Command {
+    Spawn(EntityBundle),
+    Despawn(EntityID),
+    Run(DeferredFn),
+}
+
+/// Queue returned by [`ComponentStorage::commands`]
+#[derive(Default)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    pub fn spawn(&mut self, bundle: EntityBundle) {
+        self.commands.push(Command::Spawn(bundle));
+    }
+
+    /// Spawns an entity attached to `parent`, see
+    /// [`ComponentStorage::set_parent`]. Skipped if the parent is gone.
+    pub fn spawn_child(&mut self, parent: EntityID, bundle: EntityBundle) {
+        self.run(move |storage| {
+            if !storage.is_alive(parent) {
+                return Ok(());
+            }
+            let child = storage.append(bundle);
+            storage.set_parent(child, Some(parent))
+        });
+    }
+
+    /// Despawning an entity that is already gone does nothing
+    pub fn despawn(&mut self, entity_id: EntityID) {
+        self.commands.push(Command::Despawn(entity_id));
+    }
+
+    /// Attaches `value` to the entity, replacing the one it has.
+    ///
+    /// Skipped if the entity is gone by the time the command runs, which
+    /// happens when an earlier command despawned it. An unregistered `T`
+    /// still fails, as it does with [`ComponentStorage::set`].
+    pub fn insert<T: Any>(&mut self, entity_id: EntityID, value: T) {
+        self.set(entity_id, Some(value));
+    }
+
+    /// Drops the entity's `T`, skipped like [`Self::insert`]
+    pub fn remove<T: Any>(&mut self, entity_id: EntityID) {
+        self.set::<T>(entity_id, None);
+    }
+
+    fn set<T: Any>(&mut self, entity_id: EntityID, value: Option<T>) {
+        self.run(move |storage| {
+            if !storage.is_alive(entity_id) {
+                return Ok(());
+            }
+            storage.set(entity_id, value)
+        });
+    }
+
+    /// Queues arbitrary work, e.g. a read-modify-write of a component that
+    /// has to see the commands queued before it.
+    pub fn run(&mut self, f: impl FnOnce(&mut ComponentStorage) -> EngineResult<()> + 'static) {
+        self.commands.push(Command::Run(Box::new(f)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+impl Command {
+    pub(crate) fn apply(self, storage: &mut ComponentStorage) -> EngineResult<()> {
+        match self {
+            Command::Spawn(bundle) => {
+                storage.append(bundle);
+            }
+            Command::Despawn(entity_id) => {
+                storage.remove_entity(entity_id);
+            }
+            Command::Run(f) => f(storage)?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! Typed messages between systems.
+//!
+//! ```ignore
+//! storage.register_event::<Killed>();             // once, at scene setup
+//! storage.send(Killed { entity_id })?;            // from any system
+//! for event in storage.read(&mut self.killed) {   // a reader per system
+//!     ...
+//! }
+//! ```
+//!
+//! Each reader has a cursor of its own, so every system sees every event
+//! once, whichever order the systems run in. An event is kept through the
+//! step after the one it was sent on -- long enough for a system running
+//! before the sender to see it on its next update -- and dropped after.
+
+use std::{any::Any, marker::PhantomData};
+
+/// A system's position in the queue of `E` events
+pub struct EventReader<E> {
+    cursor: u64,
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<E> EventReader<E> {
+    pub fn new() -> Self {
+        Self {
+            cursor: 0,
+            _event: PhantomData,
+        }
+    }
+}
+
+impl<E> Default for EventReader<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) struct EventQueue<E> {
+    /// sequence number, the tick it was sent on, the event
+    events: Vec<(u64, usize, E)>,
+    next: u64,
+}
+
+impl<E: Any + Clone> EventQueue<E> {
+    pub(crate) fn new() -> Self {
+        Self {
+            events: Vec::new(),
+            next: 0,
+        }
+    }
+
+    pub(crate) fn send(&mut self, event: E, tick: usize) {
+        self.events.push((self.next, tick, event));
+        self.next += 1;
+    }
+
+    pub(crate) fn read(&self, reader: &mut EventReader<E>) -> Vec<E> {
+        let events = self
+            .events
+            .iter()
+            .filter(|(seq, _, _)| *seq >= reader.cursor)
+            .map(|(_, _, event)| event.clone())
+            .collect();
+        reader.cursor = self.next;
+        events
+    }
+}
+
+/// An [`EventQueue`] with its event type erased
+pub(crate) trait AnyEventQueue {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Drops the events sent before the previous step
+    fn prune(&mut self, tick: usize);
+
+    /// Drops every event; readers still only see the ones sent later
+    fn clear(&mut self);
+}
+
+impl<E: Any + Clone> AnyEventQueue for EventQueue<E> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn prune(&mut self, tick: usize) {
+        self.events.retain(|(_, at, _)| at + 1 >= tick);
+    }
+
+    fn clear(&mut self) {
+        self.events.clear();
+    }
+}
@@ -1,35 +1,96 @@
-use std::mem::{self};
+type Word = u128;
 
-type Representation = u128;
+const WORD_BITS: usize = Word::BITS as usize;
 
+/// The set of components an entity has, one bit per registered type.
+///
+/// The first 128 types live inline, which covers every scene so far and
+/// keeps a query test to a couple of integer operations. Types registered
+/// past that spill into `overflow`, one more word per 128 types, and only
+/// footprints that actually use such a type pay for it.
+#[derive(Clone, Default)]
 pub struct Footprint {
-    raw: u128,
+    raw: Word,
+    overflow: Vec<Word>,
 }
 
 impl Footprint {
     pub fn new() -> Self {
-        Self { raw: 0 }
+        Self::default()
     }
 
     pub fn set(&mut self, pos: usize, value: bool) {
-        let val = 1 << pos;
+        let (word, val) = Self::locate(pos);
+        let raw = if word == 0 {
+            &mut self.raw
+        } else if value {
+            if self.overflow.len() < word {
+                self.overflow.resize(word, 0);
+            }
+            &mut self.overflow[word - 1]
+        } else {
+            // clearing a bit that was never set
+            let Some(raw) = self.overflow.get_mut(word - 1) else {
+                return;
+            };
+            raw
+        };
         if value {
-            self.raw |= val;
+            *raw |= val;
         } else {
-            self.raw &= !val;
+            *raw &= !val;
         }
     }
 
     pub fn get(&self, pos: usize) -> bool {
-        self.raw & 1 << pos > 0
+        let (word, val) = Self::locate(pos);
+        self.word(word) & val > 0
     }
 
+    /// True if `other` has every component this footprint has
     pub fn is_matches(&self, other: &Self) -> bool {
         self.raw & other.raw == self.raw
+            && self
+                .overflow
+                .iter()
+                .enumerate()
+                .all(|(i, raw)| raw & other.word(i + 1) == *raw)
+    }
+
+    /// True if the footprints share a component
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.raw & other.raw != 0
+            || self
+                .overflow
+                .iter()
+                .zip(&other.overflow)
+                .any(|(a, b)| a & b != 0)
+    }
+
+    fn word(&self, index: usize) -> Word {
+        if index == 0 {
+            self.raw
+        } else {
+            self.overflow.get(index - 1).copied().unwrap_or_default()
+        }
+    }
+
+    fn locate(pos: usize) -> (usize, Word) {
+        (pos / WORD_BITS, 1 << (pos % WORD_BITS))
     }
+}
+
+/// What a query asks of an entity's footprint: every required component
+/// and none of the excluded ones.
+#[derive(Clone, Default)]
+pub struct FootprintFilter {
+    pub required: Footprint,
+    pub excluded: Footprint,
+}
 
-    pub fn max_items() -> usize {
-        8 * mem::size_of::<Representation>()
+impl FootprintFilter {
+    pub fn is_matches(&self, footprint: &Footprint) -> bool {
+        self.required.is_matches(footprint) && !self.excluded.intersects(footprint)
     }
 }
 
@@ -38,7 +99,39 @@ mod test {
     use super::*;
 
     #[test]
-    fn footprint_max_items() {
-        assert_eq!(Footprint::max_items(), 128);
+    fn footprint_grows_past_the_inline_word() {
+        let mut footprint = Footprint::new();
+        for pos in [0, 127, 128, 300] {
+            footprint.set(pos, true);
+            assert!(footprint.get(pos));
+        }
+        assert!(!footprint.get(129));
+        assert!(!footprint.get(1000));
+        footprint.set(300, false);
+        assert!(!footprint.get(300));
+        // clearing beyond the allocated words is a no-op
+        footprint.set(1000, false);
+        assert!(footprint.get(128));
+    }
+
+    #[test]
+    fn matching_covers_overflow_words() {
+        let mut entity = Footprint::new();
+        entity.set(3, true);
+        entity.set(200, true);
+
+        let mut query = Footprint::new();
+        query.set(3, true);
+        assert!(query.is_matches(&entity));
+        query.set(200, true);
+        assert!(query.is_matches(&entity));
+        query.set(400, true);
+        assert!(!query.is_matches(&entity));
+
+        let mut excluded = Footprint::new();
+        excluded.set(201, true);
+        assert!(!excluded.intersects(&entity));
+        excluded.set(200, true);
+        assert!(excluded.intersects(&entity));
     }
 }
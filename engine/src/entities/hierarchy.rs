@@ -0,0 +1,72 @@
+//! Parent/child links between entities.
+//!
+//! A child goes away with its parent, and its position-like components are
+//! read as offsets from the parent's, see
+//! [`ComponentStorage::world_position`](crate::ComponentStorage::world_position).
+
+use std::collections::HashMap;
+
+use crate::EntityID;
+
+#[derive(Default)]
+pub(crate) struct Hierarchy {
+    parents: HashMap<EntityID, EntityID>,
+    /// In the order the children were attached
+    children: HashMap<EntityID, Vec<EntityID>>,
+}
+
+impl Hierarchy {
+    pub(crate) fn parent(&self, child: EntityID) -> Option<EntityID> {
+        self.parents.get(&child).copied()
+    }
+
+    pub(crate) fn children(&self, parent: EntityID) -> &[EntityID] {
+        self.children.get(&parent).map_or(&[], Vec::as_slice)
+    }
+
+    /// True if `ancestor` is `entity_id` or one of its parents
+    pub(crate) fn is_ancestor(&self, ancestor: EntityID, entity_id: EntityID) -> bool {
+        let mut current = Some(entity_id);
+        while let Some(entity_id) = current {
+            if entity_id == ancestor {
+                return true;
+            }
+            current = self.parent(entity_id);
+        }
+        false
+    }
+
+    pub(crate) fn attach(&mut self, child: EntityID, parent: EntityID) {
+        self.detach(child);
+        self.parents.insert(child, parent);
+        self.children.entry(parent).or_default().push(child);
+    }
+
+    pub(crate) fn detach(&mut self, child: EntityID) {
+        let Some(parent) = self.parents.remove(&child) else {
+            return;
+        };
+        if let Some(siblings) = self.children.get_mut(&parent) {
+            siblings.retain(|x| *x != child);
+            if siblings.is_empty() {
+                self.children.remove(&parent);
+            }
+        }
+    }
+
+    /// Unlinks the entity and returns its descendants, parents before
+    /// their children
+    pub(crate) fn remove(&mut self, entity_id: EntityID) -> Vec<EntityID> {
+        self.detach(entity_id);
+        let mut descendants = Vec::new();
+        let mut pending = vec![entity_id];
+        while let Some(parent) = pending.pop() {
+            for child in self.children.remove(&parent).unwrap_or_default() {
+                self.parents.remove(&child);
+                descendants.push(child);
+                pending.push(child);
+            }
+        }
+        descendants
+    }
+}
@@ -0,0 +1,76 @@
+//! A readable dump of the storage, for chasing down what state an entity
+//! was in. Components show their `Debug` rendering once registered with
+//! [`ComponentStorage::register_debug`]; the rest show only their name.
+
+use std::{
+    any::{Any, TypeId},
+    fmt::{Debug, Write},
+};
+
+use crate::{ComponentStorage, EntityID};
+
+pub(crate) struct DebugView {
+    pub(crate) type_id: TypeId,
+    format: fn(&ComponentStorage, EntityID) -> Option<String>,
+}
+
+impl DebugView {
+    pub(crate) fn new<T: Any + Debug>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            format: |storage, entity_id| {
+                // a component borrowed mutably right now is left out
+                let value = storage.get::<T>(entity_id)?;
+                Some(format!("{:?}", &*value))
+            },
+        }
+    }
+
+    pub(crate) fn format(&self, storage: &ComponentStorage, entity_id: EntityID) -> Option<String> {
+        (self.format)(storage, entity_id)
+    }
+}
+
+/// `std::any::type_name` without the module paths:
+/// `Option<game::Health>` becomes `Option<Health>`
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+            continue;
+        }
+        short.push_str(segment.rsplit("::").next().unwrap_or_default());
+        segment.clear();
+        short.push(c);
+    }
+    short.push_str(segment.rsplit("::").next().unwrap_or_default());
+    short
+}
+
+pub(crate) fn dump(storage: &ComponentStorage) -> String {
+    let mut text = format!("tick {}, {} entities\n", storage.tick(), storage.len());
+    for entity_id in storage.entities() {
+        let _ = write!(text, "\n{}", label(entity_id));
+        if let Some(parent) = storage.parent(entity_id) {
+            let _ = write!(text, ", child of {}", label(parent));
+        }
+        text.push('\n');
+        for (name, value) in storage.inspect(entity_id) {
+            match value {
+                Some(value) => {
+                    let _ = writeln!(text, "  {name}: {value}");
+                }
+                None => {
+                    let _ = writeln!(text, "  {name}");
+                }
+            }
+        }
+    }
+    text
+}
+
+fn label(entity_id: EntityID) -> String {
+    format!("entity {}v{}", entity_id.index(), entity_id.generation())
+}
@@ -1,3 +1,10 @@
+pub mod column;
+pub mod commands;
+pub mod events;
 mod footprint;
+mod hierarchy;
+mod inspect;
+pub mod query;
+pub mod snapshot;
 pub mod storage;
 pub mod utils;
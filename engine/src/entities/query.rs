@@ -0,0 +1,233 @@
+//! Typed iteration over the entities that have a given set of components.
+//!
+//! ```ignore
+//! for (id, (position, mut movement)) in storage.iter::<(&Position, &mut Movement)>() {
+//!     ...
+//! }
+//! ```
+//!
+//! Each component type's column is looked up and downcast once per query;
+//! matching is a footprint bit test and fetching an item is an index into
+//! the column plus a `RefCell` borrow.
+
+use std::{
+    any::{Any, TypeId},
+    cell::Ref,
+};
+
+use super::{
+    column::{AnyColumn, Column, Mut},
+    footprint::{Footprint, FootprintFilter},
+};
+use crate::{ComponentStorage, EntityID};
+
+/// What a query yields per entity: `&T`, `&mut T`, `Option<&T>`,
+/// `Option<&mut T>`, or a tuple of those. An `Option` term doesn't take part
+/// in matching and yields `None` for entities lacking the component.
+///
+/// An entity whose component is already borrowed in a conflicting way --
+/// for instance by a `get_mut` still held elsewhere -- is skipped, the way
+/// [`ComponentStorage::get`] returns `None` for it.
+pub trait QueryData {
+    /// The columns the query reads from
+    type Columns<'a>;
+    type Item<'a>;
+
+    /// Collects the component types an entity must have to match
+    fn component_types(types: &mut Vec<TypeId>);
+
+    /// None if a required component type isn't registered
+    fn columns(storage: &ComponentStorage) -> Option<Self::Columns<'_>>;
+
+    fn fetch<'a>(columns: &Self::Columns<'a>, index: usize) -> Option<Self::Item<'a>>;
+}
+
+impl<T: Any> QueryData for &T {
+    type Columns<'a> = &'a Column<T>;
+    type Item<'a> = Ref<'a, T>;
+
+    fn component_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn columns(storage: &ComponentStorage) -> Option<Self::Columns<'_>> {
+        storage.column::<T>()
+    }
+
+    fn fetch<'a>(columns: &Self::Columns<'a>, index: usize) -> Option<Self::Item<'a>> {
+        let column: &'a Column<T> = columns;
+        column.slot(index)?.value().try_borrow().ok()
+    }
+}
+
+impl<T: Any> QueryData for &mut T {
+    /// The column and the tick writes are stamped with
+    type Columns<'a> = (&'a Column<T>, usize);
+    type Item<'a> = Mut<'a, T>;
+
+    fn component_types(types: &mut Vec<TypeId>) {
+        types.push(TypeId::of::<T>());
+    }
+
+    fn columns(storage: &ComponentStorage) -> Option<Self::Columns<'_>> {
+        Some((storage.column::<T>()?, storage.tick()))
+    }
+
+    fn fetch<'a>(columns: &Self::Columns<'a>, index: usize) -> Option<Self::Item<'a>> {
+        let (column, tick) = *columns;
+        column.slot(index)?.try_borrow_mut(tick)
+    }
+}
+
+impl<T: Any> QueryData for Option<&T> {
+    type Columns<'a> = Option<&'a Column<T>>;
+    type Item<'a> = Option<Ref<'a, T>>;
+
+    fn component_types(_types: &mut Vec<TypeId>) {}
+
+    fn columns(storage: &ComponentStorage) -> Option<Self::Columns<'_>> {
+        // an unregistered optional type is simply never present
+        Some(storage.column::<T>())
+    }
+
+    fn fetch<'a>(columns: &Self::Columns<'a>, index: usize) -> Option<Self::Item<'a>> {
+        let Some(slot) = columns.and_then(|column| column.slot(index)) else {
+            return Some(None);
+        };
+        slot.value().try_borrow().ok().map(Some)
+    }
+}
+
+impl<T: Any> QueryData for Option<&mut T> {
+    type Columns<'a> = (Option<&'a Column<T>>, usize);
+    type Item<'a> = Option<Mut<'a, T>>;
+
+    fn component_types(_types: &mut Vec<TypeId>) {}
+
+    fn columns(storage: &ComponentStorage) -> Option<Self::Columns<'_>> {
+        Some((storage.column::<T>(), storage.tick()))
+    }
+
+    fn fetch<'a>(columns: &Self::Columns<'a>, index: usize) -> Option<Self::Item<'a>> {
+        let (column, tick) = *columns;
+        let Some(slot) = column.and_then(|column| column.slot(index)) else {
+            return Some(None);
+        };
+        slot.try_borrow_mut(tick).map(Some)
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($term:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($term: QueryData),+> QueryData for ($($term,)+) {
+            type Columns<'a> = ($($term::Columns<'a>,)+);
+            type Item<'a> = ($($term::Item<'a>,)+);
+
+            fn component_types(types: &mut Vec<TypeId>) {
+                $($term::component_types(types);)+
+            }
+
+            fn columns(storage: &ComponentStorage) -> Option<Self::Columns<'_>> {
+                Some(($($term::columns(storage)?,)+))
+            }
+
+            fn fetch<'a>(columns: &Self::Columns<'a>, index: usize) -> Option<Self::Item<'a>> {
+                let ($($term,)+) = columns;
+                Some(($($term::fetch($term, index)?,)+))
+            }
+        }
+    };
+}
+
+impl_query_data_tuple!(A);
+impl_query_data_tuple!(A, B);
+impl_query_data_tuple!(A, B, C);
+impl_query_data_tuple!(A, B, C, D);
+impl_query_data_tuple!(A, B, C, D, E);
+impl_query_data_tuple!(A, B, C, D, E, F);
+
+/// What an entity must match: the footprint test first, then the change
+/// ticks, which take a look into the columns.
+#[derive(Default)]
+pub(crate) struct EntityFilter<'a> {
+    footprint: FootprintFilter,
+    added_since: Vec<(&'a dyn AnyColumn, usize)>,
+    changed_since: Vec<(&'a dyn AnyColumn, usize)>,
+}
+
+impl<'a> EntityFilter<'a> {
+    pub(crate) fn new(footprint: FootprintFilter) -> Self {
+        Self {
+            footprint,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn added_since(&mut self, column: &'a dyn AnyColumn, tick: usize) {
+        self.added_since.push((column, tick));
+    }
+
+    pub(crate) fn changed_since(&mut self, column: &'a dyn AnyColumn, tick: usize) {
+        self.changed_since.push((column, tick));
+    }
+
+    pub(crate) fn is_matches(&self, index: usize, footprint: &Footprint) -> bool {
+        self.footprint.is_matches(footprint)
+            && self
+                .added_since
+                .iter()
+                .all(|(column, tick)| column.added_at(index).is_some_and(|at| at >= *tick))
+            && self
+                .changed_since
+                .iter()
+                .all(|(column, tick)| column.changed_at(index).is_some_and(|at| at >= *tick))
+    }
+}
+
+/// Iterator returned by [`ComponentStorage::iter`] and
+/// [`ComponentStorage::iter_filtered`]
+pub struct QueryIter<'a, Q: QueryData> {
+    storage: &'a ComponentStorage,
+    /// None when the query can't match anything
+    columns: Option<Q::Columns<'a>>,
+    filter: EntityFilter<'a>,
+    index: usize,
+}
+
+impl<'a, Q: QueryData> QueryIter<'a, Q> {
+    pub(crate) fn new(
+        storage: &'a ComponentStorage,
+        columns: Option<Q::Columns<'a>>,
+        filter: EntityFilter<'a>,
+    ) -> Self {
+        Self {
+            storage,
+            columns,
+            filter,
+            index: 0,
+        }
+    }
+}
+
+impl<'a, Q: QueryData> Iterator for QueryIter<'a, Q> {
+    type Item = (EntityID, Q::Item<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let columns = self.columns.as_ref()?;
+        while let Some(entity_footprint) = self.storage.footprint_at(self.index) {
+            let index = self.index;
+            self.index += 1;
+            if !self.filter.is_matches(index, entity_footprint) {
+                continue;
+            }
+            let Some(entity_id) = self.storage.entity_at(index) else {
+                continue;
+            };
+            if let Some(item) = Q::fetch(columns, index) {
+                return Some((entity_id, item));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,479 @@
+//! Saving a storage to bytes and restoring it, so a game can be resumed in
+//! another session.
+//!
+//! Only component and resource types registered as persistent are saved,
+//! each under a name that must not change between builds:
+//!
+//! ```ignore
+//! storage.register_persistent::<Health>("health")?;
+//! storage.register_persistent_resource::<Maze>("maze")?;
+//! storage.save_snapshot("game.sav")?;
+//! ...
+//! storage.load_snapshot("game.sav")?;
+//! ```
+//!
+//! Loading replaces every entity, but keeps the non-persistent resources.
+//! Entities come back in the order they were saved, under new ids. The
+//! storage's tick is saved too, so a scene carries on counting steps from
+//! where it was saved.
+//!
+//! File layout, all integers little-endian:
+//!
+//! ```text
+//! header    magic "ISNP", version: u8, tick: u64
+//! types     count: u32, names...
+//! resources count: u32, then per resource: name, size: u32, data
+//! entities  count: u32, then per entity:
+//!             parent: u32 (position in the file + 1, 0 for none)
+//!             components count: u32, then per component:
+//!               type: u32 (position in the types table), size: u32, data
+//! name      length: u32, utf-8
+//! ```
+//!
+//! Sizes let a build skip the types it doesn't know.
+
+use std::{
+    any::{Any, TypeId},
+    collections::VecDeque,
+    time::Duration,
+};
+
+use log::warn;
+
+use crate::{ComponentStorage, EngineError, EngineResult, EntityBundle, EntityID, Size, Vec2f};
+
+const MAGIC: &[u8; 4] = b"ISNP";
+const VERSION: u8 = 1;
+
+/// A component or resource that can be written to a snapshot
+pub trait Persist: Sized {
+    fn save(&self, writer: &mut SnapshotWriter);
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self>;
+}
+
+#[derive(Default)]
+pub struct SnapshotWriter {
+    buffer: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn write_sized(&mut self, data: SnapshotWriter) {
+        (data.buffer.len() as u32).save(self);
+        self.write_bytes(&data.buffer);
+    }
+}
+
+pub struct SnapshotReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn read_bytes(&mut self, count: usize) -> EngineResult<&'a [u8]> {
+        if self.data.len() < count {
+            return Err(damaged_snapshot("unexpected end of data"));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> EngineResult<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    fn read_sized(&mut self) -> EngineResult<SnapshotReader<'a>> {
+        let size = u32::load(self)? as usize;
+        Ok(SnapshotReader {
+            data: self.read_bytes(size)?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+pub(crate) fn damaged_snapshot(reason: impl std::fmt::Display) -> EngineError {
+    EngineError::ResourceParseError(format!("snapshot looks damaged: {reason}"))
+}
+
+/// A type registered as persistent, with its serializer
+pub(crate) struct Persistent {
+    pub(crate) name: &'static str,
+    pub(crate) type_id: TypeId,
+    /// False if the entity, or the storage for a resource, has no value
+    save: fn(&ComponentStorage, EntityID, &mut SnapshotWriter) -> bool,
+    load: fn(&mut SnapshotReader) -> EngineResult<Box<dyn Any>>,
+}
+
+impl Persistent {
+    pub(crate) fn component<T: Any + Persist>(name: &'static str) -> Self {
+        Self {
+            name,
+            type_id: TypeId::of::<T>(),
+            save: |storage, entity_id, writer| {
+                storage
+                    .get::<T>(entity_id)
+                    .map(|value| value.save(writer))
+                    .is_some()
+            },
+            load: load_boxed::<T>,
+        }
+    }
+
+    pub(crate) fn resource<T: Any + Persist>(name: &'static str) -> Self {
+        Self {
+            name,
+            type_id: TypeId::of::<T>(),
+            save: |storage, _, writer| {
+                storage
+                    .resource::<T>()
+                    .map(|value| value.save(writer))
+                    .is_some()
+            },
+            load: load_boxed::<T>,
+        }
+    }
+}
+
+fn load_boxed<T: Any + Persist>(reader: &mut SnapshotReader) -> EngineResult<Box<dyn Any>> {
+    Ok(Box::new(T::load(reader)?))
+}
+
+/// A snapshot read back, ready to replace the storage's content
+pub(crate) struct Snapshot {
+    pub(crate) tick: usize,
+    pub(crate) resources: Vec<(TypeId, Box<dyn Any>)>,
+    /// Parent's position in `entities`, and the components
+    pub(crate) entities: Vec<(Option<usize>, EntityBundle)>,
+}
+
+pub(crate) fn save(
+    storage: &ComponentStorage,
+    components: &[Persistent],
+    resources: &[Persistent],
+) -> Vec<u8> {
+    let mut writer = SnapshotWriter::default();
+    writer.write_bytes(MAGIC);
+    VERSION.save(&mut writer);
+    storage.tick().save(&mut writer);
+
+    (components.len() as u32).save(&mut writer);
+    for persistent in components {
+        persistent.name.to_string().save(&mut writer);
+    }
+
+    let mut saved = Vec::new();
+    for persistent in resources {
+        let mut data = SnapshotWriter::default();
+        if (persistent.save)(storage, EntityID::default(), &mut data) {
+            saved.push((persistent.name, data));
+        }
+    }
+    (saved.len() as u32).save(&mut writer);
+    for (name, data) in saved {
+        name.to_string().save(&mut writer);
+        writer.write_sized(data);
+    }
+
+    let entities = storage.entities().collect::<Vec<_>>();
+    (entities.len() as u32).save(&mut writer);
+    for &entity_id in &entities {
+        let parent = storage
+            .parent(entity_id)
+            .and_then(|parent| entities.iter().position(|x| *x == parent));
+        parent
+            .map_or(0, |position| position as u32 + 1)
+            .save(&mut writer);
+        let mut saved = Vec::new();
+        for (position, persistent) in components.iter().enumerate() {
+            let mut data = SnapshotWriter::default();
+            if (persistent.save)(storage, entity_id, &mut data) {
+                saved.push((position as u32, data));
+            }
+        }
+        (saved.len() as u32).save(&mut writer);
+        for (position, data) in saved {
+            position.save(&mut writer);
+            writer.write_sized(data);
+        }
+    }
+    writer.buffer
+}
+
+pub(crate) fn load(
+    data: &[u8],
+    components: &[Persistent],
+    resources: &[Persistent],
+) -> EngineResult<Snapshot> {
+    let mut reader = SnapshotReader { data };
+    if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(damaged_snapshot("not a snapshot"));
+    }
+    let version = u8::load(&mut reader)?;
+    if version != VERSION {
+        let msg = format!("unsupported snapshot version {version}");
+        return Err(EngineError::ResourceParseError(msg));
+    }
+    let tick = usize::load(&mut reader)?;
+
+    // the types of this build, by their position in the file
+    let mut types = Vec::new();
+    for _ in 0..u32::load(&mut reader)? {
+        let name = String::load(&mut reader)?;
+        let persistent = components.iter().find(|x| x.name == name);
+        if persistent.is_none() {
+            warn!("load_snapshot: unknown component type {name} is skipped");
+        }
+        types.push(persistent);
+    }
+
+    let mut loaded_resources = Vec::new();
+    for _ in 0..u32::load(&mut reader)? {
+        let name = String::load(&mut reader)?;
+        let mut data = reader.read_sized()?;
+        let Some(persistent) = resources.iter().find(|x| x.name == name) else {
+            warn!("load_snapshot: unknown resource type {name} is skipped");
+            continue;
+        };
+        loaded_resources.push((persistent.type_id, load_exact(persistent, &mut data)?));
+    }
+
+    let count = u32::load(&mut reader)? as usize;
+    let mut entities = Vec::new();
+    for _ in 0..count {
+        let parent = match u32::load(&mut reader)? {
+            0 => None,
+            position if position as usize <= count => Some(position as usize - 1),
+            position => return Err(damaged_snapshot(format!("no parent at {position}"))),
+        };
+        let mut bundle = EntityBundle::new();
+        for _ in 0..u32::load(&mut reader)? {
+            let position = u32::load(&mut reader)? as usize;
+            let mut data = reader.read_sized()?;
+            let Some(persistent) = types.get(position) else {
+                return Err(damaged_snapshot(format!("no component type at {position}")));
+            };
+            if let Some(persistent) = persistent {
+                let value = load_exact(persistent, &mut data)?;
+                bundle.components.insert(persistent.type_id, value);
+            }
+        }
+        entities.push((parent, bundle));
+    }
+    if !reader.is_empty() {
+        return Err(damaged_snapshot("trailing data"));
+    }
+    check_parents(&entities)?;
+    Ok(Snapshot {
+        tick,
+        resources: loaded_resources,
+        entities,
+    })
+}
+
+/// Fails if an entity is its own ancestor, which the storage would only find
+/// out about half-way through a restore
+fn check_parents(entities: &[(Option<usize>, EntityBundle)]) -> EngineResult<()> {
+    for (position, _) in entities.iter().enumerate() {
+        let mut parent = entities[position].0;
+        // a chain longer than there are entities has to run in a circle
+        for _ in 0..entities.len() {
+            match parent {
+                Some(ancestor) if ancestor == position => {
+                    return Err(damaged_snapshot(format!("{position} is its own ancestor")));
+                }
+                Some(ancestor) => parent = entities[ancestor].0,
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads a value that has to take up all of `data`
+fn load_exact(persistent: &Persistent, data: &mut SnapshotReader) -> EngineResult<Box<dyn Any>> {
+    let value = (persistent.load)(data)?;
+    if !data.is_empty() {
+        let msg = format!("{} left data unread", persistent.name);
+        return Err(damaged_snapshot(msg));
+    }
+    Ok(value)
+}
+
+impl Persist for bool {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        (*self as u8).save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        match u8::load(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(damaged_snapshot(format!("{value} is not a bool"))),
+        }
+    }
+}
+
+impl Persist for u8 {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_bytes(&[*self]);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(reader.read_array::<1>()?[0])
+    }
+}
+
+impl Persist for u32 {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Self::from_le_bytes(reader.read_array()?))
+    }
+}
+
+impl Persist for i32 {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Self::from_le_bytes(reader.read_array()?))
+    }
+}
+
+impl Persist for u64 {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Self::from_le_bytes(reader.read_array()?))
+    }
+}
+
+/// Saved as a `u64`, so a snapshot reads the same on any platform
+impl Persist for usize {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        (*self as u64).save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let value = u64::load(reader)?;
+        usize::try_from(value).map_err(|_| damaged_snapshot(format!("{value} is out of range")))
+    }
+}
+
+/// Saved in nanoseconds as a `u64`, which lasts some five centuries
+impl Persist for Duration {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        (self.as_nanos() as u64).save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Duration::from_nanos(u64::load(reader)?))
+    }
+}
+
+impl Persist for f32 {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Self::from_le_bytes(reader.read_array()?))
+    }
+}
+
+impl Persist for String {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        (self.len() as u32).save(writer);
+        writer.write_bytes(self.as_bytes());
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let len = u32::load(reader)? as usize;
+        let bytes = reader.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(damaged_snapshot)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.is_some().save(writer);
+        if let Some(value) = self {
+            value.save(writer);
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        if !bool::load(reader)? {
+            return Ok(None);
+        }
+        T::load(reader).map(Some)
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        (self.len() as u32).save(writer);
+        self.iter().for_each(|value| value.save(writer));
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let len = u32::load(reader)?;
+        // no preallocation by length: a damaged one must not trigger a
+        // giant allocation
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(T::load(reader)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<T: Persist> Persist for VecDeque<T> {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        (self.len() as u32).save(writer);
+        self.iter().for_each(|value| value.save(writer));
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Vec::load(reader).map(VecDeque::from)
+    }
+}
+
+impl Persist for Vec2f {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.x.save(writer);
+        self.y.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Self::new(f32::load(reader)?, f32::load(reader)?))
+    }
+}
+
+impl<T: Persist> Persist for Size<T> {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.width.save(writer);
+        self.height.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Self {
+            width: T::load(reader)?,
+            height: T::load(reader)?,
+        })
+    }
+}
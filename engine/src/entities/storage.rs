@@ -2,13 +2,30 @@ use log::warn;
 use std::{
     any::{Any, TypeId},
     cell::{Ref, RefCell, RefMut},
-    collections::{HashMap, HashSet},
-    rc::Rc,
+    collections::HashMap,
+    fmt::Debug,
+    ops::Add,
 };
 
-use crate::{entities::utils::*, EngineError, EngineResult};
+use crate::{
+    assets::{
+        prefab::{self, PrefabComponent},
+        Prefab,
+    },
+    entities::utils::*,
+    EngineError, EngineResult,
+};
 
-use super::footprint::Footprint;
+use super::{
+    column::{AnyColumn, Column, Mut, Slot},
+    commands::CommandBuffer,
+    events::{AnyEventQueue, EventQueue, EventReader},
+    footprint::{Footprint, FootprintFilter},
+    hierarchy::Hierarchy,
+    inspect::{self, DebugView},
+    query::{EntityFilter, QueryData, QueryIter},
+    snapshot::{self, Persist, Persistent},
+};
 
 mod allocator {
     #[derive(Default)]
@@ -55,6 +72,15 @@ mod allocator {
             true
         }
 
+        /// The id of the entity living in slot `index`, if any
+        pub fn id_at(&self, index: usize) -> Option<Index> {
+            let entry = self.entries.get(index)?;
+            entry.is_alive.then_some(Index {
+                index,
+                generation: entry.generation,
+            })
+        }
+
         pub fn is_alive(&self, index: Index) -> bool {
             self.entries
                 .get(index.index)
@@ -88,22 +114,41 @@ mod allocator {
         pub fn index(&self) -> usize {
             self.index
         }
+
+        /// Tells apart the entities that lived in the same slot
+        pub fn generation(&self) -> u64 {
+            self.generation
+        }
     }
 }
 
-pub type ComponentEntry = Rc<RefCell<dyn Any>>;
 pub type EntityID = allocator::Index;
 
 const STORAGE_CAPACITY: usize = 1000;
 
 #[derive(Default)]
 pub struct ComponentStorage {
-    raw: HashMap<TypeId, Vec<Option<ComponentEntry>>>,
+    columns: HashMap<TypeId, Box<dyn AnyColumn>>,
     allocator: allocator::Allocator,
     // type-footprint position mapping
     type_position_map: HashMap<TypeId, usize>,
-    entity_footprint: HashMap<EntityID, Footprint>,
-    indices: HashSet<EntityID>,
+    // indexed by entity index; a dead slot has an empty footprint
+    footprints: Vec<Footprint>,
+    commands: RefCell<CommandBuffer>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    /// The step changes are stamped with
+    tick: usize,
+    /// Per component type, the entities that lost it and when
+    removed: HashMap<TypeId, Vec<(EntityID, usize)>>,
+    events: HashMap<TypeId, RefCell<Box<dyn AnyEventQueue>>>,
+    hierarchy: Hierarchy,
+    /// Component types saved to snapshots, in the order they were registered
+    persistent: Vec<Persistent>,
+    persistent_resources: Vec<Persistent>,
+    /// Component types prefabs can name
+    prefab_components: Vec<PrefabComponent>,
+    /// Component types shown with their values in [`Self::dump`]
+    debug_views: Vec<DebugView>,
 }
 
 impl ComponentStorage {
@@ -113,96 +158,163 @@ impl ComponentStorage {
 
     pub fn register_component<T: Any>(&mut self) -> EngineResult<()> {
         let key = TypeId::of::<T>();
-        if self.raw.contains_key(&key) {
+        if self.columns.contains_key(&key) {
             return Err(EngineError::ComponentAlreadyRegistered);
         }
         let position = self.type_position_map.len();
-        if position >= Footprint::max_items() {
-            return Err(EngineError::ComponentCountOverflow);
-        }
         self.type_position_map.insert(key, position);
-        // the row must cover all already allocated entities to keep
-        // every row indexable by any live entity id
-        let mut row: Vec<Option<ComponentEntry>> = Vec::with_capacity(STORAGE_CAPACITY);
-        row.resize_with(self.allocator.entries_count(), || None);
-        self.raw.insert(key, row);
+        // the column must cover all already allocated entities to keep
+        // every column indexable by any live entity id
+        let column = Column::<T>::new(self.allocator.entries_count(), STORAGE_CAPACITY);
+        self.columns.insert(key, Box::new(column));
         Ok(())
     }
 
     pub fn add_entity(&mut self) -> EntityID {
         let entity_id = self.allocator.allocate();
-        self.raw.iter_mut().for_each(|(_, v)| {
-            if entity_id.index() < v.len() {
-                v[entity_id.index()] = None;
-            } else {
-                // resize instead of push: rows may be shorter than the
-                // allocated index (e.g. component registered late)
-                v.resize_with(entity_id.index() + 1, || None);
-            }
+        let index = entity_id.index();
+        self.columns.values_mut().for_each(|column| {
+            // grow instead of push: columns may be shorter than the
+            // allocated index (e.g. component registered late)
+            column.ensure_len(index + 1);
+            column.clear(index);
         });
-        self.indices.insert(entity_id);
+        if index < self.footprints.len() {
+            self.footprints[index] = Footprint::new();
+        } else {
+            self.footprints.resize_with(index + 1, Footprint::new);
+        }
         entity_id
     }
 
-    pub fn append(&mut self, bundle: &EntityBundle) -> EntityID {
+    pub fn append(&mut self, bundle: EntityBundle) -> EntityID {
         let id = self.add_entity();
-        for (key, value) in bundle.raw.iter() {
-            let Some(row) = self.raw.get_mut(key) else {
-                warn!("failed to get component's row");
+        for (key, value) in bundle.components {
+            let Some(column) = self.columns.get_mut(&key) else {
+                warn!("failed to get component's column");
                 continue;
             };
-            let Some(&position) = self.type_position_map.get(key) else {
+            let Some(&position) = self.type_position_map.get(&key) else {
                 warn!("failed to get component's position");
                 continue;
             };
-            row[id.index()] = Some(value.clone());
-            let footprint = self.entity_footprint.entry(id).or_insert(Footprint::new());
-            footprint.set(position, true);
+            column.put_boxed(id.index(), value, self.tick);
+            self.footprints[id.index()].set(position, true);
         }
         id
     }
 
     pub fn remove_all_entities(&mut self) {
-        for entity_id in self.indices.clone() {
+        for entity_id in self.entities().collect::<Vec<_>>() {
             self.remove_entity(entity_id);
         }
     }
 
+    /// Removes the entity along with its children, their children and so on
     pub fn remove_entity(&mut self, entity_id: EntityID) -> bool {
         if !self.is_alive(entity_id) {
             return false;
         }
-        self.raw.iter_mut().for_each(|(_, v)| {
-            v[entity_id.index()] = None;
-        });
-        self.allocator.deallocate(entity_id);
-        self.entity_footprint.remove(&entity_id);
-        self.indices.remove(&entity_id);
+        for descendant in self.hierarchy.remove(entity_id) {
+            self.despawn(descendant);
+        }
+        self.despawn(entity_id);
         true
     }
 
-    pub fn get<T: Any>(&self, entity_id: EntityID) -> Option<Ref<'_, T>> {
-        let val = self.get_component::<T>(entity_id)?;
-        let Ok(borrowed) = val.try_borrow() else {
-            return None;
-        };
-        let ref_val = Ref::map(borrowed, |item| {
-            item.downcast_ref::<T>()
-                .expect("[ComponentStorage] Failed to downcast_ref")
-        });
-        Some(ref_val)
+    fn despawn(&mut self, entity_id: EntityID) {
+        let index = entity_id.index();
+        for (key, column) in self.columns.iter_mut() {
+            if column.clear(index) {
+                let removed = self.removed.entry(*key).or_default();
+                removed.push((entity_id, self.tick));
+            }
+        }
+        self.allocator.deallocate(entity_id);
+        self.footprints[index] = Footprint::new();
     }
 
-    pub fn get_mut<T: Any>(&self, entity_id: EntityID) -> Option<RefMut<'_, T>> {
-        let val = self.get_component::<T>(entity_id)?;
-        let Ok(borrowed) = val.try_borrow_mut() else {
-            return None;
+    /// Attaches `child` to `parent`, or detaches it from its parent with
+    /// `None`. An entity has one parent at most; attaching it again moves it.
+    pub fn set_parent(&mut self, child: EntityID, parent: Option<EntityID>) -> EngineResult<()> {
+        if !self.is_alive(child) {
+            return Err(EngineError::EntityNotAlive(child));
+        }
+        let Some(parent) = parent else {
+            self.hierarchy.detach(child);
+            return Ok(());
         };
-        let ref_val = RefMut::map(borrowed, |item| {
-            item.downcast_mut::<T>()
-                .expect("[ComponentStorage] Failed to downcast_mut")
-        });
-        Some(ref_val)
+        if !self.is_alive(parent) {
+            return Err(EngineError::EntityNotAlive(parent));
+        }
+        if self.hierarchy.is_ancestor(child, parent) {
+            return Err(EngineError::HierarchyCycle(child));
+        }
+        self.hierarchy.attach(child, parent);
+        Ok(())
+    }
+
+    pub fn parent(&self, child: EntityID) -> Option<EntityID> {
+        self.hierarchy.parent(child)
+    }
+
+    /// In the order they were attached
+    pub fn children(&self, parent: EntityID) -> &[EntityID] {
+        self.hierarchy.children(parent)
+    }
+
+    /// The entity's `P` added to those of all its ancestors: a child's
+    /// position-like component is an offset from its parent's. Ancestors
+    /// without a `P` are passed over. `None` if the entity has no `P`.
+    pub fn world_position<P>(&self, entity_id: EntityID) -> Option<P>
+    where
+        P: Any + Copy + Add<Output = P>,
+    {
+        let mut position = *self.get::<P>(entity_id)?;
+        let mut current = self.parent(entity_id);
+        while let Some(parent) = current {
+            if let Some(offset) = self.get::<P>(parent) {
+                position = position + *offset;
+            }
+            current = self.parent(parent);
+        }
+        Some(position)
+    }
+
+    /// Queue of structural changes to make once nothing borrows the
+    /// storage, see [`Self::apply_commands`]
+    pub fn commands(&self) -> RefMut<'_, CommandBuffer> {
+        self.commands.borrow_mut()
+    }
+
+    /// Runs the queued commands in the order they were queued. Commands
+    /// queued meanwhile run in the same call, after the others.
+    ///
+    /// Stops at the first command that fails; the rest are dropped.
+    pub fn apply_commands(&mut self) -> EngineResult<()> {
+        loop {
+            let commands = self.commands.get_mut().take();
+            if commands.is_empty() {
+                return Ok(());
+            }
+            for command in commands {
+                command.apply(self)?;
+            }
+        }
+    }
+
+    pub fn get<T: Any>(&self, entity_id: EntityID) -> Option<Ref<'_, T>> {
+        self.get_component::<T>(entity_id)?
+            .value()
+            .try_borrow()
+            .ok()
+    }
+
+    /// Writing through the result marks the component changed, see
+    /// [`Self::is_changed_since`]
+    pub fn get_mut<T: Any>(&self, entity_id: EntityID) -> Option<Mut<'_, T>> {
+        self.get_component::<T>(entity_id)?
+            .try_borrow_mut(self.tick)
     }
 
     /// Attaches `value` to the entity, or drops the component when `value` is
@@ -217,46 +329,108 @@ impl ComponentStorage {
             return Err(EngineError::EntityNotAlive(entity_id));
         }
         let key = TypeId::of::<T>();
-        let Some(row) = self.raw.get_mut(&key) else {
-            return Err(EngineError::ComponentNotRegistered);
-        };
-        let footprint = self
-            .entity_footprint
-            .entry(entity_id)
-            .or_insert(Footprint::new());
         let Some(&position) = self.type_position_map.get(&key) else {
             return Err(EngineError::ComponentNotRegistered);
         };
-        row[entity_id.index()] = if let Some(x) = value {
-            footprint.set(position, true);
-            Some(Rc::new(RefCell::new(x)))
-        } else {
-            footprint.set(position, false);
-            None
+        let tick = self.tick;
+        let Some(column) = self.column_mut::<T>() else {
+            return Err(EngineError::ComponentNotRegistered);
         };
+        let is_set = value.is_some();
+        let existed = column.put(entity_id.index(), value, tick);
+        if existed && !is_set {
+            self.removed.entry(key).or_default().push((entity_id, tick));
+        }
+        self.footprints[entity_id.index()].set(position, is_set);
         Ok(())
     }
 
     pub fn has_component<T: Any>(&self, entity_id: EntityID) -> bool {
-        let Some(footprint) = self.entity_footprint.get(&entity_id) else {
+        if !self.is_alive(entity_id) {
             return false;
-        };
+        }
         let key = TypeId::of::<T>();
         let Some(&position) = self.type_position_map.get(&key) else {
             return false;
         };
-        footprint.get(position)
+        self.footprints[entity_id.index()].get(position)
     }
 
-    fn get_component<T: Any>(&self, entity_id: EntityID) -> Option<&ComponentEntry> {
+    fn get_component<T: Any>(&self, entity_id: EntityID) -> Option<&Slot<T>> {
         if !self.is_alive(entity_id) {
             return None;
         }
-        let key = TypeId::of::<T>();
-        self.raw
-            .get(&key)
-            .and_then(|arr| arr.get(entity_id.index()))
-            .and_then(|item| item.as_ref())
+        self.column::<T>()?.slot(entity_id.index())
+    }
+
+    /// The step that additions, changes and removals are stamped with
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    /// Moves the storage on to step `tick`; a scene does that once per step.
+    ///
+    /// Removals are kept for the current and the previous step only: a
+    /// system asking every step for what was removed since its last run
+    /// sees each removal, and the log doesn't grow with the session.
+    pub fn set_tick(&mut self, tick: usize) {
+        self.tick = tick;
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, at)| at + 1 >= tick);
+        }
+        for queue in self.events.values_mut() {
+            queue.get_mut().prune(tick);
+        }
+    }
+
+    /// True if the entity's `T` was attached on step `tick` or later.
+    /// Replacing a component doesn't count as adding it.
+    pub fn is_added_since<T: Any>(&self, entity_id: EntityID, tick: usize) -> bool {
+        self.get_component::<T>(entity_id)
+            .is_some_and(|slot| slot.added() >= tick)
+    }
+
+    /// True if the entity's `T` was attached, replaced or written through
+    /// [`Self::get_mut`] on step `tick` or later
+    pub fn is_changed_since<T: Any>(&self, entity_id: EntityID, tick: usize) -> bool {
+        self.get_component::<T>(entity_id)
+            .is_some_and(|slot| slot.changed() >= tick)
+    }
+
+    /// The entities that lost their `T` on step `tick` or later, either by
+    /// `set(id, None)` or by being removed themselves. Only the current and
+    /// the previous step are kept, see [`Self::set_tick`].
+    pub fn removed_since<T: Any>(&self, tick: usize) -> Vec<EntityID> {
+        let Some(removed) = self.removed.get(&TypeId::of::<T>()) else {
+            return Vec::new();
+        };
+        removed
+            .iter()
+            .filter(|(_, at)| *at >= tick)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub(crate) fn column<T: Any>(&self) -> Option<&Column<T>> {
+        self.columns
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<Column<T>>()
+    }
+
+    fn column_mut<T: Any>(&mut self) -> Option<&mut Column<T>> {
+        self.columns
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+    }
+
+    pub(crate) fn footprint_at(&self, index: usize) -> Option<&Footprint> {
+        self.footprints.get(index)
+    }
+
+    pub(crate) fn entity_at(&self, index: usize) -> Option<EntityID> {
+        self.allocator.id_at(index)
     }
 
     pub fn is_alive(&self, entity_id: EntityID) -> bool {
@@ -271,48 +445,322 @@ impl ComponentStorage {
         self.len() == 0
     }
 
-    /// Returns None if any of the query types isn't registered:
-    /// such a query can't be matched by any entity
-    fn footprint(&self, types: &HashSet<TypeId>) -> Option<Footprint> {
-        let mut footprint = Footprint::new();
-        for item in types {
+    /// Makes `E` an event type systems can send and read, see
+    /// [`crate::entities::events`]. Registering it again changes nothing.
+    pub fn register_event<E: Any + Clone>(&mut self) {
+        self.events
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| RefCell::new(Box::new(EventQueue::<E>::new())));
+    }
+
+    /// Queues `event` for every reader of `E`. Works while the storage is
+    /// borrowed, e.g. from inside a query loop.
+    pub fn send<E: Any + Clone>(&self, event: E) -> EngineResult<()> {
+        let Some(queue) = self.events.get(&TypeId::of::<E>()) else {
+            return Err(EngineError::EventNotRegistered);
+        };
+        let mut queue = queue.borrow_mut();
+        let Some(queue) = queue.as_any_mut().downcast_mut::<EventQueue<E>>() else {
+            return Err(EngineError::EventNotRegistered);
+        };
+        queue.send(event, self.tick);
+        Ok(())
+    }
+
+    /// The events of type `E` sent since `reader` last read, oldest first
+    pub fn read<E: Any + Clone>(&self, reader: &mut EventReader<E>) -> Vec<E> {
+        let Some(queue) = self.events.get(&TypeId::of::<E>()) else {
+            warn!("read: event type isn't registered");
+            return Vec::new();
+        };
+        let queue = queue.borrow();
+        let Some(queue) = queue.as_any().downcast_ref::<EventQueue<E>>() else {
+            return Vec::new();
+        };
+        queue.read(reader)
+    }
+
+    /// Lets `T` components be saved to snapshots, see
+    /// [`crate::entities::snapshot`]. `name` identifies the type in the
+    /// file, so it must stay the same between builds.
+    pub fn register_persistent<T: Any + Persist>(
+        &mut self,
+        name: &'static str,
+    ) -> EngineResult<()> {
+        if !self.columns.contains_key(&TypeId::of::<T>()) {
+            return Err(EngineError::ComponentNotRegistered);
+        }
+        if self.persistent.iter().any(|x| x.name == name) {
+            return Err(EngineError::ComponentAlreadyRegistered);
+        }
+        self.persistent.push(Persistent::component::<T>(name));
+        Ok(())
+    }
+
+    /// Lets the `T` resource be saved to snapshots, like
+    /// [`Self::register_persistent`]
+    pub fn register_persistent_resource<T: Any + Persist>(
+        &mut self,
+        name: &'static str,
+    ) -> EngineResult<()> {
+        if self.persistent_resources.iter().any(|x| x.name == name) {
+            return Err(EngineError::ComponentAlreadyRegistered);
+        }
+        self.persistent_resources
+            .push(Persistent::resource::<T>(name));
+        Ok(())
+    }
+
+    /// Lets prefabs give entities a `T` component: the prefab entry `name`
+    /// is turned into one by `parse`, see [`Prefab`]
+    pub fn register_prefab_component<T: Any>(
+        &mut self,
+        name: &'static str,
+        parse: fn(&str) -> EngineResult<T>,
+    ) -> EngineResult<()> {
+        if !self.columns.contains_key(&TypeId::of::<T>()) {
+            return Err(EngineError::ComponentNotRegistered);
+        }
+        if self.prefab_components.iter().any(|x| x.name == name) {
+            return Err(EngineError::ComponentAlreadyRegistered);
+        }
+        self.prefab_components
+            .push(PrefabComponent::new(name, parse));
+        Ok(())
+    }
+
+    /// The components `prefab` describes, to be spawned as they are or
+    /// with more put on top
+    pub fn prefab_bundle(&self, prefab: &Prefab) -> EngineResult<EntityBundle> {
+        prefab::bundle(prefab, &self.prefab_components)
+    }
+
+    /// The tick, the persistent resources and every entity's persistent
+    /// components
+    pub fn snapshot(&self) -> Vec<u8> {
+        snapshot::save(self, &self.persistent, &self.persistent_resources)
+    }
+
+    /// Replaces all entities with those of the snapshot, and the persistent
+    /// resources it has. Nothing changes if the snapshot can't be read.
+    ///
+    /// The loaded components count as added on the snapshot's tick, which
+    /// becomes the current one. Removals and pending events are forgotten.
+    pub fn restore(&mut self, data: &[u8]) -> EngineResult<()> {
+        let snapshot = snapshot::load(data, &self.persistent, &self.persistent_resources)?;
+        self.remove_all_entities();
+        self.removed.clear();
+        for queue in self.events.values_mut() {
+            queue.get_mut().clear();
+        }
+        self.tick = snapshot.tick;
+        for (key, value) in snapshot.resources {
+            self.resources.insert(key, RefCell::new(value));
+        }
+        let mut ids = Vec::with_capacity(snapshot.entities.len());
+        let mut parents = Vec::with_capacity(snapshot.entities.len());
+        for (parent, bundle) in snapshot.entities {
+            ids.push(self.append(bundle));
+            parents.push(parent);
+        }
+        for (child, parent) in ids.iter().zip(parents) {
+            if let Some(parent) = parent {
+                self.set_parent(*child, Some(ids[parent]))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes [`Self::snapshot`] to `path`, replacing any file already there
+    pub fn save_snapshot(&self, path: &str) -> EngineResult<()> {
+        std::fs::write(path, self.snapshot()).map_err(|e| {
+            EngineError::FileAccessError(format!("failed to write snapshot {path}: {e}"))
+        })
+    }
+
+    /// [`Self::restore`] from the file at `path`
+    pub fn load_snapshot(&mut self, path: &str) -> EngineResult<()> {
+        let data = std::fs::read(path).map_err(|e| {
+            EngineError::FileAccessError(format!("failed to read snapshot {path}: {e}"))
+        })?;
+        self.restore(&data)
+    }
+
+    /// Lets [`Self::inspect`] and [`Self::dump`] show `T` components with
+    /// their `Debug` rendering instead of only their name
+    pub fn register_debug<T: Any + Debug>(&mut self) -> EngineResult<()> {
+        let type_id = TypeId::of::<T>();
+        if !self.columns.contains_key(&type_id) {
+            return Err(EngineError::ComponentNotRegistered);
+        }
+        if self.debug_views.iter().any(|x| x.type_id == type_id) {
+            return Err(EngineError::ComponentAlreadyRegistered);
+        }
+        self.debug_views.push(DebugView::new::<T>());
+        Ok(())
+    }
+
+    /// The entity's component types, without their module paths, in the
+    /// order they were registered
+    pub fn component_names(&self, entity_id: EntityID) -> Vec<String> {
+        self.inspect(entity_id)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// The entity's components by type name, with their `Debug` rendering
+    /// where one is registered, see [`Self::register_debug`]
+    pub fn inspect(&self, entity_id: EntityID) -> Vec<(String, Option<String>)> {
+        if !self.is_alive(entity_id) {
+            return Vec::new();
+        }
+        let footprint = &self.footprints[entity_id.index()];
+        let mut types = self
+            .type_position_map
+            .iter()
+            .filter(|(_, position)| footprint.get(**position))
+            .collect::<Vec<_>>();
+        types.sort_by_key(|(_, position)| **position);
+        types
+            .into_iter()
+            .map(|(type_id, _)| {
+                let name = self.columns[type_id].type_name();
+                let value = self
+                    .debug_views
+                    .iter()
+                    .find(|x| x.type_id == *type_id)
+                    .and_then(|view| view.format(self, entity_id));
+                (inspect::short_type_name(name), value)
+            })
+            .collect()
+    }
+
+    /// Every live entity with its parent and components, as text
+    pub fn dump(&self) -> String {
+        inspect::dump(self)
+    }
+
+    /// Writes [`Self::dump`] to the file at `path`
+    pub fn dump_to_file(&self, path: &str) -> EngineResult<()> {
+        std::fs::write(path, self.dump())
+            .map_err(|e| EngineError::FileAccessError(format!("failed to write dump {path}: {e}")))
+    }
+
+    /// Stores `value` as the scene's one `T`, returning the one it replaces.
+    ///
+    /// A resource belongs to no entity: it is scene-wide state such as the
+    /// level's map, its RNG or the score. Resource types need no
+    /// registration, and removing entities leaves resources alone.
+    pub fn insert_resource<T: Any>(&mut self, value: T) -> Option<T> {
+        let previous = self
+            .resources
+            .insert(TypeId::of::<T>(), RefCell::new(Box::new(value)))?;
+        previous.into_inner().downcast::<T>().ok().map(|x| *x)
+    }
+
+    pub fn remove_resource<T: Any>(&mut self) -> Option<T> {
+        let cell = self.resources.remove(&TypeId::of::<T>())?;
+        cell.into_inner().downcast::<T>().ok().map(|x| *x)
+    }
+
+    pub fn has_resource<T: Any>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    /// None if there is no `T`, or it is borrowed mutably elsewhere
+    pub fn resource<T: Any>(&self) -> Option<Ref<'_, T>> {
+        let value = self.resources.get(&TypeId::of::<T>())?.try_borrow().ok()?;
+        Ref::filter_map(value, |x| x.downcast_ref::<T>()).ok()
+    }
+
+    /// None if there is no `T`, or it is borrowed elsewhere
+    pub fn resource_mut<T: Any>(&self) -> Option<RefMut<'_, T>> {
+        let value = self
+            .resources
+            .get(&TypeId::of::<T>())?
+            .try_borrow_mut()
+            .ok()?;
+        RefMut::filter_map(value, |x| x.downcast_mut::<T>()).ok()
+    }
+
+    /// Returns None if any of the required types isn't registered: such a
+    /// query can't be matched by any entity. An unregistered excluded type
+    /// excludes nothing.
+    fn filter<'a>(
+        &self,
+        required: impl IntoIterator<Item = &'a TypeId>,
+        query: &Query,
+    ) -> Option<EntityFilter<'_>> {
+        let mut footprint = FootprintFilter::default();
+        for item in required {
+            let &pos = self.type_position_map.get(item)?;
+            footprint.required.set(pos, true);
+        }
+        for item in &query.types {
             let &pos = self.type_position_map.get(item)?;
-            footprint.set(pos, true);
+            footprint.required.set(pos, true);
+        }
+        for item in &query.excluded {
+            if let Some(&pos) = self.type_position_map.get(item) {
+                footprint.excluded.set(pos, true);
+            }
+        }
+        let mut filter = EntityFilter::new(footprint);
+        for (item, &tick) in &query.added_since {
+            filter.added_since(self.columns.get(item)?.as_ref(), tick);
         }
-        Some(footprint)
+        for (item, &tick) in &query.changed_since {
+            filter.changed_since(self.columns.get(item)?.as_ref(), tick);
+        }
+        Some(filter)
+    }
+
+    /// Iterates over the entities that have every component `Q` asks for,
+    /// yielding each entity's id with its components borrowed as requested:
+    /// `storage.iter::<(&Position, &mut Movement)>()`.
+    pub fn iter<Q: QueryData>(&self) -> QueryIter<'_, Q> {
+        self.iter_filtered(&Query::new())
+    }
+
+    /// Like [`Self::iter`], but the entities must match `query` too, which
+    /// may ask for components that aren't fetched or exclude some.
+    pub fn iter_filtered<Q: QueryData>(&self, query: &Query) -> QueryIter<'_, Q> {
+        let mut types = Vec::new();
+        Q::component_types(&mut types);
+        let Some(filter) = self.filter(&types, query) else {
+            warn!("iter: query contains unregistered component type");
+            return QueryIter::new(self, None, EntityFilter::default());
+        };
+        QueryIter::new(self, Q::columns(self), filter)
+    }
+
+    /// Live entities in index order
+    pub fn entities(&self) -> impl Iterator<Item = EntityID> + '_ {
+        (0..self.footprints.len()).filter_map(|index| self.allocator.id_at(index))
     }
 
+    /// The live entity with the lowest index that matches `query`.
     pub fn fetch_first_entity(&self, query: &Query) -> Option<EntityID> {
-        let Some(query_footprint) = self.footprint(&query.types) else {
+        let Some(filter) = self.filter(&[], query) else {
             warn!("fetch_first: query contains unregistered component type");
             return None;
         };
-        for entity_id in &self.indices {
-            let Some(entity_footprint) = self.entity_footprint.get(entity_id) else {
-                continue;
-            };
-            if query_footprint.is_matches(entity_footprint) {
-                return Some(*entity_id);
-            }
-        }
-        None
+        self.entities()
+            .find(|id| filter.is_matches(id.index(), &self.footprints[id.index()]))
     }
 
+    /// The live entities that match `query`, in index order -- the order
+    /// [`Self::iter`] visits them in too. The order depends only on what
+    /// was spawned and removed, never on hashing, so a simulation that
+    /// walks the result plays out the same way every run.
     pub fn fetch_entities(&self, query: &Query) -> Vec<EntityID> {
-        let mut entities = Vec::new();
-        let Some(query_footprint) = self.footprint(&query.types) else {
+        let Some(filter) = self.filter(&[], query) else {
             warn!("fetch: query contains unregistered component type");
-            return entities;
+            return Vec::new();
         };
-        for entity_id in &self.indices {
-            let Some(entity_footprint) = self.entity_footprint.get(entity_id) else {
-                warn!("fetch: footprint isn't registered for entity");
-                continue;
-            };
-            if query_footprint.is_matches(entity_footprint) {
-                entities.push(*entity_id);
-            }
-        }
-        entities
+        self.entities()
+            .filter(|id| filter.is_matches(id.index(), &self.footprints[id.index()]))
+            .collect()
     }
 }
@@ -1,15 +1,14 @@
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
     collections::{HashMap, HashSet},
-    rc::Rc,
 };
 
-use crate::{ComponentEntry, ComponentStorage, EngineError, EngineResult, EntityID};
+use crate::{ComponentStorage, EngineError, EngineResult, EntityID};
 
+/// Components to spawn an entity with, see [`ComponentStorage::append`]
 #[derive(Default)]
 pub struct EntityBundle {
-    pub raw: HashMap<TypeId, ComponentEntry>,
+    pub(crate) components: HashMap<TypeId, Box<dyn Any>>,
 }
 
 impl EntityBundle {
@@ -19,7 +18,7 @@ impl EntityBundle {
 
     pub fn put<T: Any>(mut self, value: T) -> Self {
         let key = TypeId::of::<T>();
-        self.raw.insert(key, Rc::new(RefCell::new(value)));
+        self.components.insert(key, Box::new(value));
         self
     }
 }
@@ -27,6 +26,11 @@ impl EntityBundle {
 #[derive(Default)]
 pub struct Query {
     pub types: HashSet<TypeId>,
+    pub excluded: HashSet<TypeId>,
+    /// Component types that must have been added on the given step or later
+    pub added_since: HashMap<TypeId, usize>,
+    /// Component types that must have changed on the given step or later
+    pub changed_since: HashMap<TypeId, usize>,
 }
 
 impl Query {
@@ -39,6 +43,31 @@ impl Query {
         self.types.insert(type_id);
         self
     }
+
+    /// Entities having a `T` don't match
+    pub fn without<T: Any>(mut self) -> Self {
+        let type_id = TypeId::of::<T>();
+        self.excluded.insert(type_id);
+        self
+    }
+
+    /// Only entities that got their `T` on step `tick` or later match, see
+    /// [`ComponentStorage::is_added_since`]
+    pub fn added_since<T: Any>(mut self, tick: usize) -> Self {
+        let type_id = TypeId::of::<T>();
+        self.types.insert(type_id);
+        self.added_since.insert(type_id, tick);
+        self
+    }
+
+    /// Only entities whose `T` changed on step `tick` or later match, see
+    /// [`ComponentStorage::is_changed_since`]
+    pub fn changed_since<T: Any>(mut self, tick: usize) -> Self {
+        let type_id = TypeId::of::<T>();
+        self.types.insert(type_id);
+        self.changed_since.insert(type_id, tick);
+        self
+    }
 }
 
 pub fn fetch_first<T: Any>(storage: &ComponentStorage) -> Option<EntityID> {
@@ -6,7 +6,12 @@ pub mod runloop;
 pub mod settings;
 
 pub use assets::AssetManager;
-pub use entities::storage::{ComponentEntry, ComponentStorage, EntityID};
+pub use entities::column::Mut;
+pub use entities::commands::CommandBuffer;
+pub use entities::events::EventReader;
+pub use entities::query::{QueryData, QueryIter};
+pub use entities::snapshot::{Persist, SnapshotReader, SnapshotWriter};
+pub use entities::storage::{ComponentStorage, EntityID};
 pub use entities::utils::{
     cleanup_component, fetch_first, refresh_cached_entity, EntityBundle, Query,
 };
@@ -14,16 +19,18 @@ pub use geometry::{
     ray_cast, ray_cast_dir, Float, RayCastResult, Rectangle, Size, SizeFloat, SizeU32, Vec2f,
     RAY_CASTER_TOL,
 };
-pub use runloop::{game_scene, systems, world, SceneID};
-pub use settings::{AudioSettings, EngineSettings, WindowSettings};
+pub use runloop::{game_scene, headless, replay, schedule, systems, time, window, world, SceneID};
+pub use settings::{AudioSettings, EngineSettings, TimingSettings, WindowSettings};
 
 #[derive(Debug)]
 pub enum EngineError {
     ComponentNotRegistered,
     ComponentAlreadyRegistered,
-    ComponentCountOverflow,
     ComponentNotFound(String),
+    EventNotRegistered,
     EntityNotAlive(EntityID),
+    HierarchyCycle(EntityID),
+    InvalidSchedule(String),
     TextureNotFound(String),
     SceneNotFound,
     FileAccessError(String),
@@ -54,9 +61,11 @@ impl std::fmt::Display for EngineError {
         match self {
             ComponentNotRegistered => write!(f, "component type is not registered"),
             ComponentAlreadyRegistered => write!(f, "component type is already registered"),
-            ComponentCountOverflow => write!(f, "too many registered component types"),
             ComponentNotFound(name) => write!(f, "component not found: {name}"),
+            EventNotRegistered => write!(f, "event type is not registered"),
             EntityNotAlive(id) => write!(f, "entity {} is not alive", id.index()),
+            HierarchyCycle(id) => write!(f, "entity {} can't be its own ancestor", id.index()),
+            InvalidSchedule(msg) => write!(f, "invalid system schedule: {msg}"),
             TextureNotFound(name) => write!(f, "texture not found: {name}"),
             SceneNotFound => write!(f, "scene not found"),
             FileAccessError(path) => write!(f, "failed to access file: {path}"),
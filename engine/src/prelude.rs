@@ -9,6 +9,7 @@
 
 pub use sdl2::keyboard::Keycode;
 pub use sdl2::mixer::{AudioFormat, AUDIO_S16LSB, DEFAULT_CHANNELS};
+pub use sdl2::mouse::MouseButton;
 pub use sdl2::pixels::Color;
 pub use sdl2::rect::{Point, Rect};
 pub use sdl2::render::BlendMode;
@@ -1,15 +1,33 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{any::Any, cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
 
 use crate::{systems::InputEvent, AssetManager, ComponentStorage, EngineResult, SceneID, SizeU32};
 
+use super::schedule::{self, SystemConfig};
 use super::systems::{
     GameControlSystem, GameRendererSystem, GameSoundSystem, GameSystem, GameSystemCommand,
     RendererLayersPtr, SoundEffect,
 };
+use super::time::Time;
 
-#[derive(Clone, Copy)]
+/// What happens to a scene, sent to every one of its systems
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SceneEvent {
-    Change,
+    /// The scene became the current one: at startup, switched to, or pushed
+    /// over the current one
+    Enter,
+    /// The scene was switched away from or popped; it won't run or be drawn
+    /// until it is entered again
+    Exit,
+    /// A scene was pushed over this one, which stops running but is still
+    /// drawn
+    Suspend,
+    /// The scene pushed over this one was popped
+    Resume,
+    /// The world is stopping; sent to every scene, on the stack or not
+    Shutdown,
+    /// The window now has this many pixels to draw on; sent to every scene,
+    /// on the stack or not, after its renderers were laid out again
+    Resized(SizeU32),
 }
 
 pub type SceneParameters = HashMap<String, String>;
@@ -17,11 +35,15 @@ pub type SceneParameters = HashMap<String, String>;
 pub struct GameScene {
     id: SceneID,
     storage: ComponentStorage,
-    frames: usize,
-    common_systems: Vec<Rc<RefCell<dyn GameSystem>>>,
-    control_system: Rc<RefCell<dyn GameControlSystem>>,
-    renderer_system: Rc<RefCell<dyn GameRendererSystem>>,
-    sound_system: Option<Rc<RefCell<dyn GameSoundSystem>>>,
+    common_systems: Vec<(Rc<RefCell<dyn GameSystem>>, SystemConfig)>,
+    /// Positions in `common_systems`, in the order they run; built on
+    /// first use after a system is added
+    schedule: Option<Vec<usize>>,
+    control_systems: Vec<Rc<RefCell<dyn GameControlSystem>>>,
+    renderer_systems: Vec<Rc<RefCell<dyn GameRendererSystem>>>,
+    sound_systems: Vec<Rc<RefCell<dyn GameSoundSystem>>>,
+    /// What the renderers drew, when there is more than one
+    merged_layers: RendererLayersPtr,
 }
 
 impl GameScene {
@@ -34,11 +56,12 @@ impl GameScene {
         Self {
             id,
             storage,
-            frames: 0,
             common_systems: Default::default(),
-            control_system: Rc::new(RefCell::new(control_system)),
-            renderer_system: Rc::new(RefCell::new(renderer_system)),
-            sound_system: Default::default(),
+            schedule: None,
+            control_systems: vec![Rc::new(RefCell::new(control_system))],
+            renderer_systems: vec![Rc::new(RefCell::new(renderer_system))],
+            sound_systems: Default::default(),
+            merged_layers: Default::default(),
         }
     }
 
@@ -46,12 +69,54 @@ impl GameScene {
         self.id
     }
 
+    /// Adds a system that runs after the ones added before it, unless their
+    /// constraints say otherwise
     pub fn add_system(&mut self, system: impl GameSystem + 'static) {
-        self.common_systems.push(Rc::new(RefCell::new(system)));
+        self.add_system_with(system, SystemConfig::new());
     }
 
+    /// Adds a system with a label, ordering constraints and run conditions,
+    /// see [`crate::schedule`]
+    pub fn add_system_with(&mut self, system: impl GameSystem + 'static, config: SystemConfig) {
+        self.common_systems
+            .push((Rc::new(RefCell::new(system)), config));
+        self.schedule = None;
+    }
+
+    /// The systems in the order they run, by position of addition
+    pub fn schedule(&mut self) -> EngineResult<&[usize]> {
+        if self.schedule.is_none() {
+            let configs = self
+                .common_systems
+                .iter()
+                .map(|(_, config)| config)
+                .collect::<Vec<_>>();
+            self.schedule = Some(schedule::build(&configs)?);
+        }
+        Ok(self.schedule.as_deref().unwrap_or_default())
+    }
+
+    /// See [`ComponentStorage::insert_resource`]
+    pub fn insert_resource<T: Any>(&mut self, value: T) -> Option<T> {
+        self.storage.insert_resource(value)
+    }
+
+    /// Adds a control system that gets the input after the ones added
+    /// before it
+    pub fn add_control_system(&mut self, system: impl GameControlSystem + 'static) {
+        self.control_systems.push(Rc::new(RefCell::new(system)));
+    }
+
+    /// Adds a renderer that draws over the ones added before it; effects on
+    /// the depth layer are ordered by depth whichever renderer made them
+    pub fn add_renderer_system(&mut self, system: impl GameRendererSystem + 'static) {
+        self.renderer_systems.push(Rc::new(RefCell::new(system)));
+    }
+
+    /// Adds a sound system whose effects play after those of the ones added
+    /// before it
     pub fn add_sound_system(&mut self, system: impl GameSoundSystem + 'static) {
-        self.sound_system = Some(Rc::new(RefCell::new(system)));
+        self.sound_systems.push(Rc::new(RefCell::new(system)));
     }
 
     pub fn setup_systems(
@@ -59,28 +124,77 @@ impl GameScene {
         asset_manager: &AssetManager,
         window_size: SizeU32,
     ) -> EngineResult<()> {
-        for elem in &self.common_systems {
-            let mut system = elem.borrow_mut();
+        for index in self.schedule()?.to_vec() {
+            let mut system = self.common_systems[index].0.borrow_mut();
             system.setup(&mut self.storage, asset_manager)?;
+            self.storage.apply_commands()?;
         }
-        self.control_system.borrow_mut().setup(&self.storage)?;
-        self.renderer_system
-            .borrow_mut()
-            .setup(&self.storage, asset_manager, window_size)?;
-        if let Some(elem) = &self.sound_system {
-            let mut system = elem.borrow_mut();
-            system.setup(&self.storage, asset_manager)?;
+        for system in &self.control_systems {
+            system.borrow_mut().setup(&self.storage)?;
+        }
+        for system in &self.renderer_systems {
+            system
+                .borrow_mut()
+                .setup(&self.storage, asset_manager, window_size)?;
+        }
+        for system in &self.sound_systems {
+            system.borrow_mut().setup(&self.storage, asset_manager)?;
         }
         Ok(())
     }
 
-    pub fn send_event(&mut self, event: SceneEvent, params: &SceneParameters) -> EngineResult<()> {
-        for system in self.common_systems.iter() {
+    /// Lays the renderers out for the new window size, then tells every
+    /// system. Returns the sound systems' effects.
+    pub fn resize(
+        &mut self,
+        asset_manager: &AssetManager,
+        window_size: SizeU32,
+    ) -> EngineResult<Vec<SoundEffect>> {
+        for system in &self.renderer_systems {
+            system
+                .borrow_mut()
+                .resize(&self.storage, asset_manager, window_size)?;
+        }
+        self.send_event(
+            SceneEvent::Resized(window_size),
+            &SceneParameters::default(),
+        )
+    }
+
+    /// Tells every system of the scene, the game systems first in schedule
+    /// order, then the control, renderer and sound systems. Returns the
+    /// sound systems' effects.
+    pub fn send_event(
+        &mut self,
+        event: SceneEvent,
+        params: &SceneParameters,
+    ) -> EngineResult<Vec<SoundEffect>> {
+        for index in self.schedule()?.to_vec() {
+            self.common_systems[index].0.borrow_mut().on_scene_event(
+                &mut self.storage,
+                event,
+                params,
+            )?;
+            self.storage.apply_commands()?;
+        }
+        for system in &self.control_systems {
             system
                 .borrow_mut()
                 .on_scene_event(&mut self.storage, event, params)?;
+            self.storage.apply_commands()?;
         }
-        Ok(())
+        for system in &self.renderer_systems {
+            system
+                .borrow_mut()
+                .on_scene_event(&self.storage, event, params)?;
+        }
+        let mut effects = Vec::new();
+        for system in &self.sound_systems {
+            let mut system = system.borrow_mut();
+            effects.extend(system.on_scene_event(&mut self.storage, event, params)?);
+            self.storage.apply_commands()?;
+        }
+        Ok(effects)
     }
 
     /// Advances the scene by one simulation step.
@@ -88,46 +202,109 @@ impl GameScene {
     /// `frames` counts steps, not rendered images, so every frame-based
     /// deadline in the game systems measures the same amount of real time
     /// regardless of how often the scene is drawn.
+    ///
+    /// Systems run in schedule order, those whose run conditions aren't met
+    /// skipped. Commands the systems queue in the storage are applied right
+    /// after the system that queued them, before the next one runs.
+    ///
+    /// Changes to components are stamped with the step count, which the
+    /// storage moves on to once every system has run; input pushed before
+    /// the next update counts towards that next step. The storage's tick is
+    /// the scene's step count, so restoring a snapshot restores both.
     pub fn update(
         &mut self,
         delta_time: f32,
         asset_manager: &AssetManager,
     ) -> EngineResult<Vec<GameSystemCommand>> {
+        let frames = self.storage.tick();
+        self.sync_time();
         let mut command_buffer: Vec<GameSystemCommand> =
             Vec::with_capacity(self.common_systems.len());
-        for elem in &self.common_systems {
+        for index in self.schedule()?.to_vec() {
+            let (elem, config) = &self.common_systems[index];
+            if !config.should_run(frames, &self.storage) {
+                continue;
+            }
             let mut system = elem.borrow_mut();
-            let command =
-                system.update(self.frames, delta_time, &mut self.storage, asset_manager)?;
+            let command = system.update(frames, delta_time, &mut self.storage, asset_manager)?;
+            self.storage.apply_commands()?;
             if !matches!(command, GameSystemCommand::Nothing) {
                 command_buffer.push(command);
             }
         }
-        self.frames += 1;
+        // a system may have restored a snapshot, and with it the tick
+        self.storage.set_tick(self.storage.tick() + 1);
+        self.sync_time();
         Ok(command_buffer)
     }
 
+    /// The scene's clocks
+    pub fn time(&self) -> Time {
+        Time::of(&self.storage)
+    }
+
+    /// Bills the scene's wall clock for one iteration of the run loop
+    pub(crate) fn advance_time(&mut self, elapsed: Duration, is_paused: bool) {
+        if let Some(mut time) = self.storage.resource_mut::<Time>() {
+            time.advance(elapsed, is_paused);
+        }
+    }
+
+    /// Moves the step clock to the storage's tick
+    fn sync_time(&mut self) {
+        if let Some(mut time) = self.storage.resource_mut::<Time>() {
+            time.set_step(self.storage.tick());
+        }
+    }
+
+    /// The layers of the only renderer as they are, or those of every
+    /// renderer merged in the order they were added
     pub fn render(&self, asset_manager: &AssetManager) -> EngineResult<RendererLayersPtr> {
-        let mut system = self.renderer_system.borrow_mut();
-        system.render(self.frames, &self.storage, asset_manager)
+        let frames = self.storage.tick();
+        if let [system] = &self.renderer_systems[..] {
+            return system
+                .borrow_mut()
+                .render(frames, &self.storage, asset_manager);
+        }
+        let mut merged = self.merged_layers.borrow_mut();
+        merged.clear();
+        for system in &self.renderer_systems {
+            let layers = system
+                .borrow_mut()
+                .render(frames, &self.storage, asset_manager)?;
+            merged.append(&layers.borrow());
+        }
+        Ok(self.merged_layers.clone())
     }
 
     pub fn sound_effects(
         &mut self,
         asset_manager: &AssetManager,
     ) -> EngineResult<Vec<SoundEffect>> {
-        let Some(system) = &self.sound_system else {
-            return Ok(vec![]);
-        };
-        system.borrow_mut().update(&mut self.storage, asset_manager)
+        let mut effects = Vec::new();
+        for system in &self.sound_systems {
+            let mut system = system.borrow_mut();
+            effects.extend(system.update(&mut self.storage, asset_manager)?);
+            self.storage.apply_commands()?;
+        }
+        Ok(effects)
     }
 
-    pub fn push_events(&mut self, events: &[InputEvent]) -> EngineResult<()> {
-        if events.is_empty() {
-            return Ok(());
+    /// Hands the input to the control systems, and returns the commands
+    /// they have for the run loop
+    pub fn push_events(&mut self, events: &[InputEvent]) -> EngineResult<Vec<GameSystemCommand>> {
+        let mut commands = Vec::new();
+        for system in &self.control_systems {
+            let mut system = system.borrow_mut();
+            if !events.is_empty() {
+                system.push_events(&mut self.storage, events)?;
+                self.storage.apply_commands()?;
+            }
+            let command = system.command();
+            if !matches!(command, GameSystemCommand::Nothing) {
+                commands.push(command);
+            }
         }
-        self.control_system
-            .borrow_mut()
-            .push_events(&mut self.storage, events)
+        Ok(commands)
     }
 }
@@ -0,0 +1,144 @@
+//! Runs scenes without SDL: no window, no audio device, no event pump.
+//!
+//! Scenes are stepped by the same fixed-step loop the windowed game uses,
+//! but time is simulated rather than measured and the caller supplies the
+//! input. What would have been drawn and played is handed back frame by
+//! frame instead, which is what a test or a CI job wants to look at.
+
+use std::time::Duration;
+
+use super::{
+    systems::{RendererLayers, SoundEffect},
+    time::{StepRate, Time, TimeScale},
+    window::WindowState,
+    world::SceneRunner,
+};
+use crate::{systems::InputEvent, AssetManager, EngineResult, EngineSettings, SceneID, SizeU32};
+
+/// Everything a scene produced in one iteration of the loop.
+pub struct HeadlessFrame {
+    /// The scene that was current when the frame was rendered.
+    pub scene: SceneID,
+    pub layers: RendererLayers,
+    /// What the scenes beneath it on the stack drew, the bottom one first
+    pub covered: Vec<RendererLayers>,
+    pub sound_effects: Vec<SoundEffect>,
+}
+
+pub struct HeadlessWorld {
+    runner: SceneRunner,
+    asset_manager: AssetManager<'static>,
+    /// The logical resolution from the settings, which no resize changes
+    resolution: Option<SizeU32>,
+}
+
+impl HeadlessWorld {
+    pub(super) fn new(mut runner: SceneRunner, settings: &EngineSettings) -> EngineResult<Self> {
+        let mut asset_manager = AssetManager::default();
+        asset_manager.setup_headless(&settings.asset_source)?;
+        let window = WindowState {
+            size: settings.window.resolution.unwrap_or(settings.window.size),
+            mode: settings.window.mode,
+        };
+        runner.setup(&asset_manager, window)?;
+        Ok(Self {
+            runner,
+            asset_manager,
+            resolution: settings.window.resolution,
+        })
+    }
+
+    /// False once a system asked to terminate or a `Quit` event was fed in.
+    pub fn is_running(&self) -> bool {
+        self.runner.is_running()
+    }
+
+    pub fn current_scene(&self) -> SceneID {
+        self.runner.current_scene()
+    }
+
+    /// The scenes on the stack, the bottom one first
+    pub fn scene_stack(&self) -> &[SceneID] {
+        self.runner.scene_stack()
+    }
+
+    /// True until a replay given to the world has been played through.
+    pub fn is_replaying(&self) -> bool {
+        self.runner.is_replaying()
+    }
+
+    pub fn step_rate(&self) -> StepRate {
+        self.runner.step_rate()
+    }
+
+    pub fn time_scale(&self) -> TimeScale {
+        self.runner.time_scale()
+    }
+
+    /// The clocks of scene `id`
+    pub fn time(&self, id: SceneID) -> Option<Time> {
+        self.runner.time(id)
+    }
+
+    /// The window the scenes think they are drawn to
+    pub fn window(&self) -> WindowState {
+        self.runner.window()
+    }
+
+    /// Resizes the simulated window, which lays the scenes' renderers out
+    /// again as a real one would -- unless they draw at a logical
+    /// resolution, which is only scaled
+    pub fn resize(&mut self, size: SizeU32) -> EngineResult<()> {
+        let window = WindowState {
+            size: self.resolution.unwrap_or(size),
+            ..self.runner.window()
+        };
+        self.runner.update_window(window, &self.asset_manager)
+    }
+
+    pub fn asset_manager(&self) -> &AssetManager<'static> {
+        &self.asset_manager
+    }
+
+    /// Runs one iteration covering exactly one simulation step's worth of
+    /// wall-clock time, or the next recorded iteration while a replay lasts.
+    /// How many steps that runs depends on the time scale.
+    pub fn step(&mut self, events: &[InputEvent]) -> EngineResult<HeadlessFrame> {
+        self.advance(self.step_rate().step(), events)
+    }
+
+    /// Runs one iteration as if `elapsed` had passed since the previous one.
+    ///
+    /// The usual catch-up cap applies, so a long `elapsed` runs a handful of
+    /// steps rather than all of them.
+    pub fn advance(
+        &mut self,
+        elapsed: Duration,
+        events: &[InputEvent],
+    ) -> EngineResult<HeadlessFrame> {
+        let scene = self.runner.current_scene();
+        let output = self.runner.frame(elapsed, events, &self.asset_manager)?;
+        let mut covered = output
+            .layers
+            .iter()
+            .map(|layers| layers.borrow().clone())
+            .collect::<Vec<_>>();
+        let layers = covered.pop().unwrap_or_default();
+        Ok(HeadlessFrame {
+            scene,
+            layers,
+            covered,
+            sound_effects: output.sound_effects,
+        })
+    }
+
+    /// Steps without input until `steps` frames were produced or the world
+    /// stopped, whichever comes first.
+    pub fn run(&mut self, steps: usize) -> EngineResult<Vec<HeadlessFrame>> {
+        let mut frames = Vec::with_capacity(steps);
+        while frames.len() < steps && self.is_running() {
+            frames.push(self.step(&[])?);
+        }
+        Ok(frames)
+    }
+}
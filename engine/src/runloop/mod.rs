@@ -1,5 +1,10 @@
 pub mod game_scene;
+pub mod headless;
+pub mod replay;
+pub mod schedule;
 pub mod systems;
+pub mod time;
+pub mod window;
 pub mod world;
 
 pub type SceneID = u8;
@@ -0,0 +1,301 @@
+//! Recording of the input a world receives, and playing it back.
+//!
+//! Gameplay runs in fixed steps, so a scene fed the same events before the
+//! same steps, from the same seed, plays out the same way. A recording keeps
+//! exactly that: one record per iteration of the run loop, holding the
+//! events pushed to the current scene and the number of steps it then ran.
+//! Replaying runs that many steps rather than whatever the wall clock says
+//! is due, which is what makes it exact on any machine.
+//!
+//! File layout, all integers little-endian:
+//!
+//! ```text
+//! header  magic "IREC", version: u8, seed: u64, params count: u32, params...
+//! param   name length: u32, name, value length: u32, value (UTF-8)
+//! frame   steps: u32, events count: u32, events...
+//! event   tag: u8, then per tag
+//!           0 quit
+//!           1 keyboard  code: i32, pressed: u8
+//!           2 mouse     x: i32, y: i32, x_rel: i32, y_rel: i32
+//!           3 button    button: u8, pressed: u8, x: i32, y: i32
+//! ```
+//!
+//! The params are settings the game interprets input by, such as how far
+//! mouse motion turns the player: a replay made with other settings would
+//! drift from the session it recorded, so the game takes them from the
+//! recording instead.
+//!
+//! Recordings of an older version are refused: mouse motion they hold was
+//! ignored when they were made, or turned the player by settings they don't
+//! keep.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+};
+
+use crate::{
+    prelude::{Keycode, MouseButton},
+    systems::InputEvent,
+    EngineError, EngineResult,
+};
+
+const MAGIC: &[u8; 4] = b"IREC";
+const VERSION: u8 = 3;
+
+const EVENT_QUIT: u8 = 0;
+const EVENT_KEYBOARD: u8 = 1;
+const EVENT_MOUSE: u8 = 2;
+const EVENT_MOUSE_BUTTON: u8 = 3;
+
+/// One iteration of the run loop as it was recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+    pub steps: u32,
+    pub events: Vec<InputEvent>,
+}
+
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    path: String,
+}
+
+impl InputRecorder {
+    /// Starts a recording at `path`, replacing any file already there.
+    ///
+    /// `seed` is whatever the game seeds its randomness with: a replay is
+    /// only exact if it starts from the same one.
+    pub fn create(path: impl Into<String>, seed: u64) -> EngineResult<Self> {
+        Self::create_with(path, seed, &[])
+    }
+
+    /// Starts a recording that also keeps `params`, named settings that
+    /// change what the game makes of the input; see [`InputReplay::param`].
+    pub fn create_with(
+        path: impl Into<String>,
+        seed: u64,
+        params: &[(&str, &str)],
+    ) -> EngineResult<Self> {
+        let path = path.into();
+        let file = File::create(&path).map_err(|e| recording_error(&path, e))?;
+        let mut recorder = Self {
+            writer: BufWriter::new(file),
+            path,
+        };
+        recorder
+            .write_header(seed, params)
+            .map_err(|e| recording_error(&recorder.path, e))?;
+        Ok(recorder)
+    }
+
+    fn write_header(&mut self, seed: u64, params: &[(&str, &str)]) -> io::Result<()> {
+        let w = &mut self.writer;
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&seed.to_le_bytes())?;
+        w.write_all(&(params.len() as u32).to_le_bytes())?;
+        for text in params.iter().flat_map(|(name, value)| [name, value]) {
+            w.write_all(&(text.len() as u32).to_le_bytes())?;
+            w.write_all(text.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, steps: u32, events: &[InputEvent]) -> EngineResult<()> {
+        self.write_frame(steps, events)
+            .map_err(|e| recording_error(&self.path, e))
+    }
+
+    fn write_frame(&mut self, steps: u32, events: &[InputEvent]) -> io::Result<()> {
+        let w = &mut self.writer;
+        w.write_all(&steps.to_le_bytes())?;
+        w.write_all(&(events.len() as u32).to_le_bytes())?;
+        for event in events {
+            match *event {
+                InputEvent::Quit => w.write_all(&[EVENT_QUIT])?,
+                InputEvent::Keyboard { code, pressed } => {
+                    w.write_all(&[EVENT_KEYBOARD])?;
+                    w.write_all(&code.into_i32().to_le_bytes())?;
+                    w.write_all(&[pressed as u8])?;
+                }
+                InputEvent::Mouse { x, y, x_rel, y_rel } => {
+                    w.write_all(&[EVENT_MOUSE])?;
+                    for value in [x, y, x_rel, y_rel] {
+                        w.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                InputEvent::MouseButton {
+                    button,
+                    pressed,
+                    x,
+                    y,
+                } => {
+                    w.write_all(&[EVENT_MOUSE_BUTTON, button as u8, pressed as u8])?;
+                    for value in [x, y] {
+                        w.write_all(&value.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn recording_error(path: &str, e: io::Error) -> EngineError {
+    EngineError::FileAccessError(format!("failed to write input recording {path}: {e}"))
+}
+
+pub struct InputReplay {
+    seed: u64,
+    params: Vec<(String, String)>,
+    frames: std::vec::IntoIter<RecordedFrame>,
+}
+
+impl InputReplay {
+    /// Reads a whole recording up front, so a damaged file is reported
+    /// before the game starts rather than halfway through the replay.
+    pub fn open(path: &str) -> EngineResult<Self> {
+        let file = File::open(path).map_err(|e| {
+            EngineError::FileAccessError(format!("failed to open input recording {path}: {e}"))
+        })?;
+        let mut reader = BufReader::new(file);
+        let (seed, params) = read_header(&mut reader).map_err(damaged_recording)?;
+        let mut frames = Vec::new();
+        loop {
+            // EOF is a clean end of the recording only at a frame boundary
+            let steps = match read_u32(&mut reader) {
+                Ok(value) => value,
+                Err(e) if matches!(e.kind(), ErrorKind::UnexpectedEof) => break,
+                Err(e) => return Err(damaged_recording(e)),
+            };
+            let events = read_events(&mut reader).map_err(damaged_recording)?;
+            frames.push(RecordedFrame { steps, events });
+        }
+        Ok(Self {
+            seed,
+            params,
+            frames: frames.into_iter(),
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The value recorded for `name`, if the recorder was given one
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The next recorded frame, or `None` once the recording is used up.
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.next()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+fn damaged_recording(e: io::Error) -> EngineError {
+    EngineError::ResourceParseError(format!("input recording looks damaged: {e}"))
+}
+
+fn read_header(reader: &mut impl Read) -> io::Result<(u64, Vec<(String, String)>)> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "not an input recording",
+        ));
+    }
+    let version = read_u8(reader)?;
+    if version != VERSION {
+        let msg = format!("unsupported recording version {version}");
+        return Err(io::Error::new(ErrorKind::InvalidData, msg));
+    }
+    let seed = read_u64(reader)?;
+    let mut params = Vec::new();
+    for _ in 0..read_u32(reader)? {
+        params.push((read_string(reader)?, read_string(reader)?));
+    }
+    Ok((seed, params))
+}
+
+fn read_events(reader: &mut impl Read) -> io::Result<Vec<InputEvent>> {
+    let count = read_u32(reader)?;
+    // no preallocation by count: a corrupted count must not trigger a giant
+    // allocation, and frames hold a handful of events at most
+    let mut events = Vec::new();
+    for _ in 0..count {
+        let event = match read_u8(reader)? {
+            EVENT_QUIT => InputEvent::Quit,
+            EVENT_KEYBOARD => {
+                let raw = read_i32(reader)?;
+                let code = Keycode::from_i32(raw).ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidData, format!("unknown keycode {raw}"))
+                })?;
+                let pressed = read_u8(reader)? != 0;
+                InputEvent::Keyboard { code, pressed }
+            }
+            EVENT_MOUSE => InputEvent::Mouse {
+                x: read_i32(reader)?,
+                y: read_i32(reader)?,
+                x_rel: read_i32(reader)?,
+                y_rel: read_i32(reader)?,
+            },
+            EVENT_MOUSE_BUTTON => InputEvent::MouseButton {
+                button: MouseButton::from_ll(read_u8(reader)?),
+                pressed: read_u8(reader)? != 0,
+                x: read_i32(reader)?,
+                y: read_i32(reader)?,
+            },
+            tag => {
+                let msg = format!("unknown event tag {tag}");
+                return Err(io::Error::new(ErrorKind::InvalidData, msg));
+            }
+        };
+        events.push(event);
+    }
+    Ok(events)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let len = read_u32(reader)? as u64;
+    // read through `take`, so a corrupted length can't ask for more memory
+    // than the file has data
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
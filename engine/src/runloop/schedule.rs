@@ -0,0 +1,160 @@
+//! The order a scene's [`GameSystem`](super::systems::GameSystem)s run in.
+//!
+//! ```ignore
+//! scene.add_system_with(
+//!     NpcSystem::new(),
+//!     SystemConfig::new().label("npc").after("player").before("movement"),
+//! );
+//! scene.add_system_with(
+//!     RadarSystem::new(),
+//!     SystemConfig::new().every(10).run_if(is_player_alive),
+//! );
+//! ```
+//!
+//! Systems run in the order they were added unless a constraint says
+//! otherwise; a constraint moves a system no further than it has to. A
+//! system marked [`SystemConfig::last`] runs after every system that isn't,
+//! however late they were added. The scene builds the schedule before the
+//! first step, and a constraint naming a label no system has, or constraints
+//! that can't all hold, fail the build.
+//!
+//! A system skipped by a run condition misses the events sent while it
+//! wasn't running once they are pruned, see [`crate::entities::events`].
+
+use std::collections::HashSet;
+
+use crate::{ComponentStorage, EngineError, EngineResult};
+
+pub type SystemLabel = &'static str;
+
+#[derive(Clone, Copy)]
+pub enum RunCondition {
+    /// Only on steps that are a multiple of `n`
+    EveryNSteps(usize),
+    /// Only while the predicate holds for the scene's storage
+    If(fn(&ComponentStorage) -> bool),
+}
+
+impl RunCondition {
+    pub fn is_met(&self, frames: usize, storage: &ComponentStorage) -> bool {
+        match self {
+            Self::EveryNSteps(n) => *n == 0 || frames.is_multiple_of(*n),
+            Self::If(predicate) => predicate(storage),
+        }
+    }
+}
+
+/// How a system fits in the scene's schedule
+#[derive(Clone, Default)]
+pub struct SystemConfig {
+    pub label: Option<SystemLabel>,
+    /// Labels of the systems this one runs before
+    pub before: Vec<SystemLabel>,
+    /// Labels of the systems this one runs after
+    pub after: Vec<SystemLabel>,
+    /// Runs after every system not marked last
+    pub is_last: bool,
+    /// All must be met for the system to run on a step
+    pub conditions: Vec<RunCondition>,
+}
+
+impl SystemConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn label(mut self, label: SystemLabel) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn before(mut self, label: SystemLabel) -> Self {
+        self.before.push(label);
+        self
+    }
+
+    pub fn after(mut self, label: SystemLabel) -> Self {
+        self.after.push(label);
+        self
+    }
+
+    /// Runs after every system not marked last, including those added after
+    /// it; a system that asks to run after this one fails the build
+    pub fn last(mut self) -> Self {
+        self.is_last = true;
+        self
+    }
+
+    pub fn run_if(mut self, predicate: fn(&ComponentStorage) -> bool) -> Self {
+        self.conditions.push(RunCondition::If(predicate));
+        self
+    }
+
+    pub fn every(mut self, steps: usize) -> Self {
+        self.conditions.push(RunCondition::EveryNSteps(steps));
+        self
+    }
+
+    pub fn should_run(&self, frames: usize, storage: &ComponentStorage) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.is_met(frames, storage))
+    }
+}
+
+/// The positions of `configs` in the order they run: of the systems whose
+/// constraints are met, the one added first goes next
+pub fn build(configs: &[&SystemConfig]) -> EngineResult<Vec<usize>> {
+    let mut labels = HashSet::new();
+    for label in configs.iter().filter_map(|config| config.label) {
+        if !labels.insert(label) {
+            return Err(invalid(format!("label '{label}' is used twice")));
+        }
+    }
+    let position = |label: SystemLabel| {
+        configs
+            .iter()
+            .position(|config| config.label == Some(label))
+            .ok_or_else(|| invalid(format!("no system has label '{label}'")))
+    };
+    // edges[a] holds the systems that must run after a
+    let mut edges = vec![Vec::new(); configs.len()];
+    for (index, config) in configs.iter().enumerate() {
+        for &label in &config.before {
+            edges[index].push(position(label)?);
+        }
+        for &label in &config.after {
+            edges[position(label)?].push(index);
+        }
+        if config.is_last {
+            for (other, _) in configs.iter().enumerate().filter(|(_, x)| !x.is_last) {
+                edges[other].push(index);
+            }
+        }
+    }
+    let mut pending = vec![0; configs.len()];
+    for &next in edges.iter().flatten() {
+        pending[next] += 1;
+    }
+    let mut order = Vec::with_capacity(configs.len());
+    let mut is_done = vec![false; configs.len()];
+    while order.len() < configs.len() {
+        let Some(index) = (0..configs.len()).find(|&i| !is_done[i] && pending[i] == 0) else {
+            let stuck = (0..configs.len())
+                .filter(|&i| !is_done[i])
+                .map(|i| configs[i].label.unwrap_or("<unlabelled>"))
+                .collect::<Vec<_>>();
+            return Err(invalid(format!("ordering cycle among {stuck:?}")));
+        };
+        is_done[index] = true;
+        order.push(index);
+        for &next in &edges[index] {
+            pending[next] -= 1;
+        }
+    }
+    Ok(order)
+}
+
+fn invalid(msg: String) -> EngineError {
+    EngineError::InvalidSchedule(msg)
+}
@@ -1,18 +1,40 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::prelude::{BlendMode, Color, Keycode, Point, Rect};
+use crate::prelude::{BlendMode, Color, Keycode, MouseButton, Point, Rect};
 use crate::{
     assets::TextureId,
     game_scene::{SceneEvent, SceneParameters},
+    time::TimeScale,
+    window::WindowMode,
     AssetManager, ComponentStorage, EngineResult, Float, SceneID, SizeU32,
 };
 
 pub enum GameSystemCommand {
     Nothing,
+    /// Replaces every scene on the stack with `id`
     SwitchScene {
         id: SceneID,
         params: SceneParameters,
     },
+    /// Puts `id` over the current scene, which stays on screen beneath it
+    /// but stops running until `id` is popped
+    PushScene {
+        id: SceneID,
+        params: SceneParameters,
+    },
+    /// Takes the current scene off the stack, resuming the one beneath
+    PopScene {
+        params: SceneParameters,
+    },
+    /// Speeds simulated time up or slows it down, from the next iteration
+    /// on, until it is set again or the scene stack changes
+    SetTimeScale(TimeScale),
+    /// Runs one more step on the next iteration, which is how a frozen
+    /// world moves on
+    StepOnce,
+    /// Switches the window to `mode` after the iteration; the scenes hear of
+    /// the new size as they do of any resize
+    SetWindowMode(WindowMode),
     Terminate,
 }
 
@@ -41,18 +63,27 @@ pub trait GameSystem {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InputEvent {
     Quit,
     Keyboard {
         code: Keycode,
         pressed: bool,
     },
+    /// With the mouse captured, `x` and `y` stand still and only the
+    /// relative motion means anything
     Mouse {
         x: i32,
         y: i32,
         x_rel: i32,
         y_rel: i32,
     },
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+        x: i32,
+        y: i32,
+    },
 }
 
 pub trait GameControlSystem {
@@ -62,8 +93,25 @@ pub trait GameControlSystem {
         storage: &mut ComponentStorage,
         events: &[InputEvent],
     ) -> EngineResult<()>;
+
+    /// Asked for once per iteration, after the input. Control systems run
+    /// while time is frozen and steps don't, which makes them the place for
+    /// time controls.
+    fn command(&mut self) -> GameSystemCommand {
+        GameSystemCommand::Nothing
+    }
+
+    fn on_scene_event(
+        &mut self,
+        _storage: &mut ComponentStorage,
+        _event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        Ok(())
+    }
 }
 
+#[derive(Clone, Debug)]
 pub enum RendererEffect {
     Texture {
         texture: TextureId,
@@ -89,11 +137,13 @@ pub enum RendererEffect {
     },
 }
 
+#[derive(Clone, Debug)]
 pub struct DepthRenderEffect {
     pub effect: RendererEffect,
     pub depth: Float,
 }
 
+#[derive(Clone, Debug, Default)]
 pub struct RendererLayers {
     pub hud: Vec<RendererEffect>,
     pub depth: Vec<DepthRenderEffect>,
@@ -118,6 +168,13 @@ impl RendererLayers {
     pub fn push_background(&mut self, effect: RendererEffect) {
         self.background.push(effect)
     }
+
+    /// Adds the effects of `other` on top of these, layer by layer
+    pub fn append(&mut self, other: &RendererLayers) {
+        self.hud.extend_from_slice(&other.hud);
+        self.depth.extend_from_slice(&other.depth);
+        self.background.extend_from_slice(&other.background);
+    }
 }
 
 pub type RendererLayersPtr = Rc<RefCell<RendererLayers>>;
@@ -135,10 +192,38 @@ pub trait GameRendererSystem {
         storage: &ComponentStorage,
         asset_manager: &AssetManager,
     ) -> EngineResult<RendererLayersPtr>;
+
+    /// Lays the renderer out for a window of `window_size` pixels, before
+    /// the scene's systems hear of the resize. Sets it up again unless
+    /// overridden, so a renderer that derives nothing but its layout from
+    /// the size has nothing to do.
+    fn resize(
+        &mut self,
+        storage: &ComponentStorage,
+        asset_manager: &AssetManager,
+        window_size: SizeU32,
+    ) -> EngineResult<()> {
+        self.setup(storage, asset_manager, window_size)
+    }
+
+    fn on_scene_event(
+        &mut self,
+        _storage: &ComponentStorage,
+        _event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        Ok(())
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum SoundEffect {
-    PlaySound { asset_id: String, loops: i32 },
+    PlaySound {
+        asset_id: String,
+        loops: i32,
+    },
+    /// Halts every sound playing, looped ones included
+    StopAll,
     // TODO: play music command
 }
 
@@ -154,4 +239,14 @@ pub trait GameSoundSystem {
         storage: &mut ComponentStorage,
         asset_manager: &AssetManager,
     ) -> EngineResult<Vec<SoundEffect>>;
+
+    /// The effects are played right away, even if the scene is being left
+    fn on_scene_event(
+        &mut self,
+        _storage: &mut ComponentStorage,
+        _event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<Vec<SoundEffect>> {
+        Ok(Vec::new())
+    }
 }
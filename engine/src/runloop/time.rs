@@ -0,0 +1,169 @@
+//! How simulated time relates to the wall clock.
+//!
+//! Gameplay counts time in steps, and how long a step lasts is up to
+//! [`TimingSettings`](crate::settings::TimingSettings). A duration the game
+//! declares in real time is turned into steps with the [`StepRate`] of the
+//! world it runs in, so it means the same at any step rate.
+//!
+//! Every scene's storage holds a [`Time`], its clocks, which the run loop
+//! keeps up to date:
+//!
+//! ```ignore
+//! let time = Time::of(storage);
+//! let deadline = time.after(RECHARGE);    // a step number
+//! if time.is_due(deadline) { ... }
+//! time.game();                            // time played
+//! ```
+
+use std::time::Duration;
+
+use crate::{ComponentStorage, Float};
+
+/// Steps per second unless the settings say otherwise
+pub const DEFAULT_STEP_RATE: u32 = 60;
+
+/// The steps per second of the world a scene runs in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepRate(pub u32);
+
+impl StepRate {
+    /// The rate of the [`Time`] in `storage`
+    pub fn of(storage: &ComponentStorage) -> Self {
+        Time::of(storage).step_rate()
+    }
+
+    /// How long one step lasts
+    pub fn step(&self) -> Duration {
+        Duration::from_secs(1) / self.0
+    }
+
+    /// [`Self::step`] in seconds, the `delta_time` systems are updated with
+    pub fn step_secs(&self) -> Float {
+        1.0 / self.0 as Float
+    }
+
+    /// The whole number of steps closest to `duration`; never zero for a
+    /// duration that isn't, so a short deadline can't pass unnoticed
+    pub fn steps(&self, duration: Duration) -> usize {
+        let steps = (duration.as_secs_f64() * self.0 as f64).round() as usize;
+        if duration.is_zero() {
+            0
+        } else {
+            steps.max(1)
+        }
+    }
+}
+
+impl Default for StepRate {
+    fn default() -> Self {
+        Self(DEFAULT_STEP_RATE)
+    }
+}
+
+/// How fast simulated time runs against the wall clock
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeScale {
+    /// `1.0` is real time, less is slow motion and more is fast-forward
+    Scaled(Float),
+    /// No step runs but those asked for with
+    /// [`GameSystemCommand::StepOnce`](super::systems::GameSystemCommand::StepOnce)
+    Frozen,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self::Scaled(1.0)
+    }
+}
+
+/// A scene's clocks. The run loop inserts it into every scene's storage
+/// before the systems are set up, and keeps it up to date.
+///
+/// Gameplay runs on the step clock: [`Self::step`] is the scene's step count
+/// -- the storage's tick, so it comes back with a snapshot -- and
+/// [`Self::game`] the simulated time those steps cover. Deadlines are step
+/// numbers on it. The wall clock only measures how long the scene was
+/// around, and is no basis for gameplay: a replay or a time scale changes it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Time {
+    step_rate: StepRate,
+    step: usize,
+    real: Duration,
+    paused: Duration,
+    is_paused: bool,
+}
+
+impl Time {
+    pub fn new(step_rate: StepRate) -> Self {
+        Self {
+            step_rate,
+            ..Default::default()
+        }
+    }
+
+    /// The clocks in `storage`, or fresh ones at the default rate for a
+    /// storage no world has set up, like a test's
+    pub fn of(storage: &ComponentStorage) -> Self {
+        storage.resource::<Time>().map(|x| *x).unwrap_or_default()
+    }
+
+    pub fn step_rate(&self) -> StepRate {
+        self.step_rate
+    }
+
+    /// The step the scene is on
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Simulated time the steps so far cover
+    pub fn game(&self) -> Duration {
+        self.since(0)
+    }
+
+    /// Simulated time the steps from `step` on cover, none if it is still
+    /// ahead
+    pub fn since(&self, step: usize) -> Duration {
+        let steps = self.step.saturating_sub(step);
+        Duration::from_secs_f64(steps as f64 / self.step_rate.0 as f64)
+    }
+
+    /// Wall-clock time the scene has spent on the scene stack, paused or not
+    pub fn real(&self) -> Duration {
+        self.real
+    }
+
+    /// The part of [`Self::real`] the scene spent not running: beneath
+    /// another scene, or with time frozen
+    pub fn paused(&self) -> Duration {
+        self.paused
+    }
+
+    /// Whether the scene was paused on the last iteration
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// The step on which `duration` of game time from now has passed
+    pub fn after(&self, duration: Duration) -> usize {
+        self.step + self.step_rate.steps(duration)
+    }
+
+    /// Whether the step clock has reached `deadline`
+    pub fn is_due(&self, deadline: usize) -> bool {
+        self.step >= deadline
+    }
+
+    pub(crate) fn set_step(&mut self, step: usize) {
+        self.step = step;
+    }
+
+    /// Bills the scene for one iteration of the run loop
+    pub(crate) fn advance(&mut self, elapsed: Duration, is_paused: bool) {
+        self.real += elapsed;
+        if is_paused {
+            self.paused += elapsed;
+        }
+        self.is_paused = is_paused;
+    }
+}
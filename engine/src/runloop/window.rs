@@ -0,0 +1,98 @@
+//! The window the scenes are drawn to.
+//!
+//! Every scene's storage holds a [`WindowState`] the run loop keeps up to
+//! date. The window's size changes when the player resizes it or switches
+//! modes, which systems hear of as [`SceneEvent::Resized`]; renderers are set
+//! up for the new size first, see [`GameRendererSystem::resize`].
+//!
+//! ```ignore
+//! let window = WindowState::of(storage);
+//! let mode = window.mode.toggled(WindowMode::Borderless);
+//! GameSystemCommand::SetWindowMode(mode)     // from a control system
+//! ```
+//!
+//! With a logical resolution in the settings, the scenes draw at that size
+//! whatever the window's, and the run loop scales the picture to fit with
+//! the [`Scaling`] asked for. The window then never looks resized to them.
+//!
+//! [`SceneEvent::Resized`]: crate::game_scene::SceneEvent::Resized
+//! [`GameRendererSystem::resize`]: crate::systems::GameRendererSystem::resize
+
+use crate::{prelude::Rect, ComponentStorage, SizeU32};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Takes the display over, changing its video mode to the window's size
+    Fullscreen,
+    /// A window without decorations as large as the display, which keeps
+    /// the desktop's video mode and switches away quickly
+    Borderless,
+}
+
+impl WindowMode {
+    /// `mode` if the window isn't in it already, back to a window if it is
+    pub fn toggled(self, mode: WindowMode) -> WindowMode {
+        if self == mode {
+            WindowMode::Windowed
+        } else {
+            mode
+        }
+    }
+}
+
+/// How a logical resolution is scaled to the window. Either way the picture
+/// keeps its aspect ratio, centred between black bars.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scaling {
+    /// As large as fits
+    #[default]
+    Aspect,
+    /// The largest whole multiple that fits, which keeps every logical
+    /// pixel the same size; as large as fits if not even one does
+    Integer,
+}
+
+impl Scaling {
+    /// Where a picture of `logical` pixels goes in a window of `window`
+    /// pixels
+    pub fn viewport(self, logical: SizeU32, window: SizeU32) -> Rect {
+        let (lw, lh) = (logical.width.max(1) as u64, logical.height.max(1) as u64);
+        let (ww, wh) = (window.width as u64, window.height as u64);
+        let factor = (ww / lw).min(wh / lh);
+        let (width, height) = match self {
+            Scaling::Integer if factor > 0 => (lw * factor, lh * factor),
+            // a window narrower than the picture, for its height: fit the width
+            _ if ww * lh <= wh * lw => (ww, lh * ww / lw),
+            _ => (lw * wh / lh, wh),
+        };
+        Rect::new(
+            ((ww - width) / 2) as i32,
+            ((wh - height) / 2) as i32,
+            width.max(1) as u32,
+            height.max(1) as u32,
+        )
+    }
+}
+
+/// The window as the scenes see it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowState {
+    /// What there is to draw on, in pixels: the logical resolution if the
+    /// settings give one, or else the window's. On a high-DPI display the
+    /// window has more pixels than the size it was asked for.
+    pub size: SizeU32,
+    pub mode: WindowMode,
+}
+
+impl WindowState {
+    /// The window in `storage`, or an empty one for a storage no world has
+    /// set up, like a test's
+    pub fn of(storage: &ComponentStorage) -> Self {
+        storage
+            .resource::<WindowState>()
+            .map(|x| *x)
+            .unwrap_or_default()
+    }
+}
@@ -1,38 +1,37 @@
 use super::{
     game_scene::GameScene,
+    headless::HeadlessWorld,
+    replay::{InputRecorder, InputReplay, RecordedFrame},
     systems::{GameSystemCommand, RendererEffect, RendererLayersPtr, SoundEffect},
+    time::{StepRate, Time, TimeScale},
+    window::{Scaling, WindowMode, WindowState},
 };
 use crate::{
-    game_scene::SceneEvent, systems::InputEvent, AssetManager, AudioSettings, EngineError,
-    EngineResult, EngineSettings, SceneID, SizeU32, WindowSettings,
+    game_scene::{SceneEvent, SceneParameters},
+    systems::InputEvent,
+    AssetManager, AudioSettings, EngineError, EngineResult, EngineSettings, SceneID, SizeU32,
+    TimingSettings, WindowSettings,
+};
+use log::{info, warn};
+use sdl2::{
+    event::{Event, WindowEvent},
+    mixer::InitFlag,
+    pixels::Color,
+    render::{Texture, WindowCanvas},
+    video::FullscreenType,
+    EventPump, Sdl,
 };
-use log::warn;
-use sdl2::{event::Event, mixer::InitFlag, pixels::Color, render::WindowCanvas, EventPump, Sdl};
 use std::{
     cmp::Ordering,
     collections::HashMap,
     time::{Duration, Instant},
 };
 
-const TARGET_FPS: u64 = 60;
-/// How much simulated time one gameplay step covers. Gameplay advances in
-/// whole steps of this size, so the frame-based deadlines the game systems
-/// use (weapon recharge, damage recovery, animation) always mean the same
-/// amount of real time -- vsync at any refresh rate only changes how often
-/// the world is drawn, never how fast it runs.
-const FIXED_STEP: Duration = Duration::from_micros(1_000_000 / TARGET_FPS);
-const FIXED_STEP_SECS: f32 = 1.0 / TARGET_FPS as f32;
-/// Upper bound on how much simulated time one iteration may catch up on. A
-/// long stall -- level generation, a dragged window, a breakpoint -- would
-/// otherwise queue a burst of steps and fast-forward the game.
-const MAX_CATCH_UP: Duration = Duration::from_micros(5 * 1_000_000 / TARGET_FPS);
-/// Upper bound on the render rate. Vsync usually paces the loop; this only
-/// matters when it is unavailable or the display refreshes faster.
-const FRAME_DURATION: Duration = FIXED_STEP;
-
 #[derive(Default)]
 pub struct GameWorld {
     scenes: Vec<GameScene>,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
 }
 
 impl GameWorld {
@@ -45,15 +44,29 @@ impl GameWorld {
         self
     }
 
+    /// Writes the input of every iteration to `recorder`.
+    pub fn with_recorder(mut self, recorder: InputRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Feeds the scenes recorded input instead of live input until the
+    /// recording runs out. Live input is ignored meanwhile, except `Quit`.
+    pub fn with_replay(mut self, replay: InputReplay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
     pub fn start(self, settings: EngineSettings) -> EngineResult<()> {
+        let runner = SceneRunner::new(self, settings.timing)?;
         let systems = SDLSystems::setup(&settings)?;
-        let current_scene = self.scenes.first().map(|x| x.id()).unwrap_or_default();
-        let scenes = self
-            .scenes
-            .into_iter()
-            .map(|x| (x.id(), x))
-            .collect::<HashMap<SceneID, GameScene>>();
-        run(systems, &settings, scenes, current_scene)
+        run(systems, &settings, runner)
+    }
+
+    /// Sets the scenes up without a window or an audio device, to be stepped
+    /// by hand -- see [`HeadlessWorld`].
+    pub fn headless(self, settings: &EngineSettings) -> EngineResult<HeadlessWorld> {
+        HeadlessWorld::new(SceneRunner::new(self, settings.timing)?, settings)
     }
 }
 
@@ -66,6 +79,9 @@ impl SDLSystems {
     fn setup(settings: &EngineSettings) -> EngineResult<Self> {
         let sdl = sdl2::init().map_err(EngineError::Sdl)?;
         let canvas = Self::setup_canvas(&sdl, &settings.window)?;
+        if settings.window.capture_mouse {
+            sdl.mouse().set_relative_mouse_mode(true);
+        }
         Self::setup_audio(&sdl, &settings.audio_setting)?;
         let event_pump = sdl.event_pump().map_err(EngineError::Sdl)?;
         Ok(SDLSystems { canvas, event_pump })
@@ -74,9 +90,18 @@ impl SDLSystems {
     fn setup_canvas(sdl: &Sdl, window_settings: &WindowSettings) -> EngineResult<WindowCanvas> {
         let video_subsystem = sdl.video().map_err(EngineError::Sdl)?;
         let size = &window_settings.size;
-        let window = video_subsystem
-            .window(&window_settings.title, size.width, size.height)
-            .position_centered()
+        let mut builder = video_subsystem.window(&window_settings.title, size.width, size.height);
+        // on a high-DPI display, draw with every pixel the window covers
+        builder.position_centered().allow_highdpi();
+        if window_settings.resizable {
+            builder.resizable();
+        }
+        match window_settings.mode {
+            WindowMode::Windowed => {}
+            WindowMode::Fullscreen => _ = builder.fullscreen(),
+            WindowMode::Borderless => _ = builder.fullscreen_desktop(),
+        }
+        let window = builder
             .build()
             .map_err(|op| EngineError::Sdl(op.to_string()))?;
         window
@@ -107,8 +132,7 @@ impl SDLSystems {
 fn run(
     systems: SDLSystems,
     settings: &EngineSettings,
-    mut scenes: HashMap<SceneID, GameScene>,
-    mut current_scene: SceneID,
+    mut runner: SceneRunner,
 ) -> EngineResult<()> {
     let mut canvas = systems.canvas;
     let mut event_pump = systems.event_pump;
@@ -124,69 +148,410 @@ fn run(
     let texture_creator = canvas.texture_creator();
     let mut asset_manager = AssetManager::default();
     asset_manager.setup(&settings.asset_source, &texture_creator, max_texture)?;
-    // setup all scenes
-    for scene in scenes.values_mut() {
-        scene.setup_systems(&asset_manager, settings.window.size)?;
-    }
+    // the scenes draw into an off-screen target of the logical resolution,
+    // if there is one, which is scaled to the window
+    let mut target = match settings.window.resolution {
+        Some(size) => {
+            // nearest pixel: a low resolution should look blocky, not blurred
+            sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "0");
+            let texture = texture_creator
+                .create_texture_target(None, size.width, size.height)
+                .map_err(|op| EngineError::Sdl(op.to_string()))?;
+            Some(LogicalTarget {
+                texture,
+                size,
+                scaling: settings.window.scaling,
+            })
+        }
+        None => None,
+    };
+    let resolution = settings.window.resolution;
+    runner.setup(&asset_manager, window_state(&canvas, resolution)?)?;
     let mut last_time = Instant::now();
-    let mut accumulator = Duration::ZERO;
-    let mut is_running = true;
     let mut events = Vec::with_capacity(32);
-    while is_running {
+    while runner.is_running() {
         let frame_start = Instant::now();
-        accumulator += frame_start.duration_since(last_time).min(MAX_CATCH_UP);
+        let elapsed = frame_start.duration_since(last_time);
         last_time = frame_start;
 
         events.clear();
-        let quit_requested = get_events(&mut event_pump, &mut events);
-        let commands = {
-            let Some(scene) = scenes.get_mut(&current_scene) else {
+        if get_events(&mut event_pump, &mut events) {
+            runner.update_window(window_state(&canvas, resolution)?, &asset_manager)?;
+        }
+        let output = runner.frame(elapsed, &events, &asset_manager)?;
+        render_frame(&mut canvas, target.as_mut(), &asset_manager, &output.layers)?;
+        play_sound_effects(&output.sound_effects, &asset_manager)?;
+        if let Some(mode) = output.window_mode {
+            set_window_mode(&mut canvas, mode);
+            // the scenes get the size the switch ended up with, and the
+            // mode too if it failed
+            runner.update_window(window_state(&canvas, resolution)?, &asset_manager)?;
+        }
+        if output.scene_switched {
+            // the handler may have rebuilt the level; start the new
+            // scene on a clean clock rather than catching up on the
+            // time the switch itself took
+            last_time = Instant::now();
+        }
+        if let Some(frame_duration) = settings.timing.frame_duration() {
+            frame_delay(&frame_start, frame_duration);
+        }
+    }
+    Ok(())
+}
+
+/// What one iteration of the run loop produced for the backend to present.
+pub(super) struct FrameOutput {
+    /// Of every scene on the stack, the bottom one first: each is drawn
+    /// over the ones before it
+    pub layers: Vec<RendererLayersPtr>,
+    pub sound_effects: Vec<SoundEffect>,
+    /// A system switched, pushed or popped scenes during this iteration. The
+    /// new scene starts on a clean clock, so the caller should not bill it
+    /// for the time the switch itself took.
+    pub scene_switched: bool,
+    /// The mode a system switched the window to during this iteration
+    pub window_mode: Option<WindowMode>,
+}
+
+/// The part of the run loop that does not depend on the backend: feeding
+/// input to the current scene, stepping it on the fixed clock, and acting on
+/// the commands its systems return. The SDL loop and the headless driver
+/// both go through it, so they cannot drift apart.
+///
+/// Gameplay advances in whole steps of a fixed length, set by the step rate,
+/// so durations the systems count in steps always mean the same amount of
+/// simulated time -- vsync at any refresh rate only changes how often the
+/// world is drawn, never how fast it runs. The time scale changes how much
+/// simulated time the wall clock pays for.
+///
+/// Scenes are kept on a stack. Only the top one, the current scene, gets
+/// input and runs; those beneath it are frozen but still drawn, so a scene
+/// pushed over the level -- a pause menu, a dialog box -- shows the level
+/// behind it.
+pub(super) struct SceneRunner {
+    scenes: HashMap<SceneID, GameScene>,
+    /// Never empty; the last one is the current scene
+    stack: Vec<SceneID>,
+    timing: TimingSettings,
+    step_rate: StepRate,
+    window: WindowState,
+    accumulator: Duration,
+    time_scale: TimeScale,
+    /// Steps asked for with `StepOnce`, run on top of those the clock pays
+    /// for
+    extra_steps: u32,
+    is_running: bool,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+    /// Sound effects scenes returned for their events, played with the
+    /// next frame's
+    event_sounds: Vec<SoundEffect>,
+}
+
+impl SceneRunner {
+    pub fn new(world: GameWorld, timing: TimingSettings) -> EngineResult<Self> {
+        if timing.step_rate == 0 || timing.max_frame_rate == Some(0) {
+            return Err(EngineError::unexpected_state(format!(
+                "[run_loop] step and frame rates must be above zero: {timing:?}"
+            )));
+        }
+        let current_scene = world.scenes.first().map(|x| x.id()).unwrap_or_default();
+        let scenes = world
+            .scenes
+            .into_iter()
+            .map(|x| (x.id(), x))
+            .collect::<HashMap<SceneID, GameScene>>();
+        Ok(Self {
+            scenes,
+            stack: vec![current_scene],
+            timing,
+            step_rate: StepRate(timing.step_rate),
+            window: WindowState::default(),
+            accumulator: Duration::ZERO,
+            time_scale: TimeScale::default(),
+            extra_steps: 0,
+            is_running: true,
+            recorder: world.recorder,
+            replay: world.replay,
+            event_sounds: Vec::new(),
+        })
+    }
+
+    pub fn setup(&mut self, asset_manager: &AssetManager, window: WindowState) -> EngineResult<()> {
+        self.window = window;
+        for scene in self.scenes.values_mut() {
+            scene.insert_resource(Time::new(self.step_rate));
+            scene.insert_resource(window);
+            scene.setup_systems(asset_manager, window.size)?;
+        }
+        self.send_event(
+            self.current_scene(),
+            SceneEvent::Enter,
+            &SceneParameters::default(),
+        )
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    pub fn current_scene(&self) -> SceneID {
+        self.stack.last().copied().unwrap_or_default()
+    }
+
+    /// The scenes on the stack, the bottom one first
+    pub fn scene_stack(&self) -> &[SceneID] {
+        &self.stack
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    pub fn step_rate(&self) -> StepRate {
+        self.step_rate
+    }
+
+    pub fn time_scale(&self) -> TimeScale {
+        self.time_scale
+    }
+
+    /// The clocks of scene `id`
+    pub fn time(&self, id: SceneID) -> Option<Time> {
+        self.scenes.get(&id).map(|scene| scene.time())
+    }
+
+    pub fn window(&self) -> WindowState {
+        self.window
+    }
+
+    /// Tells every scene, on the stack or not, what the window is like now.
+    /// A new size lays the renderers out again and is sent as
+    /// [`SceneEvent::Resized`].
+    pub fn update_window(
+        &mut self,
+        window: WindowState,
+        asset_manager: &AssetManager,
+    ) -> EngineResult<()> {
+        if window == self.window {
+            return Ok(());
+        }
+        let is_resized = window.size != self.window.size;
+        if is_resized {
+            info!(
+                "window resized to {}x{}",
+                window.size.width, window.size.height
+            );
+        }
+        self.window = window;
+        let mut ids = self.scenes.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        for id in ids {
+            let scene = self.scenes.get_mut(&id).ok_or(EngineError::SceneNotFound)?;
+            scene.insert_resource(window);
+            if is_resized {
+                let effects = scene.resize(asset_manager, window.size)?;
+                self.event_sounds.extend(effects);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs one iteration: `elapsed` is the time since the previous one.
+    ///
+    /// While a replay lasts, both `elapsed` and `events` give way to the
+    /// recording; only a live `Quit` still gets through.
+    pub fn frame(
+        &mut self,
+        elapsed: Duration,
+        events: &[InputEvent],
+        asset_manager: &AssetManager,
+    ) -> EngineResult<FrameOutput> {
+        let mut quit_requested = events.iter().any(|x| matches!(x, InputEvent::Quit));
+        let (steps, replayed_events) = match self.next_replayed_frame() {
+            Some(frame) => (frame.steps, Some(frame.events)),
+            None => (self.steps_due(elapsed), None),
+        };
+        let events = replayed_events.as_deref().unwrap_or(events);
+        quit_requested |= events.iter().any(|x| matches!(x, InputEvent::Quit));
+        let is_running = self.is_running;
+        // every scene on the stack is billed for the iteration, those not
+        // running as paused
+        let is_frozen = self.time_scale == TimeScale::Frozen;
+        let current = self.current_scene();
+        for id in &self.stack {
+            let scene = self.scenes.get_mut(id).ok_or(EngineError::SceneNotFound)?;
+            scene.advance_time(elapsed, is_frozen || *id != current);
+        }
+        let (mut sound_effects, commands) = {
+            let Some(scene) = self.scenes.get_mut(&self.current_scene()) else {
                 return Err(EngineError::SceneNotFound);
             };
-            scene.push_events(&events)?;
-            let mut commands = Vec::new();
-            while accumulator >= FIXED_STEP {
-                accumulator -= FIXED_STEP;
-                let step_commands = scene.update(FIXED_STEP_SECS, &asset_manager)?;
+            let mut commands = scene.push_events(events)?;
+            let mut steps_run = 0;
+            while steps_run < steps {
+                steps_run += 1;
+                let step_commands = scene.update(self.step_rate.step_secs(), asset_manager)?;
                 if !step_commands.is_empty() {
                     // the scene is being left or the game is ending: run the
                     // command before simulating this scene any further
-                    commands = step_commands;
+                    commands.extend(step_commands);
                     break;
                 }
             }
-            let effects = scene.render(&asset_manager)?;
-            render_effects(&mut canvas, &asset_manager, effects)?;
-            let sound_effects = scene.sound_effects(&asset_manager)?;
-            play_sound_effects(&sound_effects, &asset_manager)?;
-            commands
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.record(steps_run, events)?;
+            }
+            let sound_effects = scene.sound_effects(asset_manager)?;
+            (sound_effects, commands)
         };
+        let layers = self
+            .stack
+            .iter()
+            .map(|id| {
+                let scene = self.scenes.get(id).ok_or(EngineError::SceneNotFound)?;
+                scene.render(asset_manager)
+            })
+            .collect::<EngineResult<Vec<_>>>()?;
+        let mut scene_switched = false;
+        let mut window_mode = None;
         for cmd in commands {
             match cmd {
-                GameSystemCommand::Terminate => is_running = false,
+                GameSystemCommand::Terminate => self.is_running = false,
                 GameSystemCommand::SwitchScene { id, params } => {
-                    let Some(scene) = scenes.get_mut(&id) else {
-                        return Err(EngineError::SceneNotFound);
-                    };
-                    scene.send_event(SceneEvent::Change, &params)?;
-                    current_scene = id;
-                    // the handler may have rebuilt the level; start the new
-                    // scene on a clean clock rather than catching up on the
-                    // time the switch itself took
-                    accumulator = Duration::ZERO;
-                    last_time = Instant::now();
+                    for left in std::mem::take(&mut self.stack).into_iter().rev() {
+                        self.send_event(left, SceneEvent::Exit, &params)?;
+                    }
+                    self.send_event(id, SceneEvent::Enter, &params)?;
+                    self.stack = vec![id];
+                    scene_switched = true;
+                }
+                GameSystemCommand::PushScene { id, params } => {
+                    if self.stack.contains(&id) {
+                        return Err(EngineError::unexpected_state(format!(
+                            "[run_loop] scene {id} is already on the stack"
+                        )));
+                    }
+                    self.send_event(self.current_scene(), SceneEvent::Suspend, &params)?;
+                    self.send_event(id, SceneEvent::Enter, &params)?;
+                    self.stack.push(id);
+                    scene_switched = true;
+                }
+                GameSystemCommand::PopScene { params } => {
+                    if self.stack.len() < 2 {
+                        warn!("nothing to pop beneath scene {}", self.current_scene());
+                        continue;
+                    }
+                    self.send_event(self.current_scene(), SceneEvent::Exit, &params)?;
+                    self.stack.pop();
+                    self.send_event(self.current_scene(), SceneEvent::Resume, &params)?;
+                    scene_switched = true;
                 }
-                _ => {}
+                GameSystemCommand::SetTimeScale(time_scale) => {
+                    info!("time scale {:?}", time_scale);
+                    self.time_scale = time_scale;
+                }
+                GameSystemCommand::StepOnce => self.extra_steps += 1,
+                GameSystemCommand::SetWindowMode(mode) => {
+                    if mode != self.window.mode {
+                        info!("window mode {:?}", mode);
+                        window_mode = Some(mode);
+                        let window = WindowState {
+                            mode,
+                            ..self.window
+                        };
+                        self.update_window(window, asset_manager)?;
+                    }
+                }
+                GameSystemCommand::Nothing => {}
             }
         }
+        if scene_switched {
+            // a scene frozen or slowed down for debugging must not leave the
+            // next one that way
+            self.accumulator = Duration::ZERO;
+            self.time_scale = TimeScale::default();
+            self.extra_steps = 0;
+        }
         // leave the loop instead of calling process::exit so that
         // destructors run and SDL shuts down cleanly
         if quit_requested {
-            is_running = false;
+            self.is_running = false;
+        }
+        if is_running && !self.is_running {
+            self.shutdown()?;
         }
-        frame_delay(&frame_start);
+        sound_effects.append(&mut self.event_sounds);
+        Ok(FrameOutput {
+            layers,
+            sound_effects,
+            scene_switched,
+            window_mode,
+        })
+    }
+
+    fn send_event(
+        &mut self,
+        id: SceneID,
+        event: SceneEvent,
+        params: &SceneParameters,
+    ) -> EngineResult<()> {
+        let scene = self.scenes.get_mut(&id).ok_or(EngineError::SceneNotFound)?;
+        let effects = scene.send_event(event, params)?;
+        self.event_sounds.extend(effects);
+        Ok(())
+    }
+
+    /// Tells every scene the world is stopping, the current one first and
+    /// those off the stack last
+    fn shutdown(&mut self) -> EngineResult<()> {
+        let mut ids = self.stack.iter().rev().copied().collect::<Vec<_>>();
+        let mut rest = self
+            .scenes
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        rest.sort_unstable();
+        ids.extend(rest);
+        let params = SceneParameters::default();
+        for id in ids {
+            self.send_event(id, SceneEvent::Shutdown, &params)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the whole steps the accumulated time pays for, and those asked
+    /// for by hand.
+    ///
+    /// The catch-up cap applies to the wall clock, before scaling, so
+    /// fast-forward isn't cut short by it.
+    fn steps_due(&mut self, elapsed: Duration) -> u32 {
+        let step = self.step_rate.step();
+        let elapsed = elapsed.min(step * self.timing.max_catch_up_steps);
+        if let TimeScale::Scaled(scale) = self.time_scale {
+            // in whole nanoseconds, which real time keeps exactly
+            let scaled = elapsed.as_nanos() as f64 * scale.max(0.0) as f64;
+            self.accumulator += Duration::from_nanos(scaled.round() as u64);
+        }
+        let mut steps = std::mem::take(&mut self.extra_steps);
+        while self.accumulator >= step {
+            self.accumulator -= step;
+            steps += 1;
+        }
+        steps
+    }
+
+    fn next_replayed_frame(&mut self) -> Option<RecordedFrame> {
+        let replay = self.replay.as_mut()?;
+        let frame = replay.next_frame();
+        if frame.is_none() {
+            info!("replay finished, back to live input");
+            self.replay = None;
+        }
+        frame
     }
-    Ok(())
 }
 
 /// Sleeps out the rest of the frame if the loop ran ahead of the render cap.
@@ -196,23 +561,58 @@ fn run(
 /// render cadence rather than a change in game speed. That makes a plain
 /// sleep preferable to burning a core on a spin-wait.
 #[inline(always)]
-fn frame_delay(frame_start: &Instant) {
+fn frame_delay(frame_start: &Instant, frame_duration: Duration) {
     let elapsed = frame_start.elapsed();
-    if elapsed < FRAME_DURATION {
-        std::thread::sleep(FRAME_DURATION - elapsed);
+    if elapsed < frame_duration {
+        std::thread::sleep(frame_duration - elapsed);
     }
 }
 
-/// Polls pending SDL events into `events`.
-/// Returns true if the application was asked to quit.
+/// The window as the scenes should see it: the logical `resolution` if
+/// there is one, or else the window's size in pixels rather than screen
+/// coordinates
+fn window_state(canvas: &WindowCanvas, resolution: Option<SizeU32>) -> EngineResult<WindowState> {
+    let size = match resolution {
+        Some(size) => size,
+        None => output_size(canvas)?,
+    };
+    let mode = match canvas.window().fullscreen_state() {
+        FullscreenType::Off => WindowMode::Windowed,
+        FullscreenType::True => WindowMode::Fullscreen,
+        FullscreenType::Desktop => WindowMode::Borderless,
+    };
+    Ok(WindowState { size, mode })
+}
+
+fn output_size(canvas: &WindowCanvas) -> EngineResult<SizeU32> {
+    let (width, height) = canvas.output_size().map_err(EngineError::Sdl)?;
+    Ok(SizeU32 { width, height })
+}
+
+/// A display that refuses a mode is no reason to end the run: the window
+/// stays as it was
+fn set_window_mode(canvas: &mut WindowCanvas, mode: WindowMode) {
+    let fullscreen = match mode {
+        WindowMode::Windowed => FullscreenType::Off,
+        WindowMode::Fullscreen => FullscreenType::True,
+        WindowMode::Borderless => FullscreenType::Desktop,
+    };
+    if let Err(err) = canvas.window_mut().set_fullscreen(fullscreen) {
+        warn!("failed to switch the window to {:?}: {}", mode, err);
+    }
+}
+
+/// Polls pending SDL events into `events`. Returns whether the window
+/// changed size.
 fn get_events(event_pump: &mut EventPump, events: &mut Vec<InputEvent>) -> bool {
-    let mut quit_requested = false;
+    let mut is_resized = false;
     for event in event_pump.poll_iter() {
         match event {
-            Event::Quit { .. } => {
-                quit_requested = true;
-                events.push(InputEvent::Quit);
-            }
+            Event::Quit { .. } => events.push(InputEvent::Quit),
+            Event::Window {
+                win_event: WindowEvent::SizeChanged(..),
+                ..
+            } => is_resized = true,
             Event::KeyDown {
                 keycode: Some(keycode),
                 ..
@@ -241,10 +641,30 @@ fn get_events(event_pump: &mut EventPump, events: &mut Vec<InputEvent>) -> bool
                     y_rel: yrel,
                 });
             }
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } => {
+                events.push(InputEvent::MouseButton {
+                    button: mouse_btn,
+                    pressed: true,
+                    x,
+                    y,
+                });
+            }
+            Event::MouseButtonUp {
+                mouse_btn, x, y, ..
+            } => {
+                events.push(InputEvent::MouseButton {
+                    button: mouse_btn,
+                    pressed: false,
+                    x,
+                    y,
+                });
+            }
             _ => {}
         }
     }
-    quit_requested
+    is_resized
 }
 
 fn play_sound_effects(effects: &[SoundEffect], asset_manager: &AssetManager) -> EngineResult<()> {
@@ -263,34 +683,73 @@ fn play_sound_effects(effects: &[SoundEffect], asset_manager: &AssetManager) ->
                     warn!("failed to play {}: {}", asset_id, err);
                 }
             }
+            SoundEffect::StopAll => sdl2::mixer::Channel::all().halt(),
         }
     }
     Ok(())
 }
 
+/// The off-screen picture the scenes draw at the logical resolution
+struct LogicalTarget<'a> {
+    texture: Texture<'a>,
+    size: SizeU32,
+    scaling: Scaling,
+}
+
+/// Draws the scenes to the window, or to `target` scaled to the window, and
+/// shows the result
+fn render_frame(
+    canvas: &mut WindowCanvas,
+    target: Option<&mut LogicalTarget>,
+    asset_manager: &AssetManager,
+    scene_layers: &[RendererLayersPtr],
+) -> EngineResult<()> {
+    let Some(target) = target else {
+        render_effects(canvas, asset_manager, scene_layers)?;
+        canvas.present();
+        return Ok(());
+    };
+    let mut result = Ok(());
+    canvas
+        .with_texture_canvas(&mut target.texture, |target_canvas| {
+            result = render_effects(target_canvas, asset_manager, scene_layers);
+        })
+        .map_err(|op| EngineError::Sdl(op.to_string()))?;
+    result?;
+    let viewport = target.scaling.viewport(target.size, output_size(canvas)?);
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+    canvas
+        .copy(&target.texture, None, viewport)
+        .map_err(EngineError::sdl)?;
+    canvas.present();
+    Ok(())
+}
+
 fn render_effects(
     canvas: &mut WindowCanvas,
     asset_manager: &AssetManager,
-    layers_ptr: RendererLayersPtr,
+    scene_layers: &[RendererLayersPtr],
 ) -> EngineResult<()> {
-    let mut layers = layers_ptr.borrow_mut();
     canvas.set_draw_color(Color::BLACK);
     canvas.clear();
-    for effect in &layers.background {
-        render_effect(canvas, asset_manager, effect)?;
-    }
+    for layers_ptr in scene_layers {
+        let mut layers = layers_ptr.borrow_mut();
+        for effect in &layers.background {
+            render_effect(canvas, asset_manager, effect)?;
+        }
 
-    layers
-        .depth
-        .sort_unstable_by(|a, b| b.depth.partial_cmp(&a.depth).unwrap_or(Ordering::Equal));
-    for depth_effect in &layers.depth {
-        render_effect(canvas, asset_manager, &depth_effect.effect)?;
-    }
+        layers
+            .depth
+            .sort_unstable_by(|a, b| b.depth.partial_cmp(&a.depth).unwrap_or(Ordering::Equal));
+        for depth_effect in &layers.depth {
+            render_effect(canvas, asset_manager, &depth_effect.effect)?;
+        }
 
-    for effect in &layers.hud {
-        render_effect(canvas, asset_manager, effect)?;
+        for effect in &layers.hud {
+            render_effect(canvas, asset_manager, effect)?;
+        }
     }
-    canvas.present();
     Ok(())
 }
 
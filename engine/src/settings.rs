@@ -1,14 +1,38 @@
+use std::time::Duration;
+
 use crate::prelude::{AudioFormat, AUDIO_S16LSB, DEFAULT_CHANNELS};
-use crate::{assets::AssetSource, geometry::SizeU32};
+use crate::{
+    assets::AssetSource,
+    geometry::SizeU32,
+    time::DEFAULT_STEP_RATE,
+    window::{Scaling, WindowMode},
+};
 
 pub struct EngineSettings {
     pub window: WindowSettings,
     pub asset_source: AssetSource,
     pub audio_setting: AudioSettings,
+    pub timing: TimingSettings,
 }
 pub struct WindowSettings {
     pub title: String,
+    /// The size of the window when it isn't fullscreen, in screen
+    /// coordinates; a high-DPI display gives it more pixels than that
     pub size: SizeU32,
+    /// The mode the window opens in; systems switch it with
+    /// `GameSystemCommand::SetWindowMode`
+    pub mode: WindowMode,
+    /// Whether the player may resize the window by dragging its border
+    pub resizable: bool,
+    /// The size the scenes draw at, scaled to the window, or `None` to draw
+    /// at the window's own. A fixed one keeps the cost of a frame the same
+    /// on any display.
+    pub resolution: Option<SizeU32>,
+    /// How the logical resolution is scaled to the window
+    pub scaling: Scaling,
+    /// Hides the cursor and keeps it in the window, reporting only how far
+    /// the mouse moved, for mouse look
+    pub capture_mouse: bool,
 }
 
 pub struct AudioSettings {
@@ -31,3 +55,36 @@ impl Default for AudioSettings {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimingSettings {
+    /// Simulation steps per second. Gameplay advances in whole steps, so
+    /// this is also how fine-grained its timing is.
+    pub step_rate: u32,
+    /// Most steps one iteration may catch up on. A long stall -- level
+    /// generation, a dragged window, a breakpoint -- would otherwise queue
+    /// a burst of steps and fast-forward the game.
+    pub max_catch_up_steps: u32,
+    /// Upper bound on iterations per second, or `None` to leave the pace to
+    /// vsync. Only matters when vsync is unavailable or the display
+    /// refreshes faster.
+    pub max_frame_rate: Option<u32>,
+}
+
+impl TimingSettings {
+    /// The longest one iteration may wait out, if there is a cap
+    pub fn frame_duration(&self) -> Option<Duration> {
+        self.max_frame_rate
+            .map(|rate| Duration::from_secs(1) / rate)
+    }
+}
+
+impl Default for TimingSettings {
+    fn default() -> Self {
+        Self {
+            step_rate: DEFAULT_STEP_RATE,
+            max_catch_up_steps: 5,
+            max_frame_rate: Some(DEFAULT_STEP_RATE),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use engine::{ComponentStorage, EntityBundle, Query};
+
+struct Health(u32);
+
+struct State;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Health>().unwrap();
+    storage.register_component::<State>().unwrap();
+    storage
+}
+
+#[test]
+fn set_stamps_added_and_changed() {
+    let mut storage = storage();
+    storage.set_tick(3);
+    let id = storage.append(EntityBundle::new().put(Health(1)));
+    assert!(storage.is_added_since::<Health>(id, 3));
+    assert!(storage.is_changed_since::<Health>(id, 3));
+    assert!(!storage.is_added_since::<Health>(id, 4));
+    assert!(!storage.is_changed_since::<State>(id, 0));
+
+    storage.set_tick(5);
+    storage.set(id, Some(Health(2))).unwrap();
+    // replacing is a change, not an addition
+    assert!(!storage.is_added_since::<Health>(id, 4));
+    assert!(storage.is_changed_since::<Health>(id, 5));
+}
+
+#[test]
+fn only_writes_through_get_mut_count() {
+    let mut storage = storage();
+    let id = storage.append(EntityBundle::new().put(Health(10)));
+    storage.set_tick(1);
+    {
+        let health = storage.get_mut::<Health>(id).unwrap();
+        assert_eq!(health.0, 10);
+    }
+    assert!(!storage.is_changed_since::<Health>(id, 1));
+
+    storage.get_mut::<Health>(id).unwrap().0 -= 1;
+    assert!(storage.is_changed_since::<Health>(id, 1));
+}
+
+#[test]
+fn mutable_query_stamps_what_it_writes() {
+    let mut storage = storage();
+    let ids = (0..4)
+        .map(|i| storage.append(EntityBundle::new().put(Health(i))))
+        .collect::<Vec<_>>();
+    storage.set_tick(7);
+    for (_, (mut health,)) in storage.iter::<(&mut Health,)>() {
+        if health.0 % 2 == 1 {
+            health.0 += 10;
+        }
+    }
+    let query = Query::new().changed_since::<Health>(7);
+    assert_eq!(storage.fetch_entities(&query), vec![ids[1], ids[3]]);
+    let changed = storage
+        .iter_filtered::<(&Health,)>(&query)
+        .map(|(_, (health,))| health.0)
+        .collect::<Vec<_>>();
+    assert_eq!(changed, vec![11, 13]);
+}
+
+#[test]
+fn query_by_addition() {
+    let mut storage = storage();
+    let old = storage.append(EntityBundle::new().put(State));
+    storage.set_tick(2);
+    let new = storage.append(EntityBundle::new().put(State));
+    storage.set(old, Some(State)).unwrap();
+
+    let query = Query::new().added_since::<State>(2);
+    assert_eq!(storage.fetch_entities(&query), vec![new]);
+    let query = Query::new().changed_since::<State>(2);
+    assert_eq!(storage.fetch_entities(&query), vec![old, new]);
+}
+
+#[test]
+fn removals_are_kept_for_two_steps() {
+    let mut storage = storage();
+    let detached = storage.append(EntityBundle::new().put(Health(1)).put(State));
+    let removed = storage.append(EntityBundle::new().put(Health(2)));
+    let bare = storage.add_entity();
+
+    storage.set_tick(1);
+    storage.set::<Health>(detached, None).unwrap();
+    // dropping a component that isn't there removes nothing
+    storage.set::<Health>(bare, None).unwrap();
+    storage.set_tick(2);
+    storage.remove_entity(removed);
+
+    assert_eq!(storage.removed_since::<Health>(1), vec![detached, removed]);
+    assert_eq!(storage.removed_since::<Health>(2), vec![removed]);
+    assert!(storage.removed_since::<State>(0).is_empty());
+
+    storage.set_tick(3);
+    assert_eq!(storage.removed_since::<Health>(0), vec![removed]);
+    storage.set_tick(4);
+    assert!(storage.removed_since::<Health>(0).is_empty());
+}
@@ -21,9 +21,11 @@ fn every_variant_says_something() {
     let errors = [
         EngineError::ComponentNotRegistered,
         EngineError::ComponentAlreadyRegistered,
-        EngineError::ComponentCountOverflow,
         EngineError::component_not_found("Velocity"),
+        EngineError::EventNotRegistered,
         EngineError::EntityNotAlive(dead),
+        EngineError::HierarchyCycle(dead),
+        EngineError::InvalidSchedule("no system has label 'movement'".into()),
         EngineError::TextureNotFound("wall1".into()),
         EngineError::SceneNotFound,
         EngineError::FileAccessError("assets.bin".into()),
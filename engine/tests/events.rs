@@ -0,0 +1,55 @@
+use engine::{ComponentStorage, EngineError, EventReader};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Shot(u32);
+
+#[derive(Clone)]
+struct Unregistered;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_event::<Shot>();
+    // registering twice keeps the queue
+    storage.send(Shot(0)).unwrap();
+    storage.register_event::<Shot>();
+    storage
+}
+
+#[test]
+fn each_reader_sees_every_event_once() {
+    let storage = storage();
+    let mut first = EventReader::<Shot>::new();
+    let mut second = EventReader::<Shot>::new();
+    storage.send(Shot(1)).unwrap();
+    assert_eq!(storage.read(&mut first), vec![Shot(0), Shot(1)]);
+    assert!(storage.read(&mut first).is_empty());
+
+    storage.send(Shot(2)).unwrap();
+    assert_eq!(storage.read(&mut first), vec![Shot(2)]);
+    assert_eq!(storage.read(&mut second), vec![Shot(0), Shot(1), Shot(2)]);
+}
+
+#[test]
+fn events_are_kept_through_the_next_step() {
+    let mut storage = storage();
+    let mut late = EventReader::<Shot>::new();
+    storage.set_tick(1);
+    storage.send(Shot(1)).unwrap();
+    storage.set_tick(2);
+    assert_eq!(storage.read(&mut late), vec![Shot(1)]);
+
+    let mut later = EventReader::<Shot>::new();
+    storage.set_tick(3);
+    assert!(storage.read(&mut later).is_empty());
+}
+
+#[test]
+fn send_unregistered_event_fails() {
+    let storage = storage();
+    assert!(matches!(
+        storage.send(Unregistered),
+        Err(EngineError::EventNotRegistered)
+    ));
+    let mut reader = EventReader::<Unregistered>::new();
+    assert!(storage.read(&mut reader).is_empty());
+}
@@ -0,0 +1,592 @@
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc};
+
+use engine::{
+    assets::AssetSource,
+    game_scene::{GameScene, SceneEvent, SceneParameters},
+    prelude::{BlendMode, Color, Rect},
+    systems::{
+        GameControlSystem, GameRendererSystem, GameSoundSystem, GameSystem, GameSystemCommand,
+        InputEvent, RendererEffect, RendererLayers, RendererLayersPtr, SoundEffect,
+    },
+    time::{StepRate, TimeScale},
+    window::{Scaling, WindowMode},
+    world::GameWorld,
+    AssetManager, AudioSettings, ComponentStorage, EngineResult, EngineSettings, Float, SizeU32,
+    TimingSettings, WindowSettings,
+};
+
+const SCENE_FIRST: u8 = 1;
+const SCENE_SECOND: u8 = 2;
+
+/// Counts its steps and leaves for `next` once it has run `switch_after`.
+struct CountingSystem {
+    steps: Rc<RefCell<usize>>,
+    switch_after: usize,
+    next: Option<u8>,
+    entered: Rc<RefCell<usize>>,
+}
+
+impl GameSystem for CountingSystem {
+    fn setup(&mut self, _: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        _frames: usize,
+        _delta_time: Float,
+        _storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        *self.steps.borrow_mut() += 1;
+        let steps = *self.steps.borrow();
+        match self.next {
+            Some(id) if steps == self.switch_after => Ok(GameSystemCommand::SwitchScene {
+                id,
+                params: SceneParameters::default(),
+            }),
+            None if steps == self.switch_after => Ok(GameSystemCommand::Terminate),
+            _ => Ok(GameSystemCommand::Nothing),
+        }
+    }
+
+    fn on_scene_event(
+        &mut self,
+        _storage: &mut ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        if event == SceneEvent::Enter {
+            *self.entered.borrow_mut() += 1;
+        }
+        Ok(())
+    }
+}
+
+struct NoControl;
+
+impl GameControlSystem for NoControl {
+    fn setup(&mut self, _: &ComponentStorage) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn push_events(&mut self, _: &mut ComponentStorage, _: &[InputEvent]) -> EngineResult<()> {
+        Ok(())
+    }
+}
+
+/// Draws one rectangle as wide as the frame counter.
+struct FrameRenderer {
+    layers: RendererLayersPtr,
+}
+
+impl GameRendererSystem for FrameRenderer {
+    fn setup(&mut self, _: &ComponentStorage, _: &AssetManager, _: SizeU32) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        frames: usize,
+        _storage: &ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<RendererLayersPtr> {
+        let mut layers = self.layers.borrow_mut();
+        layers.clear();
+        layers.push_hud(RendererEffect::Rectangle {
+            color: Color::RED,
+            fill: true,
+            blend_mode: BlendMode::None,
+            rect: Rect::new(0, 0, frames as u32 + 1, 1),
+        });
+        Ok(self.layers.clone())
+    }
+}
+
+struct BeepEveryFrame;
+
+impl GameSoundSystem for BeepEveryFrame {
+    fn setup(&mut self, _: &ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        _: &mut ComponentStorage,
+        _: &AssetManager,
+    ) -> EngineResult<Vec<SoundEffect>> {
+        Ok(vec![SoundEffect::PlaySound {
+            asset_id: "beep".to_string(),
+            loops: 0,
+        }])
+    }
+
+    fn on_scene_event(
+        &mut self,
+        _: &mut ComponentStorage,
+        event: SceneEvent,
+        _: &SceneParameters,
+    ) -> EngineResult<Vec<SoundEffect>> {
+        match event {
+            SceneEvent::Shutdown => Ok(vec![SoundEffect::StopAll]),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+struct Probe {
+    steps: Rc<RefCell<usize>>,
+    entered: Rc<RefCell<usize>>,
+}
+
+fn scene(id: u8, switch_after: usize, next: Option<u8>) -> (GameScene, Probe) {
+    let probe = Probe {
+        steps: Default::default(),
+        entered: Default::default(),
+    };
+    let renderer = FrameRenderer {
+        layers: Rc::new(RefCell::new(RendererLayers::default())),
+    };
+    let mut scene = GameScene::new(id, ComponentStorage::new(), NoControl, renderer);
+    scene.add_sound_system(BeepEveryFrame);
+    scene.add_system(CountingSystem {
+        steps: probe.steps.clone(),
+        switch_after,
+        next,
+        entered: probe.entered.clone(),
+    });
+    (scene, probe)
+}
+
+fn settings(name: &str) -> (EngineSettings, PathBuf) {
+    let mut path = std::env::temp_dir();
+    path.push(format!("inferis_headless_{}_{}", std::process::id(), name));
+    fs::write(&path, "color clr1 1,2,3\n").expect("failed to write temp registry");
+    let settings = EngineSettings {
+        window: WindowSettings {
+            title: "headless".to_string(),
+            size: SizeU32 {
+                width: 320,
+                height: 200,
+            },
+            mode: WindowMode::Windowed,
+            resizable: false,
+            resolution: None,
+            scaling: Scaling::Aspect,
+            capture_mouse: false,
+        },
+        asset_source: AssetSource::with_folder(path.to_str().unwrap()),
+        audio_setting: AudioSettings::default(),
+        timing: TimingSettings::default(),
+    };
+    (settings, path)
+}
+
+#[test]
+fn steps_scenes_and_collects_their_output() {
+    let (settings, path) = settings("collect.txt");
+    let (first, probe) = scene(SCENE_FIRST, usize::MAX, None);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+    assert!(world.asset_manager().color("clr1").is_some());
+
+    let frames = world.run(10).unwrap();
+    assert_eq!(frames.len(), 10);
+    assert_eq!(*probe.steps.borrow(), 10);
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.scene, SCENE_FIRST);
+        assert_eq!(frame.sound_effects.len(), 1);
+        // each frame is a snapshot, not a view of the renderer's buffer
+        let RendererEffect::Rectangle { rect, .. } = &frame.layers.hud[0] else {
+            panic!("unexpected effect {:?}", frame.layers.hud[0]);
+        };
+        assert_eq!(rect.width(), i as u32 + 2);
+    }
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn honours_switch_scene_and_terminate() {
+    let (settings, path) = settings("switch.txt");
+    let (first, first_probe) = scene(SCENE_FIRST, 3, Some(SCENE_SECOND));
+    let (second, second_probe) = scene(SCENE_SECOND, 2, None);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .with_scene(second)
+        .headless(&settings)
+        .unwrap();
+
+    let frames = world.run(100).unwrap();
+    assert!(!world.is_running());
+    assert_eq!(frames.len(), 5);
+    assert_eq!(*first_probe.steps.borrow(), 3);
+    assert_eq!(*first_probe.entered.borrow(), 1);
+    assert_eq!(*second_probe.entered.borrow(), 1);
+    assert_eq!(*second_probe.steps.borrow(), 2);
+    assert_eq!(frames[2].scene, SCENE_FIRST);
+    assert_eq!(frames[3].scene, SCENE_SECOND);
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn quit_event_stops_the_world() {
+    let (settings, path) = settings("quit.txt");
+    let (first, _) = scene(SCENE_FIRST, usize::MAX, None);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+    world.step(&[]).unwrap();
+    assert!(world.is_running());
+    world.step(&[InputEvent::Quit]).unwrap();
+    assert!(!world.is_running());
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn catch_up_is_capped() {
+    let (settings, path) = settings("catch_up.txt");
+    let (first, probe) = scene(SCENE_FIRST, usize::MAX, None);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+    world
+        .advance(std::time::Duration::from_secs(10), &[])
+        .unwrap();
+    assert_eq!(*probe.steps.borrow(), 5);
+    _ = fs::remove_file(path);
+}
+
+/// Hands the run loop one command per iteration, in order
+struct TimeKeys(Vec<GameSystemCommand>);
+
+impl GameControlSystem for TimeKeys {
+    fn setup(&mut self, _: &ComponentStorage) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn push_events(&mut self, _: &mut ComponentStorage, _: &[InputEvent]) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn command(&mut self) -> GameSystemCommand {
+        if self.0.is_empty() {
+            return GameSystemCommand::Nothing;
+        }
+        self.0.remove(0)
+    }
+}
+
+#[test]
+fn time_scale_slows_freezes_and_single_steps() {
+    use GameSystemCommand::*;
+    let (settings, path) = settings("time_scale.txt");
+    let (mut first, probe) = scene(SCENE_FIRST, usize::MAX, None);
+    first.add_control_system(TimeKeys(vec![
+        SetTimeScale(TimeScale::Scaled(0.5)),
+        Nothing,
+        Nothing,
+        Nothing,
+        SetTimeScale(TimeScale::Frozen),
+        Nothing,
+        StepOnce,
+    ]));
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+
+    let mut steps = Vec::new();
+    for _ in 0..8 {
+        world.step(&[]).unwrap();
+        steps.push(*probe.steps.borrow());
+    }
+    // a command takes effect on the iteration after the one it came in
+    assert_eq!(steps, vec![1, 1, 2, 2, 3, 3, 3, 4]);
+    assert_eq!(world.time_scale(), TimeScale::Frozen);
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn scenes_beneath_the_top_one_are_billed_as_paused() {
+    let (settings, path) = settings("time.txt");
+    let push = GameSystemCommand::PushScene {
+        id: SCENE_SECOND,
+        params: SceneParameters::default(),
+    };
+    let (first, _) = scripted_scene(SCENE_FIRST, vec![(2, push)]);
+    let (second, _) = scripted_scene(SCENE_SECOND, vec![]);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .with_scene(second)
+        .headless(&settings)
+        .unwrap();
+
+    world.run(4).unwrap();
+    let step = world.step_rate().step();
+    let first = world.time(SCENE_FIRST).unwrap();
+    assert_eq!(first.step(), 2);
+    assert!(first.is_paused());
+    assert_eq!(first.real(), step * 4);
+    assert_eq!(first.paused(), step * 2);
+    let second = world.time(SCENE_SECOND).unwrap();
+    assert_eq!(second.step(), 2);
+    assert!(!second.is_paused());
+    assert_eq!(second.real(), step * 2);
+    assert!(second.paused().is_zero());
+    assert_eq!(second.game().as_millis(), 33);
+    assert_eq!(second.since(1).as_millis(), 16);
+    assert!(second.since(10).is_zero());
+
+    let deadline = second.after(std::time::Duration::from_millis(50));
+    assert_eq!(deadline, 5);
+    assert!(!second.is_due(deadline));
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn step_rate_comes_from_the_settings() {
+    let (mut settings, path) = settings("step_rate.txt");
+    settings.timing.step_rate = 30;
+    let (first, probe) = scene(SCENE_FIRST, usize::MAX, None);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+    assert_eq!(world.step_rate(), StepRate(30));
+    world
+        .advance(std::time::Duration::from_millis(100), &[])
+        .unwrap();
+    assert_eq!(*probe.steps.borrow(), 3);
+
+    let rate = StepRate(30);
+    assert_eq!(rate.steps(std::time::Duration::from_secs(2)), 60);
+    // a short deadline still takes a step
+    assert_eq!(rate.steps(std::time::Duration::from_millis(1)), 1);
+    assert_eq!(rate.steps(std::time::Duration::ZERO), 0);
+
+    settings.timing.step_rate = 0;
+    assert!(GameWorld::new().headless(&settings).is_err());
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn extra_renderers_and_sound_systems_add_to_the_output() {
+    let (settings, path) = settings("merge.txt");
+    let (mut first, _) = scene(SCENE_FIRST, usize::MAX, None);
+    first.add_renderer_system(FrameRenderer {
+        layers: Rc::new(RefCell::new(RendererLayers::default())),
+    });
+    first.add_sound_system(BeepEveryFrame);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+
+    let frames = world.run(2).unwrap();
+    for frame in frames {
+        assert_eq!(frame.layers.hud.len(), 2);
+        assert_eq!(frame.sound_effects.len(), 2);
+    }
+    // the sound system is told the world stops, and silences the mixer
+    let frame = world.step(&[InputEvent::Quit]).unwrap();
+    assert_eq!(frame.sound_effects.last(), Some(&SoundEffect::StopAll));
+    _ = fs::remove_file(path);
+}
+
+/// Returns the scripted command on the step it's given for
+struct Scripted {
+    steps: usize,
+    script: Vec<(usize, GameSystemCommand)>,
+    events: Rc<RefCell<Vec<SceneEvent>>>,
+}
+
+impl GameSystem for Scripted {
+    fn setup(&mut self, _: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        _frames: usize,
+        _delta_time: Float,
+        _storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        self.steps += 1;
+        match self.script.iter().position(|(step, _)| *step == self.steps) {
+            Some(index) => Ok(self.script.remove(index).1),
+            None => Ok(GameSystemCommand::Nothing),
+        }
+    }
+
+    fn on_scene_event(
+        &mut self,
+        _storage: &mut ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        self.events.borrow_mut().push(event);
+        Ok(())
+    }
+}
+
+fn scripted_scene(
+    id: u8,
+    script: Vec<(usize, GameSystemCommand)>,
+) -> (GameScene, Rc<RefCell<Vec<SceneEvent>>>) {
+    let renderer = FrameRenderer {
+        layers: Rc::new(RefCell::new(RendererLayers::default())),
+    };
+    let mut scene = GameScene::new(id, ComponentStorage::new(), NoControl, renderer);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    scene.add_system(Scripted {
+        steps: 0,
+        script,
+        events: events.clone(),
+    });
+    (scene, events)
+}
+
+#[test]
+fn pushed_scene_draws_over_the_frozen_one() {
+    let (settings, path) = settings("stack.txt");
+    let params = SceneParameters::default;
+    let push = GameSystemCommand::PushScene {
+        id: SCENE_SECOND,
+        params: params(),
+    };
+    let (first, first_events) = scripted_scene(SCENE_FIRST, vec![(2, push)]);
+    let pop = GameSystemCommand::PopScene { params: params() };
+    let (second, second_events) = scripted_scene(SCENE_SECOND, vec![(2, pop)]);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .with_scene(second)
+        .headless(&settings)
+        .unwrap();
+
+    let frames = world.run(2).unwrap();
+    assert_eq!(world.scene_stack(), &[SCENE_FIRST, SCENE_SECOND]);
+    assert!(frames.iter().all(|frame| frame.covered.is_empty()));
+    let frames = world.run(2).unwrap();
+    for frame in &frames {
+        assert_eq!(frame.scene, SCENE_SECOND);
+        // the scene beneath is drawn as it was when it stopped
+        let RendererEffect::Rectangle { rect, .. } = &frame.covered[0].hud[0] else {
+            panic!("unexpected effect {:?}", frame.covered[0].hud[0]);
+        };
+        assert_eq!(rect.width(), 3);
+    }
+    assert_eq!(world.scene_stack(), &[SCENE_FIRST]);
+    let frame = world.step(&[]).unwrap();
+    assert!(frame.covered.is_empty());
+    let RendererEffect::Rectangle { rect, .. } = &frame.layers.hud[0] else {
+        panic!("unexpected effect {:?}", frame.layers.hud[0]);
+    };
+    assert_eq!(rect.width(), 4);
+    assert_eq!(
+        *first_events.borrow(),
+        vec![SceneEvent::Enter, SceneEvent::Suspend, SceneEvent::Resume]
+    );
+    assert_eq!(
+        *second_events.borrow(),
+        vec![SceneEvent::Enter, SceneEvent::Exit]
+    );
+    // scenes off the stack are told too
+    world.step(&[InputEvent::Quit]).unwrap();
+    assert_eq!(first_events.borrow().last(), Some(&SceneEvent::Shutdown));
+    assert_eq!(second_events.borrow().last(), Some(&SceneEvent::Shutdown));
+    _ = fs::remove_file(path);
+}
+
+/// Remembers every size it was laid out for
+struct SizedRenderer {
+    layers: RendererLayersPtr,
+    sizes: Rc<RefCell<Vec<SizeU32>>>,
+}
+
+impl GameRendererSystem for SizedRenderer {
+    fn setup(&mut self, _: &ComponentStorage, _: &AssetManager, size: SizeU32) -> EngineResult<()> {
+        self.sizes.borrow_mut().push(size);
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        _frames: usize,
+        _storage: &ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<RendererLayersPtr> {
+        Ok(self.layers.clone())
+    }
+}
+
+#[test]
+fn resize_lays_out_every_scene_again() {
+    let (settings, path) = settings("resize.txt");
+    let borderless = GameSystemCommand::SetWindowMode(WindowMode::Borderless);
+    let (mut first, first_events) = scripted_scene(SCENE_FIRST, vec![(1, borderless)]);
+    let sizes = Rc::new(RefCell::new(Vec::new()));
+    first.add_renderer_system(SizedRenderer {
+        layers: Rc::new(RefCell::new(RendererLayers::default())),
+        sizes: sizes.clone(),
+    });
+    let (second, second_events) = scripted_scene(SCENE_SECOND, vec![]);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .with_scene(second)
+        .headless(&settings)
+        .unwrap();
+    assert_eq!(world.window().mode, WindowMode::Windowed);
+
+    world.step(&[]).unwrap();
+    assert_eq!(world.window().mode, WindowMode::Borderless);
+    let small = SizeU32 {
+        width: 320,
+        height: 200,
+    };
+    let large = SizeU32 {
+        width: 640,
+        height: 360,
+    };
+    assert_eq!(world.window().size, small);
+    world.resize(large).unwrap();
+    // the same size again is no resize
+    world.resize(large).unwrap();
+    assert_eq!(*sizes.borrow(), vec![small, large]);
+    assert_eq!(
+        *first_events.borrow(),
+        vec![SceneEvent::Enter, SceneEvent::Resized(large)]
+    );
+    // scenes off the stack are laid out too, so they are ready when entered
+    assert_eq!(*second_events.borrow(), vec![SceneEvent::Resized(large)]);
+    _ = fs::remove_file(path);
+}
+
+#[test]
+fn a_logical_resolution_hides_the_window_size() {
+    let (mut settings, path) = settings("resolution.txt");
+    let logical = SizeU32 {
+        width: 160,
+        height: 100,
+    };
+    settings.window.resolution = Some(logical);
+    let (first, events) = scripted_scene(SCENE_FIRST, vec![]);
+    let mut world = GameWorld::new()
+        .with_scene(first)
+        .headless(&settings)
+        .unwrap();
+    assert_eq!(world.window().size, logical);
+    world
+        .resize(SizeU32 {
+            width: 1920,
+            height: 1080,
+        })
+        .unwrap();
+    assert_eq!(world.window().size, logical);
+    assert_eq!(*events.borrow(), vec![SceneEvent::Enter]);
+    _ = fs::remove_file(path);
+}
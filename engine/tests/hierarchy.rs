@@ -0,0 +1,121 @@
+use std::ops::Add;
+
+use engine::{ComponentStorage, EngineError, EntityBundle, Query};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Position(i32, i32);
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, rhs: Position) -> Position {
+        Position(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+struct Light;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Position>().unwrap();
+    storage.register_component::<Light>().unwrap();
+    storage
+}
+
+#[test]
+fn children_go_with_their_parent() {
+    let mut storage = storage();
+    let soldier = storage.append(EntityBundle::new().put(Position(1, 1)));
+    let weapon = storage.add_entity();
+    let flash = storage.append(EntityBundle::new().put(Light));
+    let bystander = storage.add_entity();
+    storage.set_parent(weapon, Some(soldier)).unwrap();
+    storage.set_parent(flash, Some(weapon)).unwrap();
+    assert_eq!(storage.parent(flash), Some(weapon));
+    assert_eq!(storage.children(soldier), &[weapon]);
+
+    assert!(storage.remove_entity(soldier));
+    assert!(!storage.is_alive(weapon));
+    assert!(!storage.is_alive(flash));
+    assert!(storage.is_alive(bystander));
+    assert_eq!(storage.len(), 1);
+    // the removals are tracked like any other
+    assert_eq!(storage.removed_since::<Light>(0), vec![flash]);
+}
+
+#[test]
+fn detached_child_stays() {
+    let mut storage = storage();
+    let torch = storage.add_entity();
+    let light = storage.append(EntityBundle::new().put(Light));
+    let other = storage.add_entity();
+    storage.set_parent(light, Some(torch)).unwrap();
+    // attaching again moves the child
+    storage.set_parent(light, Some(other)).unwrap();
+    assert!(storage.children(torch).is_empty());
+    storage.set_parent(light, None).unwrap();
+    assert_eq!(storage.parent(light), None);
+
+    storage.remove_entity(other);
+    assert!(storage.is_alive(light));
+}
+
+#[test]
+fn world_position_adds_up_offsets() {
+    let mut storage = storage();
+    let soldier = storage.append(EntityBundle::new().put(Position(10, 20)));
+    let holster = storage.add_entity();
+    let weapon = storage.append(EntityBundle::new().put(Position(1, -1)));
+    storage.set_parent(holster, Some(soldier)).unwrap();
+    storage.set_parent(weapon, Some(holster)).unwrap();
+    assert_eq!(storage.world_position(weapon), Some(Position(11, 19)));
+    assert_eq!(storage.world_position::<Position>(holster), None);
+
+    // an attachment follows its parent
+    storage.get_mut::<Position>(soldier).unwrap().0 += 5;
+    assert_eq!(storage.world_position(weapon), Some(Position(16, 19)));
+}
+
+#[test]
+fn invalid_links_are_refused() {
+    let mut storage = storage();
+    let a = storage.add_entity();
+    let b = storage.add_entity();
+    let dead = storage.add_entity();
+    storage.remove_entity(dead);
+    storage.set_parent(b, Some(a)).unwrap();
+
+    assert!(matches!(
+        storage.set_parent(a, Some(b)),
+        Err(EngineError::HierarchyCycle(id)) if id == a
+    ));
+    assert!(matches!(
+        storage.set_parent(a, Some(a)),
+        Err(EngineError::HierarchyCycle(_))
+    ));
+    assert!(matches!(
+        storage.set_parent(a, Some(dead)),
+        Err(EngineError::EntityNotAlive(id)) if id == dead
+    ));
+    assert_eq!(storage.parent(a), None);
+}
+
+#[test]
+fn spawn_child_while_iterating() {
+    let mut storage = storage();
+    let torches = (0..2)
+        .map(|i| storage.append(EntityBundle::new().put(Position(i, 0))))
+        .collect::<Vec<_>>();
+    for (id, _) in storage.iter::<(&Position,)>() {
+        storage
+            .commands()
+            .spawn_child(id, EntityBundle::new().put(Light).put(Position(0, 1)));
+    }
+    storage.commands().despawn(torches[1]);
+    storage.apply_commands().unwrap();
+
+    let lights = storage.fetch_entities(&Query::new().with_component::<Light>());
+    assert_eq!(lights.len(), 1);
+    assert_eq!(storage.parent(lights[0]), Some(torches[0]));
+    assert_eq!(storage.world_position(lights[0]), Some(Position(0, 1)));
+}
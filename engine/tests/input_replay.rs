@@ -0,0 +1,279 @@
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc, time::Duration};
+
+use engine::{
+    assets::AssetSource,
+    game_scene::GameScene,
+    prelude::{Keycode, MouseButton},
+    replay::{InputRecorder, InputReplay},
+    systems::{
+        GameControlSystem, GameRendererSystem, GameSystem, GameSystemCommand, InputEvent,
+        RendererLayers, RendererLayersPtr,
+    },
+    window::{Scaling, WindowMode},
+    world::GameWorld,
+    AssetManager, AudioSettings, ComponentStorage, EngineError, EngineResult, EngineSettings,
+    Float, SizeU32, TimingSettings, WindowSettings,
+};
+
+/// Logs every event it is handed, tagged with the step count at that time.
+struct EventLog {
+    log: Rc<RefCell<Vec<(usize, InputEvent)>>>,
+    steps: Rc<RefCell<usize>>,
+}
+
+impl GameControlSystem for EventLog {
+    fn setup(&mut self, _: &ComponentStorage) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn push_events(&mut self, _: &mut ComponentStorage, events: &[InputEvent]) -> EngineResult<()> {
+        let steps = *self.steps.borrow();
+        self.log
+            .borrow_mut()
+            .extend(events.iter().map(|event| (steps, *event)));
+        Ok(())
+    }
+}
+
+struct StepCounter {
+    steps: Rc<RefCell<usize>>,
+}
+
+impl GameSystem for StepCounter {
+    fn setup(&mut self, _: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        _frames: usize,
+        _delta_time: Float,
+        _storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        *self.steps.borrow_mut() += 1;
+        Ok(GameSystemCommand::Nothing)
+    }
+}
+
+struct NoRenderer {
+    layers: RendererLayersPtr,
+}
+
+impl GameRendererSystem for NoRenderer {
+    fn setup(&mut self, _: &ComponentStorage, _: &AssetManager, _: SizeU32) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        _frames: usize,
+        _storage: &ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<RendererLayersPtr> {
+        Ok(self.layers.clone())
+    }
+}
+
+#[derive(Default)]
+struct Probe {
+    log: Rc<RefCell<Vec<(usize, InputEvent)>>>,
+    steps: Rc<RefCell<usize>>,
+}
+
+fn world(probe: &Probe) -> GameWorld {
+    let control = EventLog {
+        log: probe.log.clone(),
+        steps: probe.steps.clone(),
+    };
+    let renderer = NoRenderer {
+        layers: Rc::new(RefCell::new(RendererLayers::default())),
+    };
+    let mut scene = GameScene::new(1, ComponentStorage::new(), control, renderer);
+    scene.add_system(StepCounter {
+        steps: probe.steps.clone(),
+    });
+    GameWorld::new().with_scene(scene)
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("inferis_replay_{}_{}", std::process::id(), name));
+    path
+}
+
+fn settings(name: &str) -> (EngineSettings, PathBuf) {
+    let path = temp_path(name);
+    fs::write(&path, "color clr1 1,2,3\n").expect("failed to write temp registry");
+    let settings = EngineSettings {
+        window: WindowSettings {
+            title: "replay".to_string(),
+            size: SizeU32 {
+                width: 320,
+                height: 200,
+            },
+            mode: WindowMode::Windowed,
+            resizable: false,
+            resolution: None,
+            scaling: Scaling::Aspect,
+            capture_mouse: false,
+        },
+        asset_source: AssetSource::with_folder(path.to_str().unwrap()),
+        audio_setting: AudioSettings::default(),
+        timing: TimingSettings::default(),
+    };
+    (settings, path)
+}
+
+fn key(code: Keycode, pressed: bool) -> InputEvent {
+    InputEvent::Keyboard { code, pressed }
+}
+
+#[test]
+fn replay_reproduces_recorded_session() {
+    let (settings, registry) = settings("session.txt");
+    let recording = temp_path("session.rec");
+    let recording_path = recording.to_str().unwrap();
+
+    // an uneven wall clock: some iterations run no step, some several
+    let session = [
+        (16_700, vec![key(Keycode::W, true)]),
+        (5_000, vec![]),
+        (40_000, vec![key(Keycode::LEFT, true)]),
+        (1_000_000, vec![]),
+        (
+            16_700,
+            vec![
+                key(Keycode::W, false),
+                InputEvent::Mouse {
+                    x: 10,
+                    y: -3,
+                    x_rel: 4,
+                    y_rel: -1,
+                },
+            ],
+        ),
+        (
+            0,
+            vec![
+                key(Keycode::LEFT, false),
+                InputEvent::MouseButton {
+                    button: MouseButton::Left,
+                    pressed: true,
+                    x: 10,
+                    y: -3,
+                },
+            ],
+        ),
+    ];
+    let recorded = Probe::default();
+    {
+        let params = [("mouse_sensitivity", "1.5"), ("mouse_invert", "")];
+        let recorder = InputRecorder::create_with(recording_path, 0xDEAD_BEEF, &params).unwrap();
+        let mut world = world(&recorded)
+            .with_recorder(recorder)
+            .headless(&settings)
+            .unwrap();
+        for (micros, events) in &session {
+            world
+                .advance(Duration::from_micros(*micros), events)
+                .unwrap();
+        }
+        // dropping the world flushes the recording
+    }
+
+    let replay = InputReplay::open(recording_path).unwrap();
+    assert_eq!(replay.seed(), 0xDEAD_BEEF);
+    assert_eq!(replay.param("mouse_sensitivity"), Some("1.5"));
+    assert_eq!(replay.param("mouse_invert"), Some(""));
+    assert_eq!(replay.param("volume"), None);
+    assert_eq!(replay.remaining(), session.len());
+
+    let replayed = Probe::default();
+    let mut world = world(&replayed)
+        .with_replay(replay)
+        .headless(&settings)
+        .unwrap();
+    for _ in 0..session.len() {
+        // live input is ignored while the recording plays
+        world.step(&[key(Keycode::X, true)]).unwrap();
+    }
+    assert_eq!(*replayed.steps.borrow(), *recorded.steps.borrow());
+    assert_eq!(*replayed.log.borrow(), *recorded.log.borrow());
+    assert!(world.is_replaying());
+
+    // past the end of the recording the world is back on live input
+    world.step(&[key(Keycode::X, true)]).unwrap();
+    assert!(!world.is_replaying());
+    assert_eq!(
+        replayed.log.borrow().last(),
+        Some(&(*recorded.steps.borrow(), key(Keycode::X, true)))
+    );
+
+    _ = fs::remove_file(registry);
+    _ = fs::remove_file(recording);
+}
+
+#[test]
+fn live_quit_interrupts_replay() {
+    let (settings, registry) = settings("quit.txt");
+    let recording = temp_path("quit.rec");
+    let recording_path = recording.to_str().unwrap();
+    {
+        let mut recorder = InputRecorder::create(recording_path, 1).unwrap();
+        for _ in 0..10 {
+            recorder.record(1, &[]).unwrap();
+        }
+    }
+    let probe = Probe::default();
+    let mut world = world(&probe)
+        .with_replay(InputReplay::open(recording_path).unwrap())
+        .headless(&settings)
+        .unwrap();
+    world.step(&[]).unwrap();
+    world.step(&[InputEvent::Quit]).unwrap();
+    assert!(!world.is_running());
+    assert_eq!(*probe.steps.borrow(), 2);
+
+    _ = fs::remove_file(registry);
+    _ = fs::remove_file(recording);
+}
+
+#[test]
+fn damaged_recording_is_rejected() {
+    let recording = temp_path("damaged.rec");
+    let recording_path = recording.to_str().unwrap();
+    {
+        let mut recorder = InputRecorder::create(recording_path, 7).unwrap();
+        recorder.record(2, &[key(Keycode::W, true)]).unwrap();
+    }
+    let mut bytes = fs::read(&recording).unwrap();
+    // cut the last event short
+    bytes.truncate(bytes.len() - 1);
+    fs::write(&recording, &bytes).unwrap();
+    assert!(matches!(
+        InputReplay::open(recording_path),
+        Err(EngineError::ResourceParseError(_))
+    ));
+
+    // a recording of another version, its header otherwise intact
+    let mut bytes = fs::read(&recording).unwrap();
+    bytes[4] = 1;
+    fs::write(&recording, &bytes).unwrap();
+    assert!(matches!(
+        InputReplay::open(recording_path),
+        Err(EngineError::ResourceParseError(_))
+    ));
+
+    fs::write(&recording, b"not a recording").unwrap();
+    assert!(matches!(
+        InputReplay::open(recording_path),
+        Err(EngineError::ResourceParseError(_))
+    ));
+
+    assert!(matches!(
+        InputReplay::open("/nonexistent/inferis.rec"),
+        Err(EngineError::FileAccessError(_))
+    ));
+    _ = fs::remove_file(recording);
+}
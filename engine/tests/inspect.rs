@@ -0,0 +1,81 @@
+use engine::{ComponentStorage, EngineError, EntityBundle};
+
+#[derive(Debug)]
+struct Health(u32);
+
+struct Tag;
+
+mod nested {
+    #[derive(Debug)]
+    pub struct Wrapper<T>(pub T);
+}
+
+use nested::Wrapper;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Health>().unwrap();
+    storage.register_component::<Tag>().unwrap();
+    storage.register_component::<Wrapper<Health>>().unwrap();
+    storage.register_debug::<Health>().unwrap();
+    storage
+}
+
+#[test]
+fn inspect_lists_components_in_registration_order() {
+    let mut storage = storage();
+    let id = storage.append(
+        EntityBundle::new()
+            .put(Wrapper(Health(1)))
+            .put(Tag)
+            .put(Health(7)),
+    );
+    assert_eq!(
+        storage.component_names(id),
+        vec!["Health", "Tag", "Wrapper<Health>"]
+    );
+    // only registered types show their value
+    assert_eq!(
+        storage.inspect(id),
+        vec![
+            ("Health".to_string(), Some("Health(7)".to_string())),
+            ("Tag".to_string(), None),
+            ("Wrapper<Health>".to_string(), None),
+        ]
+    );
+    assert_eq!(storage.get::<Health>(id).unwrap().0, 7);
+    storage.remove_entity(id);
+    assert!(storage.inspect(id).is_empty());
+}
+
+#[test]
+fn dump_shows_every_live_entity() {
+    let mut storage = storage();
+    let parent = storage.append(EntityBundle::new().put(Tag));
+    let child = storage.append(EntityBundle::new().put(Health(3)));
+    storage.set_parent(child, Some(parent)).unwrap();
+    let gone = storage.append(EntityBundle::new().put(Health(4)));
+    storage.remove_entity(gone);
+    assert_eq!(storage.entities().collect::<Vec<_>>(), vec![parent, child]);
+
+    let dump = storage.dump();
+    assert!(dump.starts_with("tick 0, 2 entities\n"), "{dump}");
+    assert!(
+        dump.contains("entity 1v1, child of entity 0v1\n  Health: Health(3)\n"),
+        "{dump}"
+    );
+    assert!(!dump.contains("Health(4)"), "{dump}");
+}
+
+#[test]
+fn debug_views_need_a_registered_component() {
+    let mut storage = storage();
+    assert!(matches!(
+        storage.register_debug::<Health>(),
+        Err(EngineError::ComponentAlreadyRegistered)
+    ));
+    assert!(matches!(
+        storage.register_debug::<String>(),
+        Err(EngineError::ComponentNotRegistered)
+    ));
+}
@@ -0,0 +1,67 @@
+use engine::{assets::Prefab, ComponentStorage, EngineError, EngineResult};
+
+struct Health(u32);
+
+struct Tag;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Health>().unwrap();
+    storage.register_component::<Tag>().unwrap();
+    storage
+        .register_prefab_component("health", |x| {
+            x.parse()
+                .map(Health)
+                .map_err(|_| EngineError::ResourceParseError(x.to_string()))
+        })
+        .unwrap();
+    storage
+        .register_prefab_component("tag", |_| EngineResult::Ok(Tag))
+        .unwrap();
+    storage
+}
+
+#[test]
+fn prefab_bundle_makes_the_named_components() {
+    let mut storage = storage();
+    let prefab = Prefab::parse("soldier", "tag  health=40").unwrap();
+    assert_eq!(
+        prefab.entries().collect::<Vec<_>>(),
+        vec![("tag", ""), ("health", "40")]
+    );
+    let bundle = storage.prefab_bundle(&prefab).unwrap();
+    let first = storage.append(bundle);
+    // the template is reusable, and what's put on top wins
+    let bundle = storage.prefab_bundle(&prefab).unwrap().put(Health(5));
+    let second = storage.append(bundle);
+    assert!(storage.has_component::<Tag>(first));
+    assert_eq!(storage.get::<Health>(first).unwrap().0, 40);
+    assert_eq!(storage.get::<Health>(second).unwrap().0, 5);
+}
+
+#[test]
+fn bad_prefabs_are_refused() {
+    let storage = storage();
+    assert!(Prefab::parse("x", "health=1 health=2").is_err());
+    assert!(Prefab::parse("x", "=1").is_err());
+    for text in ["armor=3", "health=lots"] {
+        let prefab = Prefab::parse("x", text).unwrap();
+        let Err(EngineError::ResourceParseError(msg)) = storage.prefab_bundle(&prefab) else {
+            panic!("{text} accepted");
+        };
+        assert!(msg.contains("prefab 'x'"), "{msg}");
+    }
+}
+
+#[test]
+fn prefab_components_are_registered_once() {
+    let mut storage = storage();
+    assert!(matches!(
+        storage.register_prefab_component("tag", |_| EngineResult::Ok(Tag)),
+        Err(EngineError::ComponentAlreadyRegistered)
+    ));
+    assert!(matches!(
+        storage.register_prefab_component("name", |x| EngineResult::Ok(x.to_string())),
+        Err(EngineError::ComponentNotRegistered)
+    ));
+}
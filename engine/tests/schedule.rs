@@ -0,0 +1,167 @@
+use std::{cell::RefCell, rc::Rc};
+
+use engine::{
+    game_scene::GameScene,
+    schedule::SystemConfig,
+    systems::{
+        GameControlSystem, GameRendererSystem, GameSystem, GameSystemCommand, InputEvent,
+        RendererLayers, RendererLayersPtr,
+    },
+    AssetManager, ComponentStorage, EngineError, EngineResult, Float, SizeU32,
+};
+
+type Log = Rc<RefCell<Vec<(&'static str, usize)>>>;
+
+/// Writes its name and the step into the log on each update
+struct Named {
+    name: &'static str,
+    log: Log,
+}
+
+impl GameSystem for Named {
+    fn setup(&mut self, _: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        frames: usize,
+        _delta_time: Float,
+        _storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        self.log.borrow_mut().push((self.name, frames));
+        Ok(GameSystemCommand::Nothing)
+    }
+}
+
+struct NoControl;
+
+impl GameControlSystem for NoControl {
+    fn setup(&mut self, _: &ComponentStorage) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn push_events(&mut self, _: &mut ComponentStorage, _: &[InputEvent]) -> EngineResult<()> {
+        Ok(())
+    }
+}
+
+struct NoRenderer;
+
+impl GameRendererSystem for NoRenderer {
+    fn setup(&mut self, _: &ComponentStorage, _: &AssetManager, _: SizeU32) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        _frames: usize,
+        _storage: &ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<RendererLayersPtr> {
+        Ok(Rc::new(RefCell::new(RendererLayers::default())))
+    }
+}
+
+struct Paused;
+
+fn scene_with(systems: Vec<(&'static str, SystemConfig)>) -> (GameScene, Log) {
+    let mut scene = GameScene::new(1, ComponentStorage::new(), NoControl, NoRenderer);
+    let log = Log::default();
+    for (name, config) in systems {
+        let log = log.clone();
+        scene.add_system_with(Named { name, log }, config);
+    }
+    (scene, log)
+}
+
+fn names(log: &Log) -> Vec<&'static str> {
+    log.borrow().iter().map(|(name, _)| *name).collect()
+}
+
+#[test]
+fn constraints_reorder_no_more_than_needed() {
+    let (mut scene, log) = scene_with(vec![
+        ("movement", SystemConfig::new().label("movement")),
+        ("player", SystemConfig::new().label("player")),
+        (
+            "npc",
+            SystemConfig::new().before("movement").after("player"),
+        ),
+        ("corpse", SystemConfig::new()),
+    ]);
+    assert_eq!(scene.schedule().unwrap(), &[1, 2, 0, 3]);
+    scene.update(0.0, &AssetManager::default()).unwrap();
+    assert_eq!(names(&log), vec!["player", "npc", "movement", "corpse"]);
+}
+
+#[test]
+fn last_runs_after_systems_added_later() {
+    let (mut scene, log) = scene_with(vec![
+        ("player", SystemConfig::new().label("player")),
+        ("movement", SystemConfig::new().label("movement").last()),
+        // knows nothing of movement
+        ("radar", SystemConfig::new()),
+    ]);
+    assert_eq!(scene.schedule().unwrap(), &[0, 2, 1]);
+    scene.update(0.0, &AssetManager::default()).unwrap();
+    assert_eq!(names(&log), vec!["player", "radar", "movement"]);
+}
+
+#[test]
+fn bad_schedules_are_refused() {
+    let schedules = [
+        vec![("a", SystemConfig::new().after("nobody"))],
+        vec![
+            ("a", SystemConfig::new().label("x")),
+            ("b", SystemConfig::new().label("x")),
+        ],
+        vec![
+            ("a", SystemConfig::new().label("a").after("b")),
+            ("b", SystemConfig::new().label("b").after("a")),
+        ],
+        // nothing but another last system runs after a last one
+        vec![
+            ("a", SystemConfig::new().label("a").last()),
+            ("b", SystemConfig::new().after("a")),
+        ],
+    ];
+    for systems in schedules {
+        let (mut scene, log) = scene_with(systems);
+        assert!(matches!(
+            scene.schedule(),
+            Err(EngineError::InvalidSchedule(_))
+        ));
+        assert!(scene.update(0.0, &AssetManager::default()).is_err());
+        assert!(log.borrow().is_empty());
+    }
+}
+
+#[test]
+fn run_conditions_skip_steps() {
+    let (mut scene, log) = scene_with(vec![
+        ("always", SystemConfig::new()),
+        ("third", SystemConfig::new().every(3)),
+        (
+            "unpaused",
+            SystemConfig::new().run_if(|storage| !storage.has_resource::<Paused>()),
+        ),
+    ]);
+    let asset_manager = AssetManager::default();
+    for _ in 0..4 {
+        scene.update(0.0, &asset_manager).unwrap();
+    }
+    scene.insert_resource(Paused);
+    scene.update(0.0, &asset_manager).unwrap();
+    let runs = |name| {
+        log.borrow()
+            .iter()
+            .filter(|(x, _)| *x == name)
+            .map(|(_, frames)| *frames)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(runs("always"), vec![0, 1, 2, 3, 4]);
+    assert_eq!(runs("third"), vec![0, 3]);
+    assert_eq!(runs("unpaused"), vec![0, 1, 2, 3]);
+}
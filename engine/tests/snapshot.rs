@@ -0,0 +1,224 @@
+use engine::{
+    ComponentStorage, EngineError, EngineResult, EntityBundle, EventReader, Persist, Query,
+    SnapshotReader, SnapshotWriter, Vec2f,
+};
+
+#[derive(Debug, PartialEq)]
+struct Position(Vec2f);
+
+impl Persist for Position {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Vec2f::load(reader).map(Position)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Name(String);
+
+impl Persist for Name {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        String::load(reader).map(Name)
+    }
+}
+
+/// Not persistent: lost on load
+struct Sound;
+
+#[derive(Debug, PartialEq)]
+struct Seed(u64);
+
+impl Persist for Seed {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        u64::load(reader).map(Seed)
+    }
+}
+
+struct Cache(u32);
+
+#[derive(Clone)]
+struct Shot;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Position>().unwrap();
+    storage.register_component::<Name>().unwrap();
+    storage.register_component::<Sound>().unwrap();
+    storage.register_persistent::<Position>("position").unwrap();
+    storage.register_persistent::<Name>("name").unwrap();
+    storage
+        .register_persistent_resource::<Seed>("seed")
+        .unwrap();
+    storage.register_event::<Shot>();
+    storage
+}
+
+fn names(storage: &ComponentStorage) -> Vec<String> {
+    storage
+        .iter::<(&Name,)>()
+        .map(|(_, (name,))| name.0.clone())
+        .collect()
+}
+
+#[test]
+fn restore_brings_back_entities_and_resources() {
+    let mut saved = storage();
+    saved.set_tick(42);
+    saved.append(
+        EntityBundle::new()
+            .put(Name("player".into()))
+            .put(Position(Vec2f::new(1.5, 2.0)))
+            .put(Sound),
+    );
+    let gone = saved.append(EntityBundle::new().put(Name("gone".into())));
+    let torch = saved.append(EntityBundle::new().put(Name("torch".into())));
+    let light = saved.append(EntityBundle::new().put(Position(Vec2f::new(0.0, 1.0))));
+    saved.set_parent(light, Some(torch)).unwrap();
+    saved.remove_entity(gone);
+    saved.insert_resource(Seed(7));
+    saved.insert_resource(Cache(1));
+    let data = saved.snapshot();
+
+    let mut storage = storage();
+    storage.append(EntityBundle::new().put(Name("stale".into())));
+    storage.insert_resource(Cache(2));
+    storage.restore(&data).unwrap();
+
+    assert_eq!(storage.tick(), 42);
+    assert_eq!(storage.len(), 3);
+    assert_eq!(names(&storage), vec!["player", "torch"]);
+    let player = storage
+        .iter::<(&Name, &Position)>()
+        .find(|(_, (name, _))| name.0 == "player")
+        .map(|(id, (_, position))| {
+            assert_eq!(position.0, Vec2f::new(1.5, 2.0));
+            id
+        })
+        .unwrap();
+    assert!(!storage.has_component::<Sound>(player));
+    let torch = storage.fetch_entities(&Query::new().with_component::<Name>())[1];
+    let light = storage.children(torch)[0];
+    assert_eq!(storage.parent(light), Some(torch));
+    assert_eq!(
+        storage.get::<Position>(light).unwrap().0,
+        Vec2f::new(0.0, 1.0)
+    );
+    assert_eq!(*storage.resource::<Seed>().unwrap(), Seed(7));
+    // resources that aren't persistent stay as they were
+    assert_eq!(storage.resource::<Cache>().unwrap().0, 2);
+    // what's loaded counts as added on the loaded tick
+    assert!(storage.is_added_since::<Name>(player, 42));
+}
+
+#[test]
+fn pending_events_are_dropped() {
+    let mut storage = storage();
+    let data = storage.snapshot();
+    let mut reader = EventReader::<Shot>::new();
+    storage.send(Shot).unwrap();
+    storage.restore(&data).unwrap();
+    assert!(storage.read(&mut reader).is_empty());
+    storage.send(Shot).unwrap();
+    assert_eq!(storage.read(&mut reader).len(), 1);
+}
+
+#[test]
+fn unknown_types_are_skipped() {
+    let mut saved = storage();
+    saved.append(EntityBundle::new().put(Name("player".into())));
+    saved.insert_resource(Seed(7));
+    let data = saved.snapshot();
+
+    // a build that no longer saves names or the seed
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Position>().unwrap();
+    storage.register_persistent::<Position>("position").unwrap();
+    storage.restore(&data).unwrap();
+    assert_eq!(storage.len(), 1);
+    assert!(storage.resource::<Seed>().is_none());
+}
+
+#[test]
+fn damaged_snapshot_changes_nothing() {
+    let mut saved = storage();
+    saved.append(EntityBundle::new().put(Name("player".into())));
+    let data = saved.snapshot();
+    let mut saved = storage();
+    saved.append(EntityBundle::new());
+    saved.append(EntityBundle::new());
+    let orphans = saved.snapshot();
+
+    let mut storage = storage();
+    storage.append(EntityBundle::new().put(Name("current".into())));
+    for len in [0, 4, data.len() - 1] {
+        assert!(matches!(
+            storage.restore(&data[..len]),
+            Err(EngineError::ResourceParseError(_))
+        ));
+    }
+    let mut newer = data.clone();
+    newer[4] += 1;
+    assert!(storage.restore(&newer).is_err());
+    assert_eq!(names(&storage), vec!["current"]);
+
+    // two entities without components end the file with their parent and a
+    // component count each
+    let data = orphans;
+    let at = data.len() - 16;
+    let mut own_parent = data.clone();
+    own_parent[at..at + 4].copy_from_slice(&1u32.to_le_bytes());
+    let mut cycle = own_parent.clone();
+    cycle[at + 8..at + 12].copy_from_slice(&1u32.to_le_bytes());
+    cycle[at..at + 4].copy_from_slice(&2u32.to_le_bytes());
+    for damaged in [own_parent, cycle] {
+        assert!(matches!(
+            storage.restore(&damaged),
+            Err(EngineError::ResourceParseError(_))
+        ));
+        assert_eq!(names(&storage), vec!["current"]);
+        assert_eq!(storage.len(), 1);
+    }
+}
+
+#[test]
+fn persistent_types_must_be_registered_once() {
+    let mut storage = storage();
+    assert!(matches!(
+        storage.register_persistent::<Seed>("seed"),
+        Err(EngineError::ComponentNotRegistered)
+    ));
+    storage.register_component::<Seed>().unwrap();
+    assert!(matches!(
+        storage.register_persistent::<Seed>("name"),
+        Err(EngineError::ComponentAlreadyRegistered)
+    ));
+}
+
+#[test]
+fn snapshot_file_round_trip() {
+    let path = std::env::temp_dir().join(format!("engine-snapshot-{}.sav", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut saved = storage();
+    saved.append(EntityBundle::new().put(Name("player".into())));
+    saved.save_snapshot(path).unwrap();
+
+    let mut storage = storage();
+    storage.load_snapshot(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(names(&storage), vec!["player"]);
+    assert!(matches!(
+        storage.load_snapshot(path),
+        Err(EngineError::FileAccessError(_))
+    ));
+}
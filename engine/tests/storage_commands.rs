@@ -0,0 +1,213 @@
+use std::{cell::RefCell, rc::Rc};
+
+use engine::{
+    game_scene::GameScene,
+    systems::{
+        GameControlSystem, GameRendererSystem, GameSystem, GameSystemCommand, InputEvent,
+        RendererLayers, RendererLayersPtr,
+    },
+    AssetManager, ComponentStorage, EngineError, EngineResult, EntityBundle, Float, SizeU32,
+};
+
+#[derive(Debug, PartialEq)]
+struct Health(u32);
+
+struct Projectile;
+
+struct Unregistered;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Health>().unwrap();
+    storage.register_component::<Projectile>().unwrap();
+    storage
+}
+
+#[test]
+fn spawn_while_iterating() {
+    let mut storage = storage();
+    for i in 0..3 {
+        storage.append(EntityBundle::new().put(Health(i)));
+    }
+    for (_, (health,)) in storage.iter::<(&Health,)>() {
+        storage.commands().spawn(
+            EntityBundle::new()
+                .put(Health(health.0 + 10))
+                .put(Projectile),
+        );
+    }
+    // nothing changes until the commands are applied
+    assert_eq!(storage.len(), 3);
+    assert_eq!(storage.commands().len(), 3);
+
+    storage.apply_commands().unwrap();
+    assert!(storage.commands().is_empty());
+    let spawned = storage
+        .iter::<(&Health, &Projectile)>()
+        .map(|(_, (health, _))| health.0)
+        .collect::<Vec<_>>();
+    assert_eq!(spawned, vec![10, 11, 12]);
+}
+
+#[test]
+fn commands_apply_in_order() {
+    let mut storage = storage();
+    let id = storage.append(EntityBundle::new().put(Health(1)));
+    storage.commands().insert(id, Health(2));
+    storage.commands().remove::<Health>(id);
+    storage.commands().insert(id, Health(3));
+    storage.apply_commands().unwrap();
+    assert_eq!(storage.get::<Health>(id).map(|x| x.0), Some(3));
+
+    // a command sees what the ones before it did
+    storage.commands().run(move |storage| {
+        let health = storage.get::<Health>(id).map(|x| x.0).unwrap_or_default();
+        storage.set(id, Some(Health(health * 2)))
+    });
+    storage.commands().run(move |storage| {
+        let health = storage.get::<Health>(id).map(|x| x.0).unwrap_or_default();
+        storage.set(id, Some(Health(health + 1)))
+    });
+    storage.apply_commands().unwrap();
+    assert_eq!(storage.get::<Health>(id).map(|x| x.0), Some(7));
+}
+
+#[test]
+fn commands_on_despawned_entity_are_skipped() {
+    let mut storage = storage();
+    let id = storage.append(EntityBundle::new().put(Health(1)));
+    storage.commands().despawn(id);
+    storage.commands().insert(id, Health(2));
+    storage.commands().remove::<Health>(id);
+    storage.commands().despawn(id);
+    storage.apply_commands().unwrap();
+    assert!(!storage.is_alive(id));
+    assert!(storage.is_empty());
+}
+
+#[test]
+fn commands_queued_while_applying_run_in_the_same_pass() {
+    let mut storage = storage();
+    storage.commands().run(|storage| {
+        storage
+            .commands()
+            .spawn(EntityBundle::new().put(Projectile));
+        Ok(())
+    });
+    storage.apply_commands().unwrap();
+    assert_eq!(storage.len(), 1);
+    assert!(storage.commands().is_empty());
+}
+
+#[test]
+fn failed_command_is_reported() {
+    let mut storage = storage();
+    let id = storage.add_entity();
+    storage.commands().insert(id, Unregistered);
+    storage.commands().spawn(EntityBundle::new());
+    assert!(matches!(
+        storage.apply_commands(),
+        Err(EngineError::ComponentNotRegistered)
+    ));
+    // the commands behind the failed one are dropped
+    assert_eq!(storage.len(), 1);
+    assert!(storage.commands().is_empty());
+}
+
+/// Spawns a projectile per health entity, through the command buffer
+struct Shooter;
+
+impl GameSystem for Shooter {
+    fn setup(&mut self, _: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        _frames: usize,
+        _delta_time: Float,
+        storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        for _ in storage.iter::<(&Health,)>() {
+            storage
+                .commands()
+                .spawn(EntityBundle::new().put(Projectile));
+        }
+        Ok(GameSystemCommand::Nothing)
+    }
+}
+
+/// Counts the projectiles it sees on each update
+struct Counter {
+    seen: Rc<RefCell<Vec<usize>>>,
+}
+
+impl GameSystem for Counter {
+    fn setup(&mut self, _: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        _frames: usize,
+        _delta_time: Float,
+        storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        let count = storage.iter::<(&Projectile,)>().count();
+        self.seen.borrow_mut().push(count);
+        Ok(GameSystemCommand::Nothing)
+    }
+}
+
+struct NoControl;
+
+impl GameControlSystem for NoControl {
+    fn setup(&mut self, _: &ComponentStorage) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn push_events(&mut self, _: &mut ComponentStorage, _: &[InputEvent]) -> EngineResult<()> {
+        Ok(())
+    }
+}
+
+struct NoRenderer {
+    layers: RendererLayersPtr,
+}
+
+impl GameRendererSystem for NoRenderer {
+    fn setup(&mut self, _: &ComponentStorage, _: &AssetManager, _: SizeU32) -> EngineResult<()> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        _frames: usize,
+        _storage: &ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<RendererLayersPtr> {
+        Ok(self.layers.clone())
+    }
+}
+
+#[test]
+fn scene_applies_commands_after_each_system() {
+    let mut storage = storage();
+    storage.append(EntityBundle::new().put(Health(1)));
+    storage.append(EntityBundle::new().put(Health(2)));
+    let renderer = NoRenderer {
+        layers: Rc::new(RefCell::new(RendererLayers::default())),
+    };
+    let mut scene = GameScene::new(1, storage, NoControl, renderer);
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    scene.add_system(Shooter);
+    scene.add_system(Counter { seen: seen.clone() });
+
+    let asset_manager = AssetManager::default();
+    scene.update(0.0, &asset_manager).unwrap();
+    scene.update(0.0, &asset_manager).unwrap();
+    // the counter runs after the shooter and sees its projectiles that step
+    assert_eq!(*seen.borrow(), vec![2, 4]);
+}
@@ -0,0 +1,89 @@
+use engine::{ComponentStorage, EntityBundle, EntityID};
+
+#[derive(Debug, PartialEq)]
+struct Position(i32);
+#[derive(Debug, PartialEq)]
+struct Velocity(i32);
+struct Tag;
+struct Unregistered;
+
+fn storage() -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Position>().unwrap();
+    storage.register_component::<Velocity>().unwrap();
+    storage.register_component::<Tag>().unwrap();
+    storage
+}
+
+fn spawn(storage: &mut ComponentStorage, position: i32, velocity: Option<i32>) -> EntityID {
+    let mut bundle = EntityBundle::new().put(Position(position));
+    if let Some(velocity) = velocity {
+        bundle = bundle.put(Velocity(velocity));
+    }
+    storage.append(bundle)
+}
+
+#[test]
+fn iterates_entities_having_every_term() {
+    let mut storage = storage();
+    let moving = spawn(&mut storage, 0, Some(2));
+    let still = spawn(&mut storage, 10, None);
+    let other = spawn(&mut storage, 20, Some(-1));
+
+    for (_, (velocity, mut position)) in storage.iter::<(&Velocity, &mut Position)>() {
+        position.0 += velocity.0;
+    }
+    assert_eq!(storage.get::<Position>(moving).map(|x| x.0), Some(2));
+    assert_eq!(storage.get::<Position>(still).map(|x| x.0), Some(10));
+    assert_eq!(storage.get::<Position>(other).map(|x| x.0), Some(19));
+
+    let ids = storage
+        .iter::<&Position>()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![moving, still, other]);
+}
+
+#[test]
+fn skips_removed_and_detached_entities() {
+    let mut storage = storage();
+    let removed = spawn(&mut storage, 0, Some(1));
+    let detached = spawn(&mut storage, 1, Some(1));
+    let kept = spawn(&mut storage, 2, Some(1));
+    storage.remove_entity(removed);
+    storage.set::<Velocity>(detached, None).unwrap();
+
+    let ids = storage
+        .iter::<(&Position, &Velocity)>()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![kept]);
+
+    // the recycled slot starts out without the old entity's components
+    let recycled = storage.add_entity();
+    assert_eq!(recycled.index(), removed.index());
+    assert_eq!(storage.iter::<&Position>().count(), 2);
+}
+
+#[test]
+fn unregistered_term_matches_nothing() {
+    let mut storage = storage();
+    spawn(&mut storage, 0, Some(1));
+    assert_eq!(storage.iter::<(&Position, &Unregistered)>().count(), 0);
+    assert_eq!(storage.iter::<&Tag>().count(), 0);
+}
+
+#[test]
+fn entity_borrowed_elsewhere_is_skipped() {
+    let mut storage = storage();
+    let held = spawn(&mut storage, 0, None);
+    let free = spawn(&mut storage, 1, None);
+
+    // the other cells of a column stay available while one is borrowed
+    let _guard = storage.get_mut::<Position>(held).unwrap();
+    let ids = storage
+        .iter::<&Position>()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![free]);
+}
@@ -0,0 +1,56 @@
+use engine::{ComponentStorage, EntityBundle, Query};
+
+struct Soldier(u32);
+
+fn soldiers(count: u32) -> ComponentStorage {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Soldier>().unwrap();
+    for i in 0..count {
+        storage.append(EntityBundle::new().put(Soldier(i)));
+    }
+    storage
+}
+
+fn fetched(storage: &ComponentStorage) -> Vec<u32> {
+    let query = Query::new().with_component::<Soldier>();
+    storage
+        .fetch_entities(&query)
+        .into_iter()
+        .map(|id| storage.get::<Soldier>(id).unwrap().0)
+        .collect()
+}
+
+#[test]
+fn entities_come_in_index_order() {
+    let storage = soldiers(50);
+    assert_eq!(fetched(&storage), (0..50).collect::<Vec<_>>());
+    let iterated = storage
+        .iter::<&Soldier>()
+        .map(|(_, soldier)| soldier.0)
+        .collect::<Vec<_>>();
+    assert_eq!(iterated, fetched(&storage));
+    let query = Query::new().with_component::<Soldier>();
+    let first = storage.fetch_first_entity(&query).unwrap();
+    assert_eq!(storage.get::<Soldier>(first).map(|x| x.0), Some(0));
+}
+
+#[test]
+fn order_is_the_same_for_the_same_history() {
+    let history = |storage: &mut ComponentStorage| {
+        let query = Query::new().with_component::<Soldier>();
+        let ids = storage.fetch_entities(&query);
+        for id in ids.iter().step_by(3) {
+            storage.remove_entity(*id);
+        }
+        for i in 100..110 {
+            storage.append(EntityBundle::new().put(Soldier(i)));
+        }
+    };
+    let mut first = soldiers(40);
+    let mut second = soldiers(40);
+    history(&mut first);
+    history(&mut second);
+    assert_eq!(fetched(&first), fetched(&second));
+    // new entities fill the freed slots rather than going last
+    assert_ne!(fetched(&first).last(), Some(&109));
+}
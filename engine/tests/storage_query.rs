@@ -1,4 +1,4 @@
-use engine::{ComponentStorage, Query};
+use engine::{ComponentStorage, EntityBundle, EntityID, Query};
 
 struct Registered;
 struct Unregistered;
@@ -36,3 +36,69 @@ fn registered_query_still_matches() {
     assert_eq!(storage.fetch_entities(&query).len(), 1);
     assert!(storage.fetch_first_entity(&query).is_some());
 }
+
+struct Position(i32);
+struct Dead;
+
+fn storage_with_soldiers() -> (ComponentStorage, EntityID, EntityID) {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Registered>().unwrap();
+    storage.register_component::<Position>().unwrap();
+    storage.register_component::<Dead>().unwrap();
+    let alive = storage.append(EntityBundle::new().put(Registered).put(Position(1)));
+    let dead = storage.append(
+        EntityBundle::new()
+            .put(Registered)
+            .put(Position(2))
+            .put(Dead),
+    );
+    (storage, alive, dead)
+}
+
+#[test]
+fn without_excludes_entities_having_the_component() {
+    let (storage, alive, _) = storage_with_soldiers();
+    let query = Query::new()
+        .with_component::<Registered>()
+        .without::<Dead>();
+    assert_eq!(storage.fetch_entities(&query), vec![alive]);
+    assert_eq!(storage.fetch_first_entity(&query), Some(alive));
+}
+
+#[test]
+fn excluding_an_unregistered_component_excludes_nothing() {
+    let (storage, _, _) = storage_with_soldiers();
+    let query = Query::new()
+        .with_component::<Registered>()
+        .without::<Unregistered>();
+    assert_eq!(storage.fetch_entities(&query).len(), 2);
+}
+
+#[test]
+fn filtered_iteration_applies_the_query() {
+    let (storage, alive, _) = storage_with_soldiers();
+    let query = Query::new().without::<Dead>();
+    let positions = storage
+        .iter_filtered::<&Position>(&query)
+        .map(|(id, position)| (id, position.0))
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![(alive, 1)]);
+}
+
+#[test]
+fn optional_terms_do_not_filter() {
+    let (storage, alive, dead) = storage_with_soldiers();
+    let items = storage
+        .iter::<(&Position, Option<&Dead>, Option<&Unregistered>)>()
+        .map(|(id, (_, dead, unregistered))| (id, dead.is_some(), unregistered.is_some()))
+        .collect::<Vec<_>>();
+    assert_eq!(items, vec![(alive, false, false), (dead, true, false)]);
+
+    for (_, (mut position, dead)) in storage.iter::<(&mut Position, Option<&Dead>)>() {
+        if dead.is_some() {
+            position.0 = 0;
+        }
+    }
+    assert_eq!(storage.get::<Position>(dead).map(|x| x.0), Some(0));
+    assert_eq!(storage.get::<Position>(alive).map(|x| x.0), Some(1));
+}
@@ -36,3 +36,39 @@ fn recycled_entity_works_with_late_registered_component() {
     assert_eq!(storage.get::<CompA>(e2).map(|x| x.0), Some(1));
     assert_eq!(storage.get::<CompB>(e2).map(|x| x.0), Some(2));
 }
+
+struct Numbered<const N: usize>;
+
+macro_rules! register_numbered {
+    ($storage:expr, $($n:literal)*) => {
+        $($storage.register_component::<Numbered<$n>>().unwrap();)*
+    };
+}
+
+#[test]
+fn more_than_128_component_types() {
+    let mut storage = ComponentStorage::new();
+    register_numbered!(storage,
+        0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29
+        30 31 32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56
+        57 58 59 60 61 62 63 64 65 66 67 68 69 70 71 72 73 74 75 76 77 78 79 80 81 82 83
+        84 85 86 87 88 89 90 91 92 93 94 95 96 97 98 99 100 101 102 103 104 105 106 107
+        108 109 110 111 112 113 114 115 116 117 118 119 120 121 122 123 124 125 126 127
+        128 129 130
+    );
+    let low = storage.add_entity();
+    let high = storage.add_entity();
+    storage.set(low, Some(Numbered::<3>)).unwrap();
+    storage.set(high, Some(Numbered::<3>)).unwrap();
+    storage.set(high, Some(Numbered::<130>)).unwrap();
+
+    let query = Query::new().with_component::<Numbered<130>>();
+    assert_eq!(storage.fetch_entities(&query), vec![high]);
+    let query = Query::new()
+        .with_component::<Numbered<3>>()
+        .without::<Numbered<130>>();
+    assert_eq!(storage.fetch_entities(&query), vec![low]);
+    assert!(storage.has_component::<Numbered<130>>(high));
+    assert!(!storage.has_component::<Numbered<129>>(high));
+    assert_eq!(storage.iter::<&Numbered<130>>().count(), 1);
+}
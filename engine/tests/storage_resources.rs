@@ -0,0 +1,54 @@
+use engine::{ComponentStorage, EntityBundle};
+
+#[derive(Debug, PartialEq)]
+struct Score(u32);
+
+struct Difficulty(u8);
+
+struct Tag;
+
+#[test]
+fn resource_round_trip() {
+    let mut storage = ComponentStorage::new();
+    assert!(storage.resource::<Score>().is_none());
+    assert!(!storage.has_resource::<Score>());
+
+    assert_eq!(storage.insert_resource(Score(1)), None);
+    assert_eq!(storage.insert_resource(Score(2)), Some(Score(1)));
+    storage.insert_resource(Difficulty(3));
+    assert_eq!(storage.resource::<Score>().map(|x| x.0), Some(2));
+    assert_eq!(storage.resource::<Difficulty>().map(|x| x.0), Some(3));
+
+    storage.resource_mut::<Score>().unwrap().0 += 5;
+    assert_eq!(storage.resource::<Score>().map(|x| x.0), Some(7));
+
+    assert_eq!(storage.remove_resource::<Score>(), Some(Score(7)));
+    assert!(storage.resource::<Score>().is_none());
+    assert!(storage.has_resource::<Difficulty>());
+}
+
+#[test]
+fn conflicting_borrow_yields_none() {
+    let mut storage = ComponentStorage::new();
+    storage.insert_resource(Score(1));
+    {
+        let _score = storage.resource_mut::<Score>().unwrap();
+        assert!(storage.resource::<Score>().is_none());
+        assert!(storage.resource_mut::<Score>().is_none());
+    }
+    let first = storage.resource::<Score>();
+    assert!(first.is_some());
+    assert!(storage.resource::<Score>().is_some());
+    assert!(storage.resource_mut::<Score>().is_none());
+}
+
+#[test]
+fn resources_outlive_entities() {
+    let mut storage = ComponentStorage::new();
+    storage.register_component::<Tag>().unwrap();
+    storage.append(EntityBundle::new().put(Tag));
+    storage.insert_resource(Score(4));
+    storage.remove_all_entities();
+    assert!(storage.is_empty());
+    assert_eq!(storage.resource::<Score>().map(|x| x.0), Some(4));
+}
@@ -0,0 +1,42 @@
+use engine::{prelude::Rect, window::Scaling, SizeU32};
+
+fn size(width: u32, height: u32) -> SizeU32 {
+    SizeU32 { width, height }
+}
+
+#[test]
+fn aspect_scaling_fills_one_side() {
+    let logical = size(320, 200);
+    // wider than 16:10, bars left and right
+    assert_eq!(
+        Scaling::Aspect.viewport(logical, size(1600, 900)),
+        Rect::new(80, 0, 1440, 900)
+    );
+    // taller, bars top and bottom
+    assert_eq!(
+        Scaling::Aspect.viewport(logical, size(640, 480)),
+        Rect::new(0, 40, 640, 400)
+    );
+    assert_eq!(
+        Scaling::Aspect.viewport(logical, size(320, 200)),
+        Rect::new(0, 0, 320, 200)
+    );
+}
+
+#[test]
+fn integer_scaling_keeps_pixels_whole() {
+    let logical = size(320, 200);
+    assert_eq!(
+        Scaling::Integer.viewport(logical, size(1600, 900)),
+        Rect::new(160, 50, 1280, 800)
+    );
+    assert_eq!(
+        Scaling::Integer.viewport(logical, size(639, 480)),
+        Rect::new(159, 140, 320, 200)
+    );
+    // too small for even one: shrink as aspect scaling would
+    assert_eq!(
+        Scaling::Integer.viewport(logical, size(160, 160)),
+        Scaling::Aspect.viewport(logical, size(160, 160))
+    );
+}
@@ -1,12 +1,15 @@
 use super::generator;
 use crate::resource::*;
+use chacha20::ChaCha12Rng;
 use engine::{Float, SizeFloat, Vec2f};
-use std::{collections::HashSet, fmt::Display};
+use std::{collections::HashSet, fmt::Display, time::Duration};
 
+#[derive(Debug)]
 pub struct PlayerTag;
+#[derive(Debug)]
 pub struct NpcTag;
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
 pub enum ActorState {
     #[default]
     Undefined,
@@ -31,15 +34,19 @@ impl Display for ActorState {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Debug)]
 pub struct ControllerState {
-    pub shot_pressed: bool,
+    /// X, held apart from the mouse button so releasing one doesn't stop
+    /// the other from shooting
+    pub shot_key_pressed: bool,
+    pub shot_button_pressed: bool,
     pub forward_pressed: bool,
     pub backward_pressed: bool,
     pub left_pressed: bool,
     pub right_pressed: bool,
     pub rotate_left_pressed: bool,
     pub rotate_right_pressed: bool,
+    /// Mouse motion since the player system last turned by it
     pub mouse_x_relative: i32,
     pub mouse_y_relative: i32,
     pub pause_pressed: bool,
@@ -51,30 +58,34 @@ pub struct Movement {
     pub y: Float,
     pub angle: Float,
 }
+#[derive(Clone, Copy, Debug)]
 pub struct Position(pub Vec2f);
 
+#[derive(Debug)]
 pub struct Velocity(pub Float);
 
+#[derive(Debug)]
 pub struct RotationSpeed(pub Float);
 
+#[derive(Debug)]
 pub struct BoundingBox(pub SizeFloat);
 
+#[derive(Debug)]
 pub struct Angle(pub Float);
 
 pub type HealthType = u32;
 
+#[derive(Debug)]
 pub struct Health(pub HealthType);
 
-#[derive(Clone, Copy)]
-pub struct Shot {
-    pub position: Vec2f,
-    pub angle: Float,
-    pub deadline: usize,
-}
+/// The health an entity was spawned with
+#[derive(Debug)]
+pub struct MaxHealth(pub HealthType);
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct Damage(pub HealthType);
 
+#[derive(Clone, Copy, Debug)]
 pub enum SpriteView {
     Texture {
         asset_id: &'static str,
@@ -86,6 +97,7 @@ pub enum SpriteView {
     },
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct Sprite {
     pub view: SpriteView,
 }
@@ -109,9 +121,36 @@ impl Sprite {
 }
 
 // sprite position parameters
+#[derive(Clone, Copy, Debug)]
 pub struct ScaleRatio(pub Float);
+#[derive(Clone, Copy, Debug)]
 pub struct HeightShift(pub Float);
 
+/// Remains of a dead soldier; `since` is the step it died on
+#[derive(Clone, Copy, Debug)]
+pub struct Corpse {
+    pub since: usize,
+}
+
+/// A corpse reduced to its sprite, see `CorpseSystem`
+#[derive(Debug)]
+pub struct Decal;
+
+/// The level's source of randomness, a scene resource. Generation
+/// and the soldiers' choices all draw from it, so a level generated from the
+/// same seed plays out the same way. It is the generator behind `StdRng`,
+/// whose state can be saved with the game.
+pub struct LevelRng(pub ChaCha12Rng);
+
+/// The step the level was generated on, a scene resource. The scene's clock
+/// runs on across levels; time played is counted from here.
+pub struct LevelStart(pub usize);
+
+/// Where the generator takes the seeds of the levels not given one, a scene
+/// resource kept across levels. Saved with the game, so the levels after a
+/// Continue follow from the session's seed as they would have.
+pub struct LevelSeeds(pub ChaCha12Rng);
+
 pub struct Maze {
     pub matrix: generator::matrix::Matrix,
     pub contour: HashSet<generator::matrix::Position>,
@@ -186,7 +225,7 @@ pub enum NpcIntent {
 /// This is deliberately separate from [`ActorState`], which selects the
 /// sprite animation. A soldier walking a patrol route and one closing on the
 /// player look identical; they are not doing the same thing.
-#[derive(Default)]
+#[derive(Default, Debug)]
 pub struct NpcPlan {
     pub intent: NpcIntent,
     /// Remaining waypoints, in order, as tile centres.
@@ -209,6 +248,7 @@ pub struct NpcPlan {
     pub player_visible: bool,
 }
 
+#[derive(Debug)]
 pub struct SoundFx {
     pub asset_id: String,
     pub loops: i32,
@@ -223,10 +263,10 @@ impl SoundFx {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct Weapon {
     pub damage: HealthType,
-    pub recharge_time: usize,
+    pub recharge_time: Duration,
     pub ammo_count: usize,
     pub state: WeaponState,
 }
@@ -1,16 +1,29 @@
 use engine::{
-    prelude::Keycode,
+    game_scene::{SceneEvent, SceneParameters},
+    prelude::{Keycode, MouseButton},
     refresh_cached_entity,
-    systems::{GameControlSystem, InputEvent},
+    systems::{GameControlSystem, GameSystemCommand, InputEvent},
+    time::TimeScale,
+    window::{WindowMode, WindowState},
     ComponentStorage, EngineResult, EntityID,
 };
 use log::{info, trace, warn};
 
+use crate::resource::FILE_STORAGE_DUMP;
+
 use super::components;
 
+/// Slow motion, toggled with F5
+const TIME_SCALE_SLOW: TimeScale = TimeScale::Scaled(0.25);
+/// Fast-forward, toggled with F6
+const TIME_SCALE_FAST: TimeScale = TimeScale::Scaled(4.0);
+
 #[derive(Default)]
 pub struct ControlSystem {
     player_id: EntityID,
+    /// As last set by the time keys
+    time_scale: TimeScale,
+    command: Option<GameSystemCommand>,
 }
 
 impl ControlSystem {
@@ -18,6 +31,55 @@ impl ControlSystem {
         Default::default()
     }
 
+    /// F5 slow motion, F6 fast-forward and F7 freeze, each toggled against
+    /// real time; F8 runs a single step
+    fn handle_time_keys(&mut self, events: &[InputEvent]) {
+        for event in events {
+            let InputEvent::Keyboard {
+                code,
+                pressed: true,
+            } = event
+            else {
+                continue;
+            };
+            let toggled = match *code {
+                Keycode::F5 => TIME_SCALE_SLOW,
+                Keycode::F6 => TIME_SCALE_FAST,
+                Keycode::F7 => TimeScale::Frozen,
+                Keycode::F8 => {
+                    self.command = Some(GameSystemCommand::StepOnce);
+                    continue;
+                }
+                _ => continue,
+            };
+            self.time_scale = if self.time_scale == toggled {
+                TimeScale::default()
+            } else {
+                toggled
+            };
+            self.command = Some(GameSystemCommand::SetTimeScale(self.time_scale));
+        }
+    }
+
+    /// F11 switches between a window and borderless fullscreen
+    fn handle_window_keys(&mut self, storage: &ComponentStorage, events: &[InputEvent]) {
+        let is_pressed = events.iter().any(|event| {
+            matches!(
+                event,
+                InputEvent::Keyboard {
+                    code: Keycode::F11,
+                    pressed: true
+                }
+            )
+        });
+        if is_pressed {
+            let mode = WindowState::of(storage)
+                .mode
+                .toggled(WindowMode::Borderless);
+            self.command = Some(GameSystemCommand::SetWindowMode(mode));
+        }
+    }
+
     fn update_storage_cache(&mut self, storage: &ComponentStorage) -> EngineResult<()> {
         refresh_cached_entity::<components::PlayerTag>(
             storage,
@@ -40,13 +102,28 @@ impl GameControlSystem for ControlSystem {
         events: &[InputEvent],
     ) -> EngineResult<()> {
         self.update_storage_cache(storage)?;
+        let is_dump_pressed = events.iter().any(|event| {
+            matches!(
+                event,
+                InputEvent::Keyboard {
+                    code: Keycode::F12,
+                    pressed: true
+                }
+            )
+        });
+        self.handle_time_keys(events);
+        self.handle_window_keys(storage, events);
+        if is_dump_pressed {
+            match storage.dump_to_file(FILE_STORAGE_DUMP) {
+                Ok(()) => info!("storage dumped to {FILE_STORAGE_DUMP}"),
+                Err(err) => warn!("{err}"),
+            }
+        }
         let Some(mut comp) = storage.get_mut::<components::ControllerState>(self.player_id) else {
             warn!("controller component isn't associated with player");
             return Ok(());
         };
         let state = &mut *comp;
-        state.mouse_x_relative = 0;
-        state.mouse_y_relative = 0;
         for event in events {
             match event {
                 InputEvent::Keyboard { code, pressed } => match *code {
@@ -56,17 +133,51 @@ impl GameControlSystem for ControlSystem {
                     Keycode::D => state.right_pressed = *pressed,
                     Keycode::LEFT => state.rotate_left_pressed = *pressed,
                     Keycode::RIGHT => state.rotate_right_pressed = *pressed,
-                    Keycode::X => state.shot_pressed = *pressed,
+                    Keycode::X => state.shot_key_pressed = *pressed,
                     Keycode::ESCAPE => state.pause_pressed = *pressed,
+                    Keycode::F5
+                    | Keycode::F6
+                    | Keycode::F7
+                    | Keycode::F8
+                    | Keycode::F11
+                    | Keycode::F12 => {}
                     _ => trace!("unmapped key {code} pressed {pressed}"),
                 },
+                // adds up until the player system turns by it, so none is
+                // lost to an iteration that runs no step
                 InputEvent::Mouse { x_rel, y_rel, .. } => {
-                    state.mouse_x_relative = *x_rel;
-                    state.mouse_y_relative = *y_rel;
+                    state.mouse_x_relative += *x_rel;
+                    state.mouse_y_relative += *y_rel;
                 }
+                InputEvent::MouseButton {
+                    button, pressed, ..
+                } => match *button {
+                    MouseButton::Left => state.shot_button_pressed = *pressed,
+                    _ => trace!("unmapped button {button:?} pressed {pressed}"),
+                },
                 InputEvent::Quit => state.pause_pressed = true,
             }
         }
         Ok(())
     }
+
+    fn command(&mut self) -> GameSystemCommand {
+        self.command.take().unwrap_or(GameSystemCommand::Nothing)
+    }
+
+    fn on_scene_event(
+        &mut self,
+        storage: &mut ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        // keys held when the scene was left were released somewhere else
+        if matches!(event, SceneEvent::Enter | SceneEvent::Resume) {
+            // the run loop puts time back to normal on any scene change
+            self.time_scale = TimeScale::default();
+            self.update_storage_cache(storage)?;
+            storage.set(self.player_id, Some(components::ControllerState::default()))?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use engine::{
+    systems::{GameSystem, GameSystemCommand},
+    time::StepRate,
+    AssetManager, ComponentStorage, EngineResult, EntityBundle, EntityID, EventReader, Float,
+};
+use log::{debug, info};
+
+use super::{
+    components::{self, Corpse, Decal},
+    events::SoldierKilled,
+};
+
+/// Most soldier remains kept on a level, decals included.
+const CORPSE_LIMIT: usize = 24;
+/// How long a corpse stays whole, long past the death animation.
+const CORPSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct CorpsePolicy {
+    /// Remains kept on a level, decals included; the oldest are despawned
+    /// first
+    pub limit: usize,
+    /// How long a corpse stays whole before it is reduced to a decal, or
+    /// `None` to keep it whole until the limit evicts it
+    pub timeout: Option<Duration>,
+}
+
+impl Default for CorpsePolicy {
+    fn default() -> Self {
+        Self {
+            limit: CORPSE_LIMIT,
+            timeout: Some(CORPSE_TIMEOUT),
+        }
+    }
+}
+
+/// Keeps dead soldiers from piling up over a long session.
+///
+/// A soldier becomes a [`Corpse`] when it is killed. Once the
+/// timeout runs out the corpse is reduced to a [`Decal`], a sprite no other
+/// system looks at, and remains over the limit are despawned oldest first.
+pub struct CorpseSystem {
+    policy: CorpsePolicy,
+    killed: EventReader<SoldierKilled>,
+    step_rate: StepRate,
+}
+
+impl CorpseSystem {
+    pub fn new(policy: CorpsePolicy) -> Self {
+        Self {
+            policy,
+            killed: EventReader::new(),
+            step_rate: StepRate::default(),
+        }
+    }
+
+    fn is_expired(&self, frames: usize, since: usize) -> bool {
+        self.policy
+            .timeout
+            .is_some_and(|timeout| frames >= since.saturating_add(self.step_rate.steps(timeout)))
+    }
+}
+
+impl GameSystem for CorpseSystem {
+    fn setup(&mut self, storage: &mut ComponentStorage, _: &AssetManager) -> EngineResult<()> {
+        self.step_rate = StepRate::of(storage);
+        info!("setup ok");
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        frames: usize,
+        _delta_time: Float,
+        storage: &mut ComponentStorage,
+        _asset_manager: &AssetManager,
+    ) -> EngineResult<GameSystemCommand> {
+        for SoldierKilled { entity_id } in storage.read(&mut self.killed) {
+            // a new level may have come in between
+            if storage.is_alive(entity_id) {
+                storage.set(entity_id, Some(Corpse { since: frames }))?;
+            }
+        }
+        let mut remains = storage
+            .iter::<(&Corpse, Option<&Decal>)>()
+            .map(|(id, (corpse, decal))| (id, corpse.since, decal.is_some()))
+            .collect::<Vec<_>>();
+        // stable: remains of the same age stay in index order
+        remains.sort_by_key(|(_, since, _)| *since);
+        let excess = remains.len().saturating_sub(self.policy.limit);
+        let mut commands = storage.commands();
+        for (entity_id, _, _) in &remains[..excess] {
+            debug!("corpse limit reached, despawn {}", entity_id.index());
+            commands.despawn(*entity_id);
+        }
+        for &(entity_id, since, is_decal) in &remains[excess..] {
+            if is_decal || !self.is_expired(frames, since) {
+                continue;
+            }
+            commands.run(move |storage| into_decal(storage, entity_id));
+        }
+        Ok(GameSystemCommand::Nothing)
+    }
+}
+
+/// Replaces the corpse with an entity holding only what the renderer draws.
+/// The death animation has long finished, and a finished animation shows
+/// its last frame.
+fn into_decal(storage: &mut ComponentStorage, entity_id: EntityID) -> EngineResult<()> {
+    let (Some(corpse), Some(sprite), Some(position)) = (
+        storage.get::<Corpse>(entity_id).map(|x| *x),
+        storage.get::<components::Sprite>(entity_id).map(|x| *x),
+        storage.get::<components::Position>(entity_id).map(|x| *x),
+    ) else {
+        return Ok(());
+    };
+    let mut bundle = EntityBundle::new()
+        .put(corpse)
+        .put(Decal)
+        .put(sprite)
+        .put(position);
+    if let Some(scale) = storage.get::<components::ScaleRatio>(entity_id).map(|x| *x) {
+        bundle = bundle.put(scale);
+    }
+    if let Some(shift) = storage
+        .get::<components::HeightShift>(entity_id)
+        .map(|x| *x)
+    {
+        bundle = bundle.put(shift);
+    }
+    storage.remove_entity(entity_id);
+    storage.append(bundle);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use engine::{Query, Vec2f};
+
+    use super::*;
+    use crate::game_scene::{components::*, compose_component_storage};
+
+    fn corpse(storage: &mut ComponentStorage, since: usize) -> EntityID {
+        storage.append(
+            EntityBundle::new()
+                .put(Corpse { since })
+                .put(ActorState::Dead(usize::MAX))
+                .put(NpcPlan::default())
+                .put(Sprite::with_texture("corpse"))
+                .put(Position(Vec2f::new(since as Float, 0.0)))
+                .put(ScaleRatio(0.7)),
+        )
+    }
+
+    fn run(system: &mut CorpseSystem, storage: &mut ComponentStorage, frames: usize) {
+        system
+            .update(frames, 0.0, storage, &AssetManager::default())
+            .unwrap();
+        storage.apply_commands().unwrap();
+    }
+
+    fn remains(storage: &ComponentStorage) -> Vec<(usize, bool)> {
+        storage
+            .iter::<(&Corpse, Option<&Decal>)>()
+            .map(|(_, (corpse, decal))| (corpse.since, decal.is_some()))
+            .collect()
+    }
+
+    #[test]
+    fn expired_corpse_becomes_a_decal() {
+        let mut storage = compose_component_storage().unwrap();
+        let mut system = CorpseSystem::new(CorpsePolicy::default());
+        corpse(&mut storage, 0);
+        corpse(&mut storage, 100);
+
+        let timeout = StepRate::default().steps(CORPSE_TIMEOUT);
+        run(&mut system, &mut storage, timeout - 1);
+        assert_eq!(remains(&storage), vec![(0, false), (100, false)]);
+
+        run(&mut system, &mut storage, timeout);
+        assert_eq!(remains(&storage), vec![(0, true), (100, false)]);
+        // the decal is all sprite
+        let query = Query::new().with_component::<Decal>();
+        let decal = storage.fetch_first_entity(&query).unwrap();
+        assert!(storage.has_component::<Sprite>(decal));
+        assert!(storage.has_component::<ScaleRatio>(decal));
+        assert!(!storage.has_component::<ActorState>(decal));
+        assert!(!storage.has_component::<NpcPlan>(decal));
+        assert_eq!(storage.len(), 2);
+    }
+
+    #[test]
+    fn killed_soldier_becomes_a_corpse() {
+        let mut storage = compose_component_storage().unwrap();
+        let mut system = CorpseSystem::new(CorpsePolicy::default());
+        let entity_id = storage.append(EntityBundle::new().put(ActorState::Dead(usize::MAX)));
+        storage.send(SoldierKilled { entity_id }).unwrap();
+        run(&mut system, &mut storage, 5);
+        assert_eq!(remains(&storage), vec![(5, false)]);
+        // the event is read once
+        run(&mut system, &mut storage, 6);
+        assert_eq!(remains(&storage), vec![(5, false)]);
+    }
+
+    #[test]
+    fn oldest_remains_go_over_the_limit() {
+        let mut storage = compose_component_storage().unwrap();
+        let mut system = CorpseSystem::new(CorpsePolicy {
+            limit: 2,
+            timeout: None,
+        });
+        for since in [30, 10, 20] {
+            corpse(&mut storage, since);
+        }
+        run(&mut system, &mut storage, 1_000_000);
+        assert_eq!(remains(&storage), vec![(30, false), (20, false)]);
+
+        // decals count against the limit too
+        let mut system = CorpseSystem::new(CorpsePolicy {
+            limit: 2,
+            timeout: Some(Duration::ZERO),
+        });
+        run(&mut system, &mut storage, 1_000_000);
+        assert_eq!(remains(&storage), vec![(30, true), (20, true)]);
+        corpse(&mut storage, 40);
+        run(&mut system, &mut storage, 1_000_000);
+        assert_eq!(remains(&storage), vec![(30, true), (40, true)]);
+    }
+}
@@ -1,71 +1,51 @@
 use engine::{
-    refresh_cached_entity,
+    game_scene::{SceneEvent, SceneParameters},
     systems::{GameSystem, GameSystemCommand},
-    AssetManager, ComponentStorage, EngineResult, EntityID, Float, Query,
+    AssetManager, ComponentStorage, EngineResult, EventReader, Float,
 };
 use log::{debug, info};
 
-use super::{components, subsystems::ray_cast_from_entity};
+use super::{
+    components,
+    events::ShotFired,
+    subsystems::{ray_cast_from_entity, RayHit},
+};
 
+/// Lands the shots fired, each once its deadline comes
 pub struct DamageSystem {
-    frames: usize,
-    maze_id: EntityID,
+    shots: EventReader<ShotFired>,
+    /// Shots read whose deadline hasn't come yet; events don't last that
+    /// long
+    pending: Vec<ShotFired>,
 }
 
 impl DamageSystem {
     pub fn new() -> Self {
         Self {
-            frames: Default::default(),
-            maze_id: Default::default(),
+            shots: EventReader::new(),
+            pending: Vec::new(),
         }
     }
 
-    fn update_storage_cache(&mut self, storage: &ComponentStorage) -> EngineResult<()> {
-        refresh_cached_entity::<components::Maze>(storage, &mut self.maze_id, "[v2.damage] maze")
-    }
-
-    fn process_shot(
-        &self,
-        storage: &mut ComponentStorage,
-        entity_id: EntityID,
-    ) -> EngineResult<()> {
-        let Some(shot) = storage.get::<components::Shot>(entity_id).map(|x| *x) else {
-            return Ok(());
-        };
-        if self.frames < shot.deadline {
-            return Ok(());
-        }
-        // consume the shot so it can't be applied twice
-        storage.set::<components::Shot>(entity_id, None)?;
-        // TODO: it's a lazy implementation to obtain the shot damage value
-        // The correct approach is to provide the damage value as part of the Shot component
-        // In the future, user can change weapon type but damaged will be calculated based on
-        // the currently selected type but not that one which used for shooting
-        // Now I don't think that is a problem because there is no option to change ammo
-        let Some(weapon_damage) = storage
-            .get::<components::Weapon>(entity_id)
-            .map(|x| x.damage)
-        else {
-            return Ok(());
-        };
-        let Some(target_id) =
-            ray_cast_from_entity(entity_id, storage, self.maze_id, shot.position, shot.angle)?
+    fn process_shot(&self, storage: &ComponentStorage, shot: &ShotFired) -> EngineResult<()> {
+        // walls consume the shot without taking damage
+        let Some(RayHit::Entity(target_id)) =
+            ray_cast_from_entity(shot.shooter, storage, shot.position, shot.angle)?
         else {
             return Ok(());
         };
-        // the ray caster reports the maze entity on a wall hit;
-        // walls consume the shot without taking damage
-        if target_id == self.maze_id {
-            return Ok(());
-        }
         debug!("shot hit entity {}", target_id.index());
-        // accumulate damages
-        let total_damage = weapon_damage
-            + storage
-                .get::<components::Damage>(target_id)
-                .map(|x| x.0)
-                .unwrap_or_default();
-        storage.set::<components::Damage>(target_id, Some(components::Damage(total_damage)))?;
+        // accumulate damages: deferred, so a target hit by several shots
+        // in one step adds them all up
+        let weapon_damage = shot.damage;
+        storage.commands().run(move |storage| {
+            let total_damage = weapon_damage
+                + storage
+                    .get::<components::Damage>(target_id)
+                    .map(|x| x.0)
+                    .unwrap_or_default();
+            storage.set(target_id, Some(components::Damage(total_damage)))
+        });
         Ok(())
     }
 }
@@ -73,10 +53,9 @@ impl DamageSystem {
 impl GameSystem for DamageSystem {
     fn setup(
         &mut self,
-        storage: &mut ComponentStorage,
+        _storage: &mut ComponentStorage,
         _asset_manager: &AssetManager,
     ) -> EngineResult<()> {
-        self.update_storage_cache(storage)?;
         info!("setup ok");
         Ok(())
     }
@@ -88,14 +67,28 @@ impl GameSystem for DamageSystem {
         storage: &mut ComponentStorage,
         _asset_manager: &AssetManager,
     ) -> EngineResult<GameSystemCommand> {
-        self.update_storage_cache(storage)?;
-        self.frames = frames;
-
-        let query = Query::new().with_component::<components::Shot>();
-        let entities = storage.fetch_entities(&query);
-        for entity_id in entities {
-            self.process_shot(storage, entity_id)?;
+        self.pending.extend(storage.read(&mut self.shots));
+        let (due, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|shot| frames >= shot.deadline);
+        self.pending = pending;
+        for shot in &due {
+            self.process_shot(storage, shot)?;
         }
         Ok(GameSystemCommand::Nothing)
     }
+
+    fn on_scene_event(
+        &mut self,
+        storage: &mut ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        // shots in flight belong to the level left behind
+        if event == SceneEvent::Enter {
+            storage.read(&mut self.shots);
+            self.pending.clear();
+        }
+        Ok(())
+    }
 }
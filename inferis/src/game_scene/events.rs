@@ -0,0 +1,26 @@
+use engine::{EntityID, Float, Vec2f};
+
+use super::components::HealthType;
+
+/// A soldier died; its entity stays behind as a corpse
+#[derive(Clone, Copy)]
+pub struct SoldierKilled {
+    pub entity_id: EntityID,
+}
+
+/// A weapon went off. The shot hits whatever is in its line once its
+/// deadline comes, which gives the shooter's animation time to play.
+#[derive(Clone, Copy, Debug)]
+pub struct ShotFired {
+    pub shooter: EntityID,
+    pub position: Vec2f,
+    pub angle: Float,
+    /// Of the weapon the shot came from, whatever the shooter holds by the
+    /// time it lands
+    pub damage: HealthType,
+    pub deadline: usize,
+}
+
+/// No soldier is left standing on the level
+#[derive(Clone, Copy)]
+pub struct LevelWon;
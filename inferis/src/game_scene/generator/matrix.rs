@@ -74,11 +74,11 @@ pub fn size(matrix: &Matrix) -> Option<Dimension> {
 
 pub fn noise_matrix(
     matrix: &mut Matrix,
+    rng: &mut impl Rng,
     noise_density: u8,
     val_on: MatrixElement,
     val_off: MatrixElement,
 ) {
-    let mut rng = rand::rng();
     for row in matrix.iter_mut() {
         for elem in row {
             let val: u8 = rng.random_range(1..=100);
@@ -204,6 +204,7 @@ pub fn regions(matrix: &Matrix, value: MatrixElement) -> Vec<HashSet<Position>>
 }
 
 pub fn generate_matrix(
+    rng: &mut impl Rng,
     rows: usize,
     cols: usize,
     val_on: MatrixElement,
@@ -213,7 +214,7 @@ pub fn generate_matrix(
     let noise_density = 58;
     let iterations = 3;
     let mut matrix = vec![vec![0; cols]; rows];
-    noise_matrix(&mut matrix, noise_density, val_on, val_off);
+    noise_matrix(&mut matrix, rng, noise_density, val_on, val_off);
     fill_borders(&mut matrix, val_on);
     for _ in 0..iterations {
         matrix = moore_neighborhood(&matrix, val_on, val_off)?;
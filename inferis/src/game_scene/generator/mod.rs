@@ -1,25 +1,23 @@
 pub mod matrix;
 
+use chacha20::ChaCha12Rng;
 use engine::{
+    fetch_first,
+    game_scene::SceneParameters,
     systems::{GameSystem, GameSystemCommand},
-    AssetManager, ComponentStorage, EngineError, EngineResult, EntityBundle, EntityID, Float,
-    SizeFloat, Vec2f,
+    AssetManager, ComponentStorage, EngineError, EngineResult, EntityID, Float, Vec2f,
 };
-use log::info;
-use rand::seq::SliceRandom;
+use log::{info, warn};
+use rand::{seq::SliceRandom, RngExt, SeedableRng};
 
-use crate::resource::{SCENE_PARAM_INVALIDATE, WORLD_TORCH_GREEN_ANIM, WORLD_TORCH_RED_ANIM};
+use crate::resource::{
+    FILE_SAVED_GAME, SCENE_PARAM_INVALIDATE, SCENE_PARAM_RESTORE, SCENE_PARAM_SEED,
+    WORLD_TORCH_GREEN_ANIM, WORLD_TORCH_RED_ANIM,
+};
 
 use self::matrix::{contours, generate_matrix, regions, MatrixElement};
 
-use super::components::*;
-
-pub const PLAYER_SHOTGUN_DAMAGE: HealthType = 27;
-pub const PLAYER_SHOTGUN_RECHARGE_FRAMES: usize = 45;
-
-pub const NPC_SOLDIER_HEALTH: HealthType = 100;
-pub const NPC_SOLDIER_SHOTGUN_DAMAGE: HealthType = 4;
-pub const NPC_SOLDIER_SHOTGUN_RECHARGE_FRAMES: usize = 30;
+use super::{components::*, prefabs::LevelPrefabs};
 
 // the maze may be resized freely: ray casts are bounded by the maze's own
 // dimensions (see Maze::ray_cast_steps), not by a shared constant
@@ -30,24 +28,53 @@ const TILE_WALL: MatrixElement = 1;
 const TILE_FLOOR: MatrixElement = 0;
 const REGION_THRESHOLD: usize = 3;
 
-#[derive(Default)]
 pub struct GeneratorSystem {
     player_id: EntityID,
-    maze_id: EntityID,
+    /// The session's seed, which `LevelSeeds` starts from
+    seed: u64,
+    prefabs: LevelPrefabs,
 }
 
 impl GeneratorSystem {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(seed: u64) -> Self {
+        Self {
+            player_id: Default::default(),
+            seed,
+            prefabs: LevelPrefabs::default(),
+        }
+    }
+
+    /// The seed passed along with an `invalidate`, or the next one in line.
+    fn level_seed(storage: &ComponentStorage, params: &SceneParameters) -> EngineResult<u64> {
+        let Some(value) = params.get(SCENE_PARAM_SEED) else {
+            return Self::next_seed(storage);
+        };
+        value.parse().map_err(|_| {
+            EngineError::ResourceParseError(format!("[v2.generator] bad level seed: {value}"))
+        })
+    }
+
+    /// Draws from the scene's `LevelSeeds`, which outlive the levels
+    fn next_seed(storage: &ComponentStorage) -> EngineResult<u64> {
+        storage
+            .resource_mut::<LevelSeeds>()
+            .map(|mut seeds| seeds.0.random())
+            .ok_or(EngineError::ResourceNotFound(
+                "[v2.generator] LevelSeeds".to_string(),
+            ))
     }
 
     fn generate_level(
         &mut self,
         frames: usize,
         storage: &mut ComponentStorage,
+        seed: u64,
     ) -> EngineResult<()> {
+        info!("level seed {seed}");
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
         storage.remove_all_entities();
         let mut matrix = generate_matrix(
+            &mut rng,
             MAZE_ROWS,
             MAZE_COLS,
             TILE_WALL,
@@ -67,15 +94,22 @@ impl GeneratorSystem {
                 }
             }
         }
-        let mut available_places = regions(&matrix, TILE_FLOOR)
+        let mut floor = regions(&matrix, TILE_FLOOR)
             .first()
             .ok_or(EngineError::unexpected_state(
                 "[v2.generator] no empty spaces",
             ))?
             .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        // a region is a hash set: put its cells in a fixed order first, or
+        // the same seed would shuffle them into different places every run
+        floor.sort_unstable_by_key(|p| (p.row, p.col));
+        floor.shuffle(&mut rng);
+        let mut available_places = floor
+            .into_iter()
             .map(|p| Vec2f::new(p.col as Float, p.row as Float))
             .collect::<Vec<Vec2f>>();
-        available_places.shuffle(&mut rand::rng());
 
         let offset = Vec2f::new(0.5, 0.5);
         {
@@ -84,7 +118,13 @@ impl GeneratorSystem {
                     "[v2.generator] no place for player position",
                 ));
             };
-            self.player_id = storage.append(&bundle_player(pos + offset));
+            let bundle = storage
+                .prefab_bundle(&self.prefabs.player)?
+                .put(ActorState::Undefined)
+                .put(ControllerState::default())
+                .put(Position(pos + offset))
+                .put(Angle(0.0));
+            self.player_id = storage.append(bundle);
         }
         // npc
         #[cfg(not(debug_assertions))]
@@ -95,7 +135,12 @@ impl GeneratorSystem {
             let Some(pos) = available_places.pop() else {
                 break;
             };
-            storage.append(&bundle_npc_soldier(pos + offset));
+            let bundle = storage
+                .prefab_bundle(&self.prefabs.npc_soldier)?
+                .put(ActorState::Undefined)
+                .put(NpcPlan::default())
+                .put(Position(pos + offset));
+            storage.append(bundle);
         }
 
         let maze = Maze { matrix, contour };
@@ -112,41 +157,46 @@ impl GeneratorSystem {
             let left = maze.is_wall(*pos - dx);
             let bottom = maze.is_wall(*pos + dy);
             let right = maze.is_wall(*pos + dx);
-            if left && top {
-                storage.append(&bundle_torch(
-                    TorchStyle::Green,
-                    *pos + Vec2f::new(0.1, 0.1),
-                    frames,
-                ));
+            let (animation_id, shift) = if left && top {
+                (WORLD_TORCH_GREEN_ANIM, Vec2f::new(0.1, 0.1))
             } else if top && right {
-                storage.append(&bundle_torch(
-                    TorchStyle::Red,
-                    *pos + Vec2f::new(0.9, 0.1),
-                    frames,
-                ));
+                (WORLD_TORCH_RED_ANIM, Vec2f::new(0.9, 0.1))
             } else if bottom && left {
-                storage.append(&bundle_torch(
-                    TorchStyle::Red,
-                    *pos + Vec2f::new(0.1, 0.9),
-                    frames,
-                ));
+                (WORLD_TORCH_RED_ANIM, Vec2f::new(0.1, 0.9))
             } else if bottom && right {
-                storage.append(&bundle_torch(
-                    TorchStyle::Green,
-                    *pos + Vec2f::new(0.9, 0.9),
-                    frames,
-                ));
+                (WORLD_TORCH_GREEN_ANIM, Vec2f::new(0.9, 0.9))
             } else {
                 continue;
-            }
+            };
+            let bundle = storage
+                .prefab_bundle(&self.prefabs.torch)?
+                .put(Sprite::with_animation(animation_id, frames, usize::MAX))
+                .put(Position(*pos + shift));
+            storage.append(bundle);
             decorations -= 1;
             if decorations == 0 {
                 break;
             }
         }
 
-        let maze_bundle = EntityBundle::new().put(maze);
-        self.maze_id = storage.append(&maze_bundle);
+        storage.insert_resource(maze);
+        storage.insert_resource(LevelRng(rng));
+        storage.insert_resource(LevelStart(storage.tick()));
+        Ok(())
+    }
+
+    /// Loads the game saved on pause, or starts a new level if there is
+    /// none to load
+    fn restore_level(&mut self, storage: &mut ComponentStorage) -> EngineResult<()> {
+        if let Err(err) = storage.load_snapshot(FILE_SAVED_GAME) {
+            warn!("{err}, starting a new level");
+            let seed = Self::next_seed(storage)?;
+            return self.generate_level(storage.tick(), storage, seed);
+        }
+        self.player_id = fetch_first::<PlayerTag>(storage).ok_or(EngineError::unexpected_state(
+            "[v2.generator] saved game has no player",
+        ))?;
+        info!("restored {FILE_SAVED_GAME}");
         Ok(())
     }
 }
@@ -155,9 +205,12 @@ impl GameSystem for GeneratorSystem {
     fn setup(
         &mut self,
         storage: &mut engine::ComponentStorage,
-        _asset_manager: &engine::AssetManager,
+        asset_manager: &engine::AssetManager,
     ) -> engine::EngineResult<()> {
-        self.generate_level(0, storage)?;
+        self.prefabs = LevelPrefabs::new(asset_manager)?;
+        storage.insert_resource(LevelSeeds(ChaCha12Rng::seed_from_u64(self.seed)));
+        let seed = Self::next_seed(storage)?;
+        self.generate_level(0, storage, seed)?;
         info!("setup ok");
         Ok(())
     }
@@ -175,77 +228,82 @@ impl GameSystem for GeneratorSystem {
     fn on_scene_event(
         &mut self,
         storage: &mut ComponentStorage,
-        _event: engine::game_scene::SceneEvent,
-        params: &engine::game_scene::SceneParameters,
+        event: engine::game_scene::SceneEvent,
+        params: &SceneParameters,
     ) -> EngineResult<()> {
-        storage.set(self.player_id, Some(ControllerState::default()))?;
+        if event != engine::game_scene::SceneEvent::Enter {
+            return Ok(());
+        }
+        if params.contains_key(SCENE_PARAM_RESTORE) {
+            self.restore_level(storage)?;
+        }
         let is_invalidated = params.contains_key(SCENE_PARAM_INVALIDATE);
         if is_invalidated {
-            self.generate_level(0, storage)?;
+            let seed = Self::level_seed(storage, params)?;
+            self.generate_level(0, storage, seed)?;
         }
         Ok(())
     }
 }
 
-fn bundle_player(position: Vec2f) -> EntityBundle {
-    EntityBundle::new()
-        .put(PlayerTag)
-        .put(ActorState::Undefined)
-        .put(ControllerState::default())
-        .put(weapon(
-            PLAYER_SHOTGUN_DAMAGE,
-            PLAYER_SHOTGUN_RECHARGE_FRAMES,
-            usize::MAX,
-        ))
-        .put(Health(500))
-        .put(Velocity(7.5))
-        .put(RotationSpeed(2.5))
-        .put(Position(position))
-        .put(Angle(0.0))
-        .put(BoundingBox(SizeFloat::new(0.7, 0.7)))
-}
+#[cfg(test)]
+mod test {
+    use engine::Query;
 
-fn bundle_npc_soldier(position: Vec2f) -> EntityBundle {
-    EntityBundle::new()
-        .put(weapon(
-            NPC_SOLDIER_SHOTGUN_DAMAGE,
-            NPC_SOLDIER_SHOTGUN_RECHARGE_FRAMES,
-            usize::MAX,
-        ))
-        .put(Position(position))
-        .put(NpcTag)
-        .put(ActorState::Undefined)
-        .put(NpcPlan::default())
-        .put(Health(NPC_SOLDIER_HEALTH))
-        .put(ScaleRatio(0.7))
-        .put(HeightShift(0.27))
-        .put(BoundingBox(SizeFloat::new(0.7, 0.7)))
-        .put(Velocity(4.3))
-}
+    use super::*;
+    use crate::game_scene::compose_component_storage;
 
-fn weapon(damage: HealthType, recharge_time: usize, ammo_count: usize) -> Weapon {
-    Weapon {
-        damage,
-        recharge_time,
-        state: WeaponState::Undefined,
-        ammo_count,
+    /// The maze and the sorted positions of everything placed in it.
+    fn level(seed: u64) -> (matrix::Matrix, Vec<(Float, Float)>, u64) {
+        let mut storage = compose_component_storage().unwrap();
+        let mut generator = GeneratorSystem::new(0);
+        generator.generate_level(0, &mut storage, seed).unwrap();
+        let maze = storage.resource::<Maze>().unwrap().matrix.clone();
+        let query = Query::new().with_component::<Position>();
+        let mut positions = storage
+            .fetch_entities(&query)
+            .into_iter()
+            .map(|id| storage.get::<Position>(id).unwrap().0)
+            .map(|pos| (pos.x, pos.y))
+            .collect::<Vec<_>>();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // what the soldiers draw next must carry on from the same state too
+        let next = storage.resource_mut::<LevelRng>().unwrap().0.random();
+        (maze, positions, next)
     }
-}
 
-enum TorchStyle {
-    Green,
-    Red,
-}
+    #[test]
+    fn same_seed_same_level() {
+        assert!(level(42) == level(42));
+    }
 
-fn bundle_torch(style: TorchStyle, position: Vec2f, frame: usize) -> EntityBundle {
-    let animation_id = match style {
-        TorchStyle::Green => WORLD_TORCH_GREEN_ANIM,
-        TorchStyle::Red => WORLD_TORCH_RED_ANIM,
-    };
-    EntityBundle::new()
-        .put(Sprite::with_animation(animation_id, frame, usize::MAX))
-        .put(Position(position))
-        .put(ScaleRatio(0.7))
-        .put(HeightShift(0.27))
-    // .put(BoundingBox(SizeFloat::new(0.3, 0.3)))
+    #[test]
+    fn different_seeds_different_levels() {
+        assert!(level(42) != level(43));
+    }
+
+    #[test]
+    fn invalidate_takes_the_seed_from_params() {
+        let seeded = || {
+            let mut storage = compose_component_storage().unwrap();
+            storage.insert_resource(LevelSeeds(ChaCha12Rng::seed_from_u64(0)));
+            storage
+        };
+        let storage = seeded();
+        let mut params = SceneParameters::default();
+        params.insert(SCENE_PARAM_SEED.to_string(), "12345".to_string());
+        assert_eq!(
+            GeneratorSystem::level_seed(&storage, &params).unwrap(),
+            12345
+        );
+        params.insert(SCENE_PARAM_SEED.to_string(), "twelve".to_string());
+        assert!(GeneratorSystem::level_seed(&storage, &params).is_err());
+        // without one, levels follow from the scene's seed
+        let other = seeded();
+        let params = SceneParameters::default();
+        assert_eq!(
+            GeneratorSystem::level_seed(&storage, &params).unwrap(),
+            GeneratorSystem::level_seed(&other, &params).unwrap()
+        );
+    }
 }
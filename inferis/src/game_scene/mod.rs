@@ -1,23 +1,38 @@
 // TODO: make private
 pub mod components;
 mod control;
+mod corpse;
 mod damage;
+mod events;
 mod generator;
 mod movement;
 mod navigation;
 mod npc;
+mod persist;
 mod player;
+mod prefabs;
 mod renderer;
 mod sound;
 mod subsystems;
 
-use engine::{game_scene::GameScene, ComponentStorage, EngineResult};
+use engine::{
+    game_scene::GameScene,
+    schedule::{SystemConfig, SystemLabel},
+    ComponentStorage, EngineResult,
+};
 
 use crate::resource::SCENE_GAME_PLAY;
 
+pub use self::player::MouseLook;
 use self::{
-    control::ControlSystem, damage::DamageSystem, generator::GeneratorSystem,
-    movement::MovementSystem, npc::NpcSystem, player::PlayerSystem, renderer::RendererSystem,
+    control::ControlSystem,
+    corpse::{CorpsePolicy, CorpseSystem},
+    damage::DamageSystem,
+    generator::GeneratorSystem,
+    movement::MovementSystem,
+    npc::NpcSystem,
+    player::PlayerSystem,
+    renderer::RendererSystem,
     sound::SoundSystem,
 };
 
@@ -32,21 +47,61 @@ fn compose_component_storage() -> EngineResult<ComponentStorage> {
     storage.register_component::<components::RotationSpeed>()?;
     storage.register_component::<components::Angle>()?;
     storage.register_component::<components::Health>()?;
+    storage.register_component::<components::MaxHealth>()?;
     storage.register_component::<components::Sprite>()?;
     storage.register_component::<components::ScaleRatio>()?;
     storage.register_component::<components::HeightShift>()?;
-    storage.register_component::<components::Maze>()?;
     storage.register_component::<components::BoundingBox>()?;
     storage.register_component::<components::SoundFx>()?;
     storage.register_component::<components::Weapon>()?;
-    storage.register_component::<components::Shot>()?;
     storage.register_component::<components::Damage>()?;
     storage.register_component::<components::ActorState>()?;
     storage.register_component::<components::NpcPlan>()?;
+    storage.register_component::<components::Corpse>()?;
+    storage.register_component::<components::Decal>()?;
+    storage.register_event::<events::SoldierKilled>();
+    storage.register_event::<events::ShotFired>();
+    storage.register_event::<events::LevelWon>();
+    persist::register_persistent(&mut storage)?;
+    prefabs::register_prefab_components(&mut storage)?;
+    register_debug(&mut storage)?;
     Ok(storage)
 }
 
-pub fn compose_scene() -> EngineResult<GameScene> {
+/// Components shown with their values in a storage dump
+fn register_debug(storage: &mut ComponentStorage) -> EngineResult<()> {
+    storage.register_debug::<components::ControllerState>()?;
+    storage.register_debug::<components::Movement>()?;
+    storage.register_debug::<components::Position>()?;
+    storage.register_debug::<components::Velocity>()?;
+    storage.register_debug::<components::RotationSpeed>()?;
+    storage.register_debug::<components::Angle>()?;
+    storage.register_debug::<components::Health>()?;
+    storage.register_debug::<components::MaxHealth>()?;
+    storage.register_debug::<components::Sprite>()?;
+    storage.register_debug::<components::ScaleRatio>()?;
+    storage.register_debug::<components::HeightShift>()?;
+    storage.register_debug::<components::BoundingBox>()?;
+    storage.register_debug::<components::SoundFx>()?;
+    storage.register_debug::<components::Weapon>()?;
+    storage.register_debug::<components::Damage>()?;
+    storage.register_debug::<components::ActorState>()?;
+    storage.register_debug::<components::NpcPlan>()?;
+    storage.register_debug::<components::Corpse>()?;
+    Ok(())
+}
+
+const LABEL_GENERATOR: SystemLabel = "generator";
+const LABEL_PLAYER: SystemLabel = "player";
+const LABEL_NPC: SystemLabel = "npc";
+const LABEL_CORPSE: SystemLabel = "corpse";
+const LABEL_DAMAGE: SystemLabel = "damage";
+/// Runs last, after every system that requests movement, see
+/// `MovementSystem`
+const LABEL_MOVEMENT: SystemLabel = "movement";
+
+/// `seed` seeds the sequence of levels the scene generates.
+pub fn compose_scene(seed: u64, mouse_look: MouseLook) -> EngineResult<GameScene> {
     let storage = compose_component_storage()?;
     let mut scene = GameScene::new(
         SCENE_GAME_PLAY,
@@ -56,10 +111,37 @@ pub fn compose_scene() -> EngineResult<GameScene> {
     );
     scene.add_sound_system(SoundSystem::new());
     // general purpose systems
-    scene.add_system(GeneratorSystem::new());
-    scene.add_system(PlayerSystem::new());
-    scene.add_system(NpcSystem::new());
-    scene.add_system(DamageSystem::new());
-    scene.add_system(MovementSystem::new());
+    scene.add_system_with(
+        GeneratorSystem::new(seed),
+        SystemConfig::new().label(LABEL_GENERATOR),
+    );
+    scene.add_system_with(
+        PlayerSystem::new(mouse_look),
+        SystemConfig::new()
+            .label(LABEL_PLAYER)
+            .after(LABEL_GENERATOR),
+    );
+    scene.add_system_with(
+        NpcSystem::new(),
+        SystemConfig::new().label(LABEL_NPC).after(LABEL_PLAYER),
+    );
+    scene.add_system_with(
+        CorpseSystem::new(CorpsePolicy::default()),
+        SystemConfig::new().label(LABEL_CORPSE).after(LABEL_NPC),
+    );
+    // shots are fired by the player and the soldiers
+    scene.add_system_with(
+        DamageSystem::new(),
+        SystemConfig::new()
+            .label(LABEL_DAMAGE)
+            .after(LABEL_PLAYER)
+            .after(LABEL_NPC),
+    );
+    scene.add_system_with(
+        MovementSystem::new(),
+        SystemConfig::new().label(LABEL_MOVEMENT).last(),
+    );
+    // fail at startup rather than on the first step
+    scene.schedule()?;
     Ok(scene)
 }
@@ -1,41 +1,44 @@
+use std::{cell::RefMut, time::Duration};
+
 use components::SoundFx;
 use engine::{
-    game_scene::SceneParameters, refresh_cached_entity, systems::GameSystem, ComponentStorage,
-    EngineError, EngineResult, EntityID, Float, Query, Vec2f,
+    refresh_cached_entity, systems::GameSystem, time::Time, ComponentStorage, EngineError,
+    EngineResult, EntityID, Float, Query, Vec2f,
 };
 use log::info;
 use rand::RngExt;
 
 use crate::{
-    game_scene::subsystems::{can_shoot, get_actor_state, update_weapon_state},
+    game_scene::subsystems::{can_shoot, fire_weapon, get_actor_state, update_weapon_state},
     resource::{
         NPC_SOLDIER_ATTACK, NPC_SOLDIER_DAMAGE, NPC_SOLDIER_DEATH, NPC_SOLDIER_IDLE,
-        NPC_SOLDIER_WALK, SCENE_MAIN_MENU, SCENE_PARAM_WIN, SOUND_NPC_ATTACK, SOUND_NPC_DEATH,
-        SOUND_NPC_PAIN,
+        NPC_SOLDIER_WALK, SOUND_NPC_ATTACK, SOUND_NPC_DEATH, SOUND_NPC_PAIN,
     },
 };
 
 use super::{
     components::{self, ActorState, HealthType, NpcIntent, Sprite},
-    generator::{matrix::Position as MatrixPosition, NPC_SOLDIER_HEALTH},
+    events::{LevelWon, SoldierKilled},
+    generator::matrix::Position as MatrixPosition,
     navigation::{cell_at, cell_center, has_line_of_sight, Flood},
-    subsystems::{is_actor_dead, ray_cast_from_entity, replace_actor_state, updated_state},
+    subsystems::{is_actor_dead, ray_cast_from_entity, replace_actor_state, updated_state, RayHit},
 };
 
-pub const NPC_SOLDIER_SHOT_DEADLINE: usize = 10;
-pub const NPC_SOLDIER_DAMAGE_RECOVER: usize = 20;
+pub const NPC_SOLDIER_SHOT_DEADLINE: Duration = Duration::from_millis(167);
+pub const NPC_SOLDIER_DAMAGE_RECOVER: Duration = Duration::from_millis(333);
 /// Distance at which a soldier stops closing in and starts shooting.
 const NPC_SOLDIER_ATTACK_DISTANCE: Float = 5.0;
 
-/// Health at or below which a soldier breaks off and looks for cover.
-const NPC_SOLDIER_CRITICAL_HEALTH: HealthType = NPC_SOLDIER_HEALTH / 4;
+/// The maximum health of a soldier whose prefab doesn't give one, or that
+/// comes from a save older than `MaxHealth`: the built-in prefab's.
+const NPC_SOLDIER_DEFAULT_MAX_HEALTH: HealthType = 100;
 /// How long a wounded soldier stays out of sight before coming back.
-/// Steps are a fixed 1/60s, so these counts are honest durations.
-const NPC_SOLDIER_HIDE_FRAMES: usize = 4 * 60;
+const NPC_SOLDIER_HIDE_TIME: Duration = Duration::from_secs(4);
 /// How long a soldier looks around on reaching the player's last known spot.
-const NPC_SOLDIER_LOOK_AROUND_FRAMES: usize = 45;
-/// Range of pauses between wander legs, so a group does not move in lockstep.
-const NPC_SOLDIER_WANDER_PAUSE: std::ops::Range<usize> = 30..150;
+const NPC_SOLDIER_LOOK_AROUND_TIME: Duration = Duration::from_millis(750);
+/// Range of pauses between wander legs in milliseconds, so a group does not
+/// move in lockstep.
+const NPC_SOLDIER_WANDER_PAUSE: std::ops::Range<u64> = 500..2500;
 /// How far a soldier steps aside after being hit. A dodge is a sidestep,
 /// not a sprint: far enough not to be a stationary target, near enough to
 /// stay in the fight and in view.
@@ -52,7 +55,7 @@ const NPC_MIN_PROGRESS: Float = 0.01;
 /// How long a soldier shoves at a blockage before giving up on its route.
 /// Soldiers are obstacles to each other, so a route planned around the walls
 /// can still be blocked by a comrade standing in it.
-const NPC_SOLDIER_STUCK_FRAMES: usize = 45;
+const NPC_SOLDIER_STUCK_TIME: Duration = Duration::from_millis(750);
 
 /// What a soldier is doing on this step.
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -85,11 +88,11 @@ const TARGET_CHECK_INTERVALS: [(Float, usize); 3] = [
 #[derive(Default)]
 pub struct NpcSystem {
     player_id: EntityID,
-    maze_id: EntityID,
     // short term cache
     player_position: Vec2f,
     frames: usize,
     delta_time: f32,
+    time: Time,
 }
 
 impl NpcSystem {
@@ -102,8 +105,7 @@ impl NpcSystem {
             storage,
             &mut self.player_id,
             "[v2.npc] player",
-        )?;
-        refresh_cached_entity::<components::Maze>(storage, &mut self.maze_id, "[v2.npc] maze")
+        )
     }
 
     fn prefetch(&mut self, storage: &ComponentStorage) -> EngineResult<()> {
@@ -122,12 +124,13 @@ impl NpcSystem {
         // being hit, dying, or recovering from a hit overrides whatever the
         // soldier had in mind
         if let Some(state) =
-            updated_state(self.frames, storage, entity_id, NPC_SOLDIER_DAMAGE_RECOVER)?
+            updated_state(&self.time, storage, entity_id, NPC_SOLDIER_DAMAGE_RECOVER)?
         {
             if matches!(state, ActorState::Dead(_)) {
                 storage.set::<components::NpcTag>(entity_id, None)?;
                 storage.set::<components::BoundingBox>(entity_id, None)?;
                 storage.set::<components::Angle>(entity_id, None)?;
+                storage.send(SoldierKilled { entity_id })?;
             } else if matches!(state, ActorState::Damaged(_)) {
                 self.react_to_damage(storage, entity_id)?;
             }
@@ -139,7 +142,7 @@ impl NpcSystem {
             storage.set(entity_id, Some(state))?;
             self.update_npc_view(storage, entity_id, &state)?;
             self.update_npc_sound(storage, entity_id, &state)?;
-            _ = update_weapon_state(self.frames, storage, entity_id);
+            _ = update_weapon_state(&self.time, storage, entity_id, false);
             return Ok(());
         }
         // still flinching from the last hit, or already dead
@@ -149,7 +152,7 @@ impl NpcSystem {
         ) {
             return Ok(());
         }
-        _ = update_weapon_state(self.frames, storage, entity_id);
+        _ = update_weapon_state(&self.time, storage, entity_id, false);
 
         let action = self.decide(storage, entity_id)?;
         let state = self.perform(storage, entity_id, action)?;
@@ -228,7 +231,7 @@ impl NpcSystem {
         if !plan.route.is_empty() {
             return Some(NpcAction::Follow);
         }
-        if self.frames < plan.hold_until {
+        if !self.time.is_due(plan.hold_until) {
             return Some(NpcAction::Hold);
         }
         None
@@ -253,9 +256,8 @@ impl NpcSystem {
             return Ok(standing_verdict);
         }
         let angle = vector.y.atan2(vector.x);
-        let target_id =
-            ray_cast_from_entity(entity_id, storage, self.maze_id, npc_position, angle)?;
-        let visible = target_id == Some(self.player_id);
+        let hit = ray_cast_from_entity(entity_id, storage, npc_position, angle)?;
+        let visible = hit == Some(RayHit::Entity(self.player_id));
         if let Some(mut plan) = storage.get_mut::<components::NpcPlan>(entity_id) {
             plan.player_visible = visible;
         }
@@ -287,7 +289,7 @@ impl NpcSystem {
             let Some(plan) = storage.get::<components::NpcPlan>(entity_id) else {
                 return Ok(());
             };
-            let idle = plan.route.is_empty() && self.frames >= plan.hold_until;
+            let idle = plan.route.is_empty() && self.time.is_due(plan.hold_until);
             (plan.intent, idle, plan.last_seen)
         };
         if !idle {
@@ -313,10 +315,10 @@ impl NpcSystem {
                 }
             }
             // arrived, or nowhere to go: look around, then drift off
-            self.set_wander_plan(storage, entity_id, NPC_SOLDIER_LOOK_AROUND_FRAMES)?;
+            self.set_wander_plan(storage, entity_id, NPC_SOLDIER_LOOK_AROUND_TIME)?;
             return Ok(());
         }
-        self.set_wander_plan(storage, entity_id, 0)
+        self.set_wander_plan(storage, entity_id, Duration::ZERO)
     }
 
     /// Routes to a random reachable tile, then stands for a moment.
@@ -324,20 +326,23 @@ impl NpcSystem {
         &mut self,
         storage: &mut engine::ComponentStorage,
         entity_id: EntityID,
-        extra_hold: usize,
+        extra_hold: Duration,
     ) -> EngineResult<()> {
         let position = storage
             .get::<components::Position>(entity_id)
             .map(|x| x.0)
             .unwrap_or_default();
+        let mut level_rng = self.level_rng(storage)?;
+        let rng = &mut level_rng.0;
         let route = self.plan_route(storage, entity_id, |flood, _, _| {
             let reached = flood.reached();
             if reached.len() < 2 {
                 return None;
             }
-            Some(reached[rand::rng().random_range(1..reached.len())])
+            Some(reached[rng.random_range(1..reached.len())])
         });
-        let pause = rand::rng().random_range(NPC_SOLDIER_WANDER_PAUSE) + extra_hold;
+        let pause = Duration::from_millis(rng.random_range(NPC_SOLDIER_WANDER_PAUSE)) + extra_hold;
+        let pause = self.time.step_rate().steps(pause);
         if let Some(mut plan) = storage.get_mut::<components::NpcPlan>(entity_id) {
             plan.intent = NpcIntent::Wander;
             plan.route = route.unwrap_or_default().into();
@@ -375,18 +380,30 @@ impl NpcSystem {
         Ok(true)
     }
 
+    /// The level's RNG: soldiers' choices must come from the level seed.
+    fn level_rng<'a>(
+        &self,
+        storage: &'a ComponentStorage,
+    ) -> EngineResult<RefMut<'a, components::LevelRng>> {
+        storage
+            .resource_mut::<components::LevelRng>()
+            .ok_or(EngineError::ResourceNotFound(
+                "[v2.npc] LevelRng".to_string(),
+            ))
+    }
+
     /// Floods out from the soldier and routes to whatever `pick` chooses.
     fn plan_route(
         &self,
         storage: &ComponentStorage,
         entity_id: EntityID,
-        pick: impl Fn(&Flood, &components::Maze, Vec2f) -> Option<MatrixPosition>,
+        pick: impl FnOnce(&Flood, &components::Maze, Vec2f) -> Option<MatrixPosition>,
     ) -> Option<Vec<Vec2f>> {
         let position = storage
             .get::<components::Position>(entity_id)
             .map(|x| x.0)?;
         let origin = cell_at(position)?;
-        let maze = storage.get::<components::Maze>(self.maze_id)?;
+        let maze = storage.resource::<components::Maze>()?;
         let flood = Flood::new(&maze, origin, NPC_NAV_FLOOD_CELLS);
         let target = pick(&flood, &maze, position)?;
         flood.route_to(target)
@@ -446,12 +463,9 @@ impl NpcSystem {
         ) else {
             return Ok(());
         };
-        let shot = components::Shot {
-            position,
-            angle,
-            deadline: self.frames + NPC_SOLDIER_SHOT_DEADLINE,
-        };
-        storage.set(entity_id, Some(shot))?;
+        let deadline = self.time.after(NPC_SOLDIER_SHOT_DEADLINE);
+        fire_weapon(storage, entity_id, position, angle, deadline)?;
+        _ = update_weapon_state(&self.time, storage, entity_id, true);
         storage.set(entity_id, Some(SoundFx::once(SOUND_NPC_ATTACK)))?;
         Ok(())
     }
@@ -475,7 +489,8 @@ impl NpcSystem {
                 plan.progress_frame = self.frames;
             }
             plan.last_position = position;
-            if self.frames.saturating_sub(plan.progress_frame) > NPC_SOLDIER_STUCK_FRAMES {
+            let stuck = self.time.step_rate().steps(NPC_SOLDIER_STUCK_TIME);
+            if self.frames.saturating_sub(plan.progress_frame) > stuck {
                 plan.route.clear();
                 plan.progress_frame = self.frames;
                 if plan.intent == NpcIntent::Reposition {
@@ -551,7 +566,10 @@ impl NpcSystem {
             .get::<components::Health>(entity_id)
             .map(|x| x.0)
             .unwrap_or_default();
-        if health <= NPC_SOLDIER_CRITICAL_HEALTH {
+        let max = storage
+            .get::<components::MaxHealth>(entity_id)
+            .map_or(NPC_SOLDIER_DEFAULT_MAX_HEALTH, |x| x.0);
+        if is_critical(health, max) {
             self.set_hide_plan(storage, entity_id)
         } else {
             self.set_reposition_plan(storage, entity_id)
@@ -572,7 +590,7 @@ impl NpcSystem {
         if let Some(mut plan) = storage.get_mut::<components::NpcPlan>(entity_id) {
             plan.intent = NpcIntent::Hide;
             plan.route = route.unwrap_or_default().into();
-            plan.hold_until = self.frames + NPC_SOLDIER_HIDE_FRAMES;
+            plan.hold_until = self.time.after(NPC_SOLDIER_HIDE_TIME);
             plan.pause_after_route = 0;
             plan.progress_frame = self.frames;
             plan.last_position = Vec2f::default();
@@ -586,6 +604,7 @@ impl NpcSystem {
         storage: &mut engine::ComponentStorage,
         entity_id: EntityID,
     ) -> EngineResult<()> {
+        let mut level_rng = self.level_rng(storage)?;
         let route = self.plan_route(storage, entity_id, |flood, _, origin| {
             let candidates = flood
                 .reached()
@@ -599,7 +618,7 @@ impl NpcSystem {
             if candidates.is_empty() {
                 return None;
             }
-            Some(candidates[rand::rng().random_range(0..candidates.len())])
+            Some(candidates[level_rng.0.random_range(0..candidates.len())])
         });
         if let Some(mut plan) = storage.get_mut::<components::NpcPlan>(entity_id) {
             plan.intent = NpcIntent::Reposition;
@@ -692,6 +711,7 @@ impl GameSystem for NpcSystem {
         self.update_storage_cache(storage)?;
         self.prefetch(storage)?;
         self.frames = frames;
+        self.time = Time::of(storage);
         self.delta_time = delta_time;
 
         let query = Query::new().with_component::<components::NpcTag>();
@@ -702,24 +722,32 @@ impl GameSystem for NpcSystem {
             alive_npc |= !is_actor_dead(storage, entity_id);
         }
 
-        let command = if alive_npc {
-            engine::systems::GameSystemCommand::Nothing
-        } else {
-            let mut params = SceneParameters::default();
-            params.insert(SCENE_PARAM_WIN.to_string(), "".to_string());
-            engine::systems::GameSystemCommand::SwitchScene {
-                id: SCENE_MAIN_MENU,
-                params,
-            }
-        };
-        Ok(command)
+        if !alive_npc {
+            // the player system takes the game to the menu
+            storage.send(LevelWon)?;
+        }
+        Ok(engine::systems::GameSystemCommand::Nothing)
     }
 }
 
+/// Whether a soldier left with `health` of the `max` it spawned with breaks
+/// off and looks for cover: at a quarter or less.
+fn is_critical(health: HealthType, max: HealthType) -> bool {
+    health <= max / 4
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn a_quarter_of_the_maximum_is_critical() {
+        assert!(is_critical(25, 100));
+        assert!(!is_critical(26, 100));
+        assert!(is_critical(10, 40));
+        assert!(!is_critical(11, 40));
+    }
+
     #[test]
     fn inside_attack_range_the_cast_runs_every_step() {
         assert_eq!(target_check_interval(0.0), 1);
@@ -0,0 +1,543 @@
+//! What a saved game keeps. Input, sounds and shots in flight are left out:
+//! a restored game starts with no key held and nothing playing.
+
+use chacha20::ChaCha12Rng;
+use engine::{
+    ComponentStorage, EngineError, EngineResult, Persist, SnapshotReader, SnapshotWriter,
+};
+use rand::SeedableRng;
+
+use crate::resource::*;
+
+use super::{components::*, generator::matrix};
+
+/// Every texture a sprite may show; a sprite keeps a `&'static str`, so a
+/// loaded one is looked up here
+const SPRITE_ASSETS: [&str; 10] = [
+    WORLD_TORCH_RED_ANIM,
+    WORLD_TORCH_GREEN_ANIM,
+    WORLD_GAME_OVER,
+    PLAYER_SHOTGUN_SHOT_ANIM,
+    PLAYER_SHOTGUN_IDLE_ANIM,
+    NPC_SOLDIER_IDLE,
+    NPC_SOLDIER_ATTACK,
+    NPC_SOLDIER_DEATH,
+    NPC_SOLDIER_DAMAGE,
+    NPC_SOLDIER_WALK,
+];
+
+pub fn register_persistent(storage: &mut ComponentStorage) -> EngineResult<()> {
+    storage.register_persistent::<PlayerTag>("player")?;
+    storage.register_persistent::<NpcTag>("npc")?;
+    storage.register_persistent::<ControllerState>("controller")?;
+    storage.register_persistent::<Movement>("movement")?;
+    storage.register_persistent::<Position>("position")?;
+    storage.register_persistent::<Velocity>("velocity")?;
+    storage.register_persistent::<RotationSpeed>("rotation_speed")?;
+    storage.register_persistent::<Angle>("angle")?;
+    storage.register_persistent::<Health>("health")?;
+    storage.register_persistent::<MaxHealth>("max_health")?;
+    storage.register_persistent::<Sprite>("sprite")?;
+    storage.register_persistent::<ScaleRatio>("scale_ratio")?;
+    storage.register_persistent::<HeightShift>("height_shift")?;
+    storage.register_persistent::<BoundingBox>("bounding_box")?;
+    storage.register_persistent::<Weapon>("weapon")?;
+    storage.register_persistent::<ActorState>("actor_state")?;
+    storage.register_persistent::<NpcPlan>("npc_plan")?;
+    storage.register_persistent::<Corpse>("corpse")?;
+    storage.register_persistent::<Decal>("decal")?;
+    storage.register_persistent_resource::<Maze>("maze")?;
+    storage.register_persistent_resource::<LevelRng>("level_rng")?;
+    storage.register_persistent_resource::<LevelSeeds>("level_seeds")?;
+    storage.register_persistent_resource::<LevelStart>("level_start")?;
+    Ok(())
+}
+
+fn unexpected(what: &str, tag: u8) -> EngineError {
+    EngineError::ResourceParseError(format!("[v2.persist] unknown {what} {tag}"))
+}
+
+impl Persist for PlayerTag {
+    fn save(&self, _: &mut SnapshotWriter) {}
+
+    fn load(_: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(PlayerTag)
+    }
+}
+
+impl Persist for NpcTag {
+    fn save(&self, _: &mut SnapshotWriter) {}
+
+    fn load(_: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(NpcTag)
+    }
+}
+
+impl Persist for Decal {
+    fn save(&self, _: &mut SnapshotWriter) {}
+
+    fn load(_: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Decal)
+    }
+}
+
+/// Only its presence: no key is held when a game is restored
+impl Persist for ControllerState {
+    fn save(&self, _: &mut SnapshotWriter) {}
+
+    fn load(_: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(ControllerState::default())
+    }
+}
+
+impl Persist for Movement {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.x.save(writer);
+        self.y.save(writer);
+        self.angle.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Movement {
+            x: Persist::load(reader)?,
+            y: Persist::load(reader)?,
+            angle: Persist::load(reader)?,
+        })
+    }
+}
+
+impl Persist for Position {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(Position)
+    }
+}
+
+impl Persist for Velocity {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(Velocity)
+    }
+}
+
+impl Persist for RotationSpeed {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(RotationSpeed)
+    }
+}
+
+impl Persist for Angle {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(Angle)
+    }
+}
+
+impl Persist for Health {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(Health)
+    }
+}
+
+impl Persist for MaxHealth {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(MaxHealth)
+    }
+}
+
+impl Persist for ScaleRatio {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(ScaleRatio)
+    }
+}
+
+impl Persist for HeightShift {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(HeightShift)
+    }
+}
+
+impl Persist for BoundingBox {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(BoundingBox)
+    }
+}
+
+impl Persist for Corpse {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.since.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Ok(Corpse {
+            since: Persist::load(reader)?,
+        })
+    }
+}
+
+impl Persist for Sprite {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        match self.view {
+            SpriteView::Texture { asset_id } => {
+                0u8.save(writer);
+                asset_id.to_string().save(writer);
+            }
+            SpriteView::Animation {
+                asset_id,
+                frame_start,
+                times,
+            } => {
+                1u8.save(writer);
+                asset_id.to_string().save(writer);
+                frame_start.save(writer);
+                times.save(writer);
+            }
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let tag = u8::load(reader)?;
+        let name = String::load(reader)?;
+        let asset_id = SPRITE_ASSETS
+            .into_iter()
+            .find(|x| *x == name)
+            .ok_or_else(|| {
+                EngineError::ResourceParseError(format!("[v2.persist] unknown sprite {name}"))
+            })?;
+        match tag {
+            0 => Ok(Sprite::with_texture(asset_id)),
+            1 => Ok(Sprite::with_animation(
+                asset_id,
+                Persist::load(reader)?,
+                Persist::load(reader)?,
+            )),
+            tag => Err(unexpected("sprite view", tag)),
+        }
+    }
+}
+
+/// Tag, then the deadline if there is one
+fn save_deadline(tag: u8, deadline: Option<usize>, writer: &mut SnapshotWriter) {
+    tag.save(writer);
+    if let Some(deadline) = deadline {
+        deadline.save(writer);
+    }
+}
+
+impl Persist for ActorState {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        match *self {
+            ActorState::Undefined => save_deadline(0, None, writer),
+            ActorState::Idle(x) => save_deadline(1, Some(x), writer),
+            ActorState::Dead(x) => save_deadline(2, Some(x), writer),
+            ActorState::Attack(x) => save_deadline(3, Some(x), writer),
+            ActorState::Walk(x) => save_deadline(4, Some(x), writer),
+            ActorState::Damaged(x) => save_deadline(5, Some(x), writer),
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let state = match u8::load(reader)? {
+            0 => ActorState::Undefined,
+            1 => ActorState::Idle(Persist::load(reader)?),
+            2 => ActorState::Dead(Persist::load(reader)?),
+            3 => ActorState::Attack(Persist::load(reader)?),
+            4 => ActorState::Walk(Persist::load(reader)?),
+            5 => ActorState::Damaged(Persist::load(reader)?),
+            tag => return Err(unexpected("actor state", tag)),
+        };
+        Ok(state)
+    }
+}
+
+impl Persist for Weapon {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.damage.save(writer);
+        self.recharge_time.save(writer);
+        self.ammo_count.save(writer);
+        match self.state {
+            WeaponState::Undefined => save_deadline(0, None, writer),
+            WeaponState::Ready(x) => save_deadline(1, Some(x), writer),
+            WeaponState::Recharge(x) => save_deadline(2, Some(x), writer),
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let damage = Persist::load(reader)?;
+        let recharge_time = Persist::load(reader)?;
+        let ammo_count = Persist::load(reader)?;
+        let state = match u8::load(reader)? {
+            0 => WeaponState::Undefined,
+            1 => WeaponState::Ready(Persist::load(reader)?),
+            2 => WeaponState::Recharge(Persist::load(reader)?),
+            tag => return Err(unexpected("weapon state", tag)),
+        };
+        Ok(Weapon {
+            damage,
+            recharge_time,
+            ammo_count,
+            state,
+        })
+    }
+}
+
+impl Persist for NpcPlan {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        let intent: u8 = match self.intent {
+            NpcIntent::Wander => 0,
+            NpcIntent::Investigate => 1,
+            NpcIntent::Reposition => 2,
+            NpcIntent::Hide => 3,
+        };
+        intent.save(writer);
+        self.route.save(writer);
+        self.hold_until.save(writer);
+        self.pause_after_route.save(writer);
+        self.last_position.save(writer);
+        self.progress_frame.save(writer);
+        self.last_seen.save(writer);
+        self.player_visible.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let intent = match u8::load(reader)? {
+            0 => NpcIntent::Wander,
+            1 => NpcIntent::Investigate,
+            2 => NpcIntent::Reposition,
+            3 => NpcIntent::Hide,
+            tag => return Err(unexpected("npc intent", tag)),
+        };
+        Ok(NpcPlan {
+            intent,
+            route: Persist::load(reader)?,
+            hold_until: Persist::load(reader)?,
+            pause_after_route: Persist::load(reader)?,
+            last_position: Persist::load(reader)?,
+            progress_frame: Persist::load(reader)?,
+            last_seen: Persist::load(reader)?,
+            player_visible: Persist::load(reader)?,
+        })
+    }
+}
+
+impl Persist for LevelStart {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.0.save(writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        Persist::load(reader).map(LevelStart)
+    }
+}
+
+impl Persist for Maze {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        self.matrix.save(writer);
+        // a hash set has no order of its own; sorted, the same maze always
+        // saves the same
+        let mut contour = self
+            .contour
+            .iter()
+            .map(|p| (p.row, p.col))
+            .collect::<Vec<_>>();
+        contour.sort_unstable();
+        (contour.len() as u32).save(writer);
+        for (row, col) in contour {
+            row.save(writer);
+            col.save(writer);
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        let matrix = Persist::load(reader)?;
+        let mut contour = std::collections::HashSet::new();
+        for _ in 0..u32::load(reader)? {
+            contour.insert(matrix::Position {
+                row: Persist::load(reader)?,
+                col: Persist::load(reader)?,
+            });
+        }
+        Ok(Maze { matrix, contour })
+    }
+}
+
+/// The seed, the stream and how far along it the generator is: a restored
+/// level draws the same numbers the saved one would have
+impl Persist for LevelRng {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        save_rng(&self.0, writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        load_rng(reader).map(LevelRng)
+    }
+}
+
+impl Persist for LevelSeeds {
+    fn save(&self, writer: &mut SnapshotWriter) {
+        save_rng(&self.0, writer);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> EngineResult<Self> {
+        load_rng(reader).map(LevelSeeds)
+    }
+}
+
+fn save_rng(rng: &ChaCha12Rng, writer: &mut SnapshotWriter) {
+    writer.write_bytes(&rng.get_seed());
+    rng.get_stream().save(writer);
+    writer.write_bytes(&rng.get_word_pos().to_le_bytes());
+}
+
+fn load_rng(reader: &mut SnapshotReader) -> EngineResult<ChaCha12Rng> {
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(reader.read_bytes(32)?);
+    let stream = u64::load(reader)?;
+    let mut word_pos = [0u8; 16];
+    word_pos.copy_from_slice(reader.read_bytes(16)?);
+    let mut rng = ChaCha12Rng::from_seed(seed);
+    rng.set_stream(stream);
+    rng.set_word_pos(u128::from_le_bytes(word_pos));
+    Ok(rng)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use engine::{systems::GameSystem, AssetManager, EntityBundle, Query, Vec2f};
+    use rand::RngExt;
+
+    use super::*;
+    use crate::game_scene::{compose_component_storage, generator::GeneratorSystem};
+
+    #[test]
+    fn restored_level_plays_on_the_same() {
+        let mut storage = compose_component_storage().unwrap();
+        let mut generator = GeneratorSystem::new(42);
+        generator
+            .setup(&mut storage, &AssetManager::default())
+            .unwrap();
+        // the soldiers have drawn from the level RNG for a while
+        for _ in 0..100 {
+            let _: u32 = storage.resource_mut::<LevelRng>().unwrap().0.random();
+        }
+        storage.set_tick(250);
+        let data = storage.snapshot();
+
+        let mut restored = compose_component_storage().unwrap();
+        restored.restore(&data).unwrap();
+        assert_eq!(restored.tick(), 250);
+        assert_eq!(restored.len(), storage.len());
+        assert_eq!(
+            restored.resource::<Maze>().unwrap().matrix,
+            storage.resource::<Maze>().unwrap().matrix
+        );
+        let positions = |storage: &ComponentStorage| {
+            storage
+                .iter::<(&Position, Option<&Sprite>)>()
+                .map(|(_, (position, sprite))| (position.0.x, position.0.y, sprite.is_some()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(&restored), positions(&storage));
+        let query = Query::new().with_component::<PlayerTag>();
+        assert!(restored.fetch_first_entity(&query).is_some());
+        // and the choices to come are the same
+        let next = |storage: &ComponentStorage| {
+            let mut rng = storage.resource_mut::<LevelRng>().unwrap();
+            (0..10).map(|_| rng.0.random()).collect::<Vec<u64>>()
+        };
+        assert_eq!(next(&restored), next(&storage));
+        // and so are the levels after it
+        let seed = |storage: &ComponentStorage| {
+            storage
+                .resource_mut::<LevelSeeds>()
+                .unwrap()
+                .0
+                .random::<u64>()
+        };
+        assert_eq!(seed(&restored), seed(&storage));
+    }
+
+    #[test]
+    fn soldier_comes_back_as_it_was() {
+        let mut storage = compose_component_storage().unwrap();
+        let mut plan = NpcPlan {
+            intent: NpcIntent::Hide,
+            hold_until: 12,
+            last_seen: Some(Vec2f::new(3.5, 4.5)),
+            player_visible: true,
+            ..Default::default()
+        };
+        plan.route.push_back(Vec2f::new(1.5, 1.5));
+        storage.append(
+            EntityBundle::new()
+                .put(NpcTag)
+                .put(plan)
+                .put(ActorState::Walk(77))
+                .put(Sprite::with_animation(NPC_SOLDIER_WALK, 5, usize::MAX))
+                .put(Weapon {
+                    damage: 4,
+                    recharge_time: Duration::from_millis(500),
+                    ammo_count: 9,
+                    state: WeaponState::Recharge(90),
+                }),
+        );
+        let data = storage.snapshot();
+        storage.restore(&data).unwrap();
+
+        let (_, (plan, state, sprite, weapon)) = storage
+            .iter::<(&NpcPlan, &ActorState, &Sprite, &Weapon)>()
+            .next()
+            .unwrap();
+        assert_eq!(plan.intent, NpcIntent::Hide);
+        assert_eq!(plan.route, [Vec2f::new(1.5, 1.5)]);
+        assert_eq!(plan.hold_until, 12);
+        assert_eq!(plan.last_seen, Some(Vec2f::new(3.5, 4.5)));
+        assert!(plan.player_visible);
+        assert!(*state == ActorState::Walk(77));
+        assert!(matches!(
+            sprite.view,
+            SpriteView::Animation {
+                asset_id: NPC_SOLDIER_WALK,
+                frame_start: 5,
+                times: usize::MAX,
+            }
+        ));
+        assert_eq!(weapon.state, WeaponState::Recharge(90));
+        assert_eq!(weapon.ammo_count, 9);
+    }
+}
@@ -1,26 +1,64 @@
+use std::time::Duration;
+
 use engine::{
-    game_scene::SceneParameters,
+    game_scene::{SceneEvent, SceneParameters},
     refresh_cached_entity,
     systems::{GameSystem, GameSystemCommand},
-    AssetManager, ComponentStorage, EngineError, EngineResult, EntityID, Float,
+    time::Time,
+    AssetManager, ComponentStorage, EngineError, EngineResult, EntityID, EventReader, Float,
 };
 use log::{info, warn};
 
 use crate::{
     game_scene::{components::Sprite, subsystems::update_weapon_state},
     resource::{
-        PLAYER_SHOTGUN_IDLE_ANIM, PLAYER_SHOTGUN_SHOT_ANIM, SCENE_MAIN_MENU, SCENE_PARAM_PAUSE,
-        SOUND_PLAYER_ATTACK, SOUND_PLAYER_PAIN, WORLD_GAME_OVER,
+        FILE_SAVED_GAME, PLAYER_SHOTGUN_IDLE_ANIM, PLAYER_SHOTGUN_SHOT_ANIM, SCENE_MAIN_MENU,
+        SCENE_PARAM_PAUSE, SCENE_PARAM_TIME_PLAYED, SCENE_PARAM_WIN, SOUND_PLAYER_ATTACK,
+        SOUND_PLAYER_PAIN, WORLD_GAME_OVER,
     },
 };
 
 use super::{
-    components::{self, ControllerState, Movement, Shot},
-    subsystems::{can_shoot, is_actor_dead, updated_state},
+    components::{self, ControllerState, Movement},
+    events::LevelWon,
+    subsystems::{can_shoot, fire_weapon, is_actor_dead, updated_state},
 };
 
-pub const PLAYER_SHOT_DEADLINE: usize = 3;
-pub const PLAYER_DAMAGE_DAMAGE_RECOVER: usize = 5;
+pub const PLAYER_SHOT_DEADLINE: Duration = Duration::from_millis(50);
+pub const PLAYER_DAMAGE_DAMAGE_RECOVER: Duration = Duration::from_millis(83);
+/// How far the player turns for one count of mouse motion, at a
+/// sensitivity of 1
+const MOUSE_LOOK_RADIANS_PER_COUNT: Float = 0.0025;
+
+/// How mouse motion turns the player
+#[derive(Clone, Copy, Debug)]
+pub struct MouseLook {
+    /// Scales the turn a motion makes: 2 turns twice as far
+    pub sensitivity: Float,
+    /// Turns left for a motion to the right
+    pub invert: bool,
+}
+
+impl Default for MouseLook {
+    fn default() -> Self {
+        Self {
+            sensitivity: 1.0,
+            invert: false,
+        }
+    }
+}
+
+impl MouseLook {
+    /// The turn, in radians, that `x_relative` counts of motion make
+    fn turn(&self, x_relative: i32) -> Float {
+        let turn = x_relative as Float * MOUSE_LOOK_RADIANS_PER_COUNT * self.sensitivity;
+        if self.invert {
+            -turn
+        } else {
+            turn
+        }
+    }
+}
 
 struct InputResult {
     pub movement: Movement,
@@ -46,11 +84,17 @@ pub struct PlayerSystem {
     angle: Float,
     rotation_speed: Float,
     frames: usize,
+    time: Time,
+    mouse_look: MouseLook,
+    won: EventReader<LevelWon>,
 }
 
 impl PlayerSystem {
-    pub fn new() -> Self {
-        Default::default()
+    pub fn new(mouse_look: MouseLook) -> Self {
+        Self {
+            mouse_look,
+            ..Default::default()
+        }
     }
 
     fn update_storage_cache(&mut self, storage: &ComponentStorage) -> EngineResult<()> {
@@ -117,6 +161,8 @@ impl PlayerSystem {
         if controller.rotate_right_pressed {
             rotation = self.rotation_speed * delta_time;
         }
+        // the motion is a distance, already independent of the step length
+        rotation += self.mouse_look.turn(controller.mouse_x_relative);
         let movement = components::Movement {
             x: dx,
             y: dy,
@@ -125,6 +171,15 @@ impl PlayerSystem {
         Ok(movement)
     }
 
+    /// Whole seconds played on this level, pauses left out
+    fn time_played(&self, storage: &ComponentStorage) -> u64 {
+        let start = storage
+            .resource::<components::LevelStart>()
+            .map(|x| x.0)
+            .unwrap_or_default();
+        self.time.since(start).as_secs()
+    }
+
     fn handle_controls(
         &self,
         delta_time: Float,
@@ -136,21 +191,39 @@ impl PlayerSystem {
             return Ok(result);
         };
         if controller.pause_pressed {
+            // the menu's "Continue" restores it in a later session; failing
+            // to write it must not end this one. A lost game isn't worth
+            // coming back to.
+            if is_actor_dead(storage, self.player_id) {
+                discard_saved_game();
+            } else if let Err(err) = storage.save_snapshot(FILE_SAVED_GAME) {
+                warn!("{err}");
+            }
             let mut params = SceneParameters::default();
             params.insert(SCENE_PARAM_PAUSE.to_string(), "".to_string());
-            result.command = GameSystemCommand::SwitchScene {
+            let time_played = self.time_played(storage);
+            params.insert(SCENE_PARAM_TIME_PLAYED.to_string(), time_played.to_string());
+            // the menu goes over the level, which stays on screen
+            result.command = GameSystemCommand::PushScene {
                 id: SCENE_MAIN_MENU,
                 params,
             };
         }
-        result.is_shooting = controller.shot_pressed;
+        result.is_shooting = controller.shot_key_pressed || controller.shot_button_pressed;
         result.movement = self.update_movement(delta_time, &controller)?;
+        drop(controller);
+        // the motion is turned once, whichever step it came in before
+        if let Some(mut controller) = storage.get_mut::<components::ControllerState>(self.player_id)
+        {
+            controller.mouse_x_relative = 0;
+            controller.mouse_y_relative = 0;
+        }
         Ok(result)
     }
 
-    fn update_weapon(&self, storage: &mut ComponentStorage) -> EngineResult<()> {
+    fn update_weapon(&self, storage: &mut ComponentStorage, fired: bool) -> EngineResult<()> {
         use components::WeaponState::*;
-        let Some(state) = update_weapon_state(self.frames, storage, self.player_id) else {
+        let Some(state) = update_weapon_state(&self.time, storage, self.player_id, fired) else {
             return Ok(());
         };
         let sprite = match state {
@@ -170,27 +243,24 @@ impl PlayerSystem {
         Ok(())
     }
 
-    fn handle_shot(&self, storage: &mut ComponentStorage) -> EngineResult<()> {
+    /// Returns whether the weapon went off
+    fn handle_shot(&self, storage: &mut ComponentStorage) -> EngineResult<bool> {
         if !can_shoot(storage, self.player_id) {
-            return Ok(());
+            return Ok(false);
         }
         let Some(position) = storage
             .get::<components::Position>(self.player_id)
             .map(|x| x.0)
         else {
-            return Ok(());
-        };
-        let shot = Shot {
-            angle: self.angle,
-            position,
-            deadline: self.frames + PLAYER_SHOT_DEADLINE,
+            return Ok(false);
         };
-        storage.set(self.player_id, Some(shot))?;
+        let deadline = self.time.after(PLAYER_SHOT_DEADLINE);
+        fire_weapon(storage, self.player_id, position, self.angle, deadline)?;
 
         let sound_fx = components::SoundFx::once(SOUND_PLAYER_ATTACK);
         storage.set(self.player_id, Some(sound_fx))?;
 
-        Ok(())
+        Ok(true)
     }
 }
 
@@ -215,9 +285,10 @@ impl GameSystem for PlayerSystem {
         self.update_storage_cache(storage)?;
         self.prefetch(storage)?;
         self.frames = frames;
+        self.time = Time::of(storage);
 
         if let Some(new_state) = updated_state(
-            self.frames,
+            &self.time,
             storage,
             self.player_id,
             PLAYER_DAMAGE_DAMAGE_RECOVER,
@@ -225,6 +296,7 @@ impl GameSystem for PlayerSystem {
             storage.set(self.player_id, Some(new_state))?;
             match new_state {
                 components::ActorState::Dead(_) => {
+                    discard_saved_game();
                     storage.set::<components::BoundingBox>(self.player_id, None)?;
                     let sprite = Sprite::with_texture(WORLD_GAME_OVER);
                     storage.set(self.player_id, Some(sprite))?;
@@ -239,14 +311,69 @@ impl GameSystem for PlayerSystem {
             }
         }
 
+        if !storage.read(&mut self.won).is_empty() {
+            discard_saved_game();
+            let mut params = SceneParameters::default();
+            params.insert(SCENE_PARAM_WIN.to_string(), "".to_string());
+            let time_played = self.time_played(storage);
+            params.insert(SCENE_PARAM_TIME_PLAYED.to_string(), time_played.to_string());
+            return Ok(GameSystemCommand::SwitchScene {
+                id: SCENE_MAIN_MENU,
+                params,
+            });
+        }
+
         let input = self.handle_controls(delta_time, storage)?;
         if !is_actor_dead(storage, self.player_id) {
             storage.set(self.player_id, Some(input.movement))?;
-            if input.is_shooting {
-                self.handle_shot(storage)?;
-            }
-            self.update_weapon(storage)?;
+            let fired = input.is_shooting && self.handle_shot(storage)?;
+            self.update_weapon(storage, fired)?;
         }
         Ok(input.command)
     }
+
+    fn on_scene_event(
+        &mut self,
+        storage: &mut ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        // the level won may have been sent again before the scene was left
+        if event == SceneEvent::Enter {
+            storage.read(&mut self.won);
+        }
+        Ok(())
+    }
+}
+
+/// Removes the game saved on pause once its level is over, so "Continue"
+/// can't bring back a game already won or lost
+fn discard_saved_game() {
+    match std::fs::remove_file(FILE_SAVED_GAME) {
+        Ok(()) => info!("removed {FILE_SAVED_GAME}"),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => warn!("failed to remove {FILE_SAVED_GAME}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mouse_look_scales_and_inverts_the_turn() {
+        let normal = MouseLook::default();
+        assert_eq!(normal.turn(0), 0.0);
+        assert!(normal.turn(10) > 0.0);
+        let fast = MouseLook {
+            sensitivity: 2.0,
+            invert: false,
+        };
+        assert!((fast.turn(10) - 2.0 * normal.turn(10)).abs() < 1e-6);
+        let inverted = MouseLook {
+            sensitivity: 1.0,
+            invert: true,
+        };
+        assert_eq!(inverted.turn(-10), normal.turn(10));
+    }
 }
@@ -0,0 +1,149 @@
+//! What the generator spawns, as prefabs designers tune in the asset
+//! registry. Position, state and everything else a level decides are put on
+//! top in code.
+
+use std::{str::FromStr, time::Duration};
+
+use engine::{
+    assets::Prefab, AssetManager, ComponentStorage, EngineError, EngineResult, SizeFloat,
+};
+use log::warn;
+
+use crate::resource::*;
+
+use super::components::*;
+
+// used for the prefabs missing from the registry, so an older bundle still
+// plays
+const DEFAULT_PLAYER: &str =
+    "player health=500 velocity=7.5 rotation_speed=2.5 bounding_box=0.7,0.7 weapon=27,750";
+const DEFAULT_NPC_SOLDIER: &str =
+    "npc health=100 max_health=100 velocity=4.3 scale_ratio=0.7 height_shift=0.27 bounding_box=0.7,0.7 weapon=4,500";
+const DEFAULT_TORCH: &str = "scale_ratio=0.7 height_shift=0.27";
+
+/// The prefabs the generator spawns
+pub struct LevelPrefabs {
+    pub player: Prefab,
+    pub npc_soldier: Prefab,
+    pub torch: Prefab,
+}
+
+impl LevelPrefabs {
+    pub fn new(asset_manager: &AssetManager) -> EngineResult<Self> {
+        Ok(Self {
+            player: prefab(asset_manager, PREFAB_PLAYER, DEFAULT_PLAYER)?,
+            npc_soldier: prefab(asset_manager, PREFAB_NPC_SOLDIER, DEFAULT_NPC_SOLDIER)?,
+            torch: prefab(asset_manager, PREFAB_TORCH, DEFAULT_TORCH)?,
+        })
+    }
+}
+
+impl Default for LevelPrefabs {
+    fn default() -> Self {
+        Self::new(&AssetManager::default()).expect("built-in prefabs parse")
+    }
+}
+
+pub fn register_prefab_components(storage: &mut ComponentStorage) -> EngineResult<()> {
+    storage.register_prefab_component("player", |x| marker(x).map(|_| PlayerTag))?;
+    storage.register_prefab_component("npc", |x| marker(x).map(|_| NpcTag))?;
+    storage.register_prefab_component("health", |x| number(x).map(Health))?;
+    storage.register_prefab_component("max_health", |x| number(x).map(MaxHealth))?;
+    storage.register_prefab_component("velocity", |x| number(x).map(Velocity))?;
+    storage.register_prefab_component("rotation_speed", |x| number(x).map(RotationSpeed))?;
+    storage.register_prefab_component("scale_ratio", |x| number(x).map(ScaleRatio))?;
+    storage.register_prefab_component("height_shift", |x| number(x).map(HeightShift))?;
+    storage.register_prefab_component("bounding_box", bounding_box)?;
+    storage.register_prefab_component("weapon", weapon)?;
+    Ok(())
+}
+
+fn prefab(asset_manager: &AssetManager, id: &str, default: &str) -> EngineResult<Prefab> {
+    if let Some(prefab) = asset_manager.prefab(id) {
+        return Ok(prefab.clone());
+    }
+    warn!("prefab '{id}' not found, using the built-in one");
+    Prefab::parse(id, default)
+}
+
+fn marker(text: &str) -> EngineResult<()> {
+    if !text.is_empty() {
+        return Err(bad_value(text));
+    }
+    Ok(())
+}
+
+fn number<T: FromStr>(text: &str) -> EngineResult<T> {
+    text.parse().map_err(|_| bad_value(text))
+}
+
+/// `width,height`
+fn bounding_box(text: &str) -> EngineResult<BoundingBox> {
+    let Some((width, height)) = text.split_once(',') else {
+        return Err(bad_value(text));
+    };
+    Ok(BoundingBox(SizeFloat::new(number(width)?, number(height)?)))
+}
+
+/// `damage,recharge_ms` with endless ammo, or `damage,recharge_ms,ammo`
+fn weapon(text: &str) -> EngineResult<Weapon> {
+    let values = text.split(',').collect::<Vec<_>>();
+    let (damage, recharge_ms, ammo_count) = match values[..] {
+        [damage, recharge_ms] => (damage, recharge_ms, None),
+        [damage, recharge_ms, ammo_count] => (damage, recharge_ms, Some(ammo_count)),
+        _ => return Err(bad_value(text)),
+    };
+    Ok(Weapon {
+        damage: number(damage)?,
+        recharge_time: Duration::from_millis(number(recharge_ms)?),
+        state: WeaponState::Undefined,
+        ammo_count: ammo_count.map_or(Ok(usize::MAX), number)?,
+    })
+}
+
+fn bad_value(text: &str) -> EngineError {
+    EngineError::ResourceParseError(format!("[v2.prefabs] bad value '{text}'"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game_scene::compose_component_storage;
+
+    #[test]
+    fn built_in_prefabs_make_entities() {
+        let mut storage = compose_component_storage().unwrap();
+        let prefabs = LevelPrefabs::default();
+        let player = storage.append(storage.prefab_bundle(&prefabs.player).unwrap());
+        assert!(storage.has_component::<PlayerTag>(player));
+        assert_eq!(storage.get::<Health>(player).unwrap().0, 500);
+        {
+            let weapon = storage.get::<Weapon>(player).unwrap();
+            assert_eq!((weapon.damage, weapon.recharge_time.as_millis()), (27, 750));
+            assert_eq!(weapon.ammo_count, usize::MAX);
+        }
+
+        let soldier = storage.append(storage.prefab_bundle(&prefabs.npc_soldier).unwrap());
+        assert!(storage.has_component::<NpcTag>(soldier));
+        assert_eq!(storage.get::<HeightShift>(soldier).unwrap().0, 0.27);
+        assert_eq!(storage.get::<MaxHealth>(soldier).unwrap().0, 100);
+    }
+
+    #[test]
+    fn bad_values_are_refused() {
+        let mut storage = compose_component_storage().unwrap();
+        for text in [
+            "health=-1",
+            "weapon=4",
+            "bounding_box=0.7",
+            "npc=yes",
+            "armor=3",
+        ] {
+            let prefab = Prefab::parse("test", text).unwrap();
+            assert!(storage.prefab_bundle(&prefab).is_err(), "{text}");
+        }
+        let prefab = Prefab::parse("test", "weapon=4,500,12").unwrap();
+        let id = storage.append(storage.prefab_bundle(&prefab).unwrap());
+        assert_eq!(storage.get::<Weapon>(id).unwrap().ammo_count, 12);
+    }
+}
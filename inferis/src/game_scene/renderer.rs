@@ -8,9 +8,11 @@ use std::{
 
 use engine::{
     assets::{TextureId, TextureInfo},
+    game_scene::{SceneEvent, SceneParameters},
     prelude::{BlendMode, Color, Point, Rect},
     ray_cast_dir, refresh_cached_entity,
     systems::{GameRendererSystem, RendererEffect, RendererLayers, RendererLayersPtr},
+    time::StepRate,
     AssetManager, ComponentStorage, EngineError, EngineResult, EntityID, Float, Query, SizeU32,
     Vec2f, RAY_CASTER_TOL,
 };
@@ -93,14 +95,14 @@ pub struct RendererSystem {
     angle: Float,
     player_pos: Vec2f,
     frames: usize,
+    step_rate: StepRate,
     // long term cached values
     player_id: EntityID,
-    maze_id: EntityID,
     window_size: SizeU32,
     rays_count: u32,
     ray_angle_step: Float,
     /// (sin, cos) of each ray's angular offset from the view direction.
-    /// Fixed for the lifetime of the window, which is what lets a frame
+    /// Fixed until the window changes size, which is what lets a frame
     /// derive every ray's direction from the player's without per-ray
     /// transcendentals -- and the cosine doubles as the fishbowl correction.
     ray_offsets: Vec<(Float, Float)>,
@@ -142,8 +144,8 @@ impl Default for RendererSystem {
             angle: Default::default(),
             player_pos: Default::default(),
             frames: Default::default(),
+            step_rate: Default::default(),
             player_id: Default::default(),
-            maze_id: Default::default(),
             window_size: Default::default(),
             rays_count: Default::default(),
             ray_angle_step: Default::default(),
@@ -170,8 +172,20 @@ impl RendererSystem {
             storage,
             &mut self.player_id,
             "[v2.renderer] player",
-        )?;
-        refresh_cached_entity::<components::Maze>(storage, &mut self.maze_id, "[v2.renderer] maze")
+        )
+    }
+
+    /// Fits the ray fan and the HUD to a window of `window_size` pixels
+    fn layout(&mut self, window_size: SizeU32) {
+        self.window_size = window_size;
+        // a window shrunk to nothing still casts a ray
+        self.rays_count = (window_size.width >> 1).max(1);
+        self.ray_angle_step = FIELD_OF_VIEW / self.rays_count as Float;
+        self.scale = window_size.width as Float / self.rays_count as Float;
+        self.ray_offsets = (0..self.rays_count)
+            .map(|ray| (ray as Float * self.ray_angle_step - HALF_FIELD_OF_VIEW).sin_cos())
+            .collect();
+        self.screen_distance = (window_size.width >> 1) as Float / HALF_FIELD_OF_VIEW.tan();
     }
 
     // ------------------------------------------------------------------------------------------------------------
@@ -180,38 +194,36 @@ impl RendererSystem {
         storage: &ComponentStorage,
         asset_manager: &AssetManager,
     ) -> EngineResult<()> {
-        let query = Query::new().with_component::<components::Sprite>();
-        let entities = storage.fetch_entities(&query);
-        for entity_id in entities {
-            if entity_id == self.player_id {
-                self.render_hud_weapon(storage, asset_manager)?;
-            } else {
-                self.render_sprite(storage, asset_manager, entity_id)?;
-            }
+        // the player's sprite is the weapon in hand, drawn over the view
+        let world_sprites = Query::new().without::<components::PlayerTag>();
+        let sprites = storage.iter_filtered::<(
+            &components::Sprite,
+            &components::Position,
+            Option<&components::ScaleRatio>,
+            Option<&components::HeightShift>,
+        )>(&world_sprites);
+        for (_, (sprite, position, scale, height_shift)) in sprites {
+            let scale = scale.map(|x| x.0).unwrap_or(1.0);
+            let height_shift = height_shift.map(|x| x.0).unwrap_or(1.0);
+            self.render_sprite(asset_manager, &sprite, position.0, scale, height_shift)?;
+        }
+        if let Some(sprite) = storage.get::<components::Sprite>(self.player_id) {
+            self.render_hud_weapon(asset_manager, &sprite)?;
         }
         Ok(())
     }
 
     fn render_sprite(
         &self,
-        storage: &ComponentStorage,
         asset_manager: &AssetManager,
-        entity_id: EntityID,
+        sprite: &components::Sprite,
+        sprite_pos: Vec2f,
+        sprite_scale: Float,
+        sprite_height_shift: Float,
     ) -> EngineResult<()> {
-        let Some(data) = self.sprite_view_data(storage, asset_manager, entity_id) else {
+        let Some(data) = self.sprite_view_data(asset_manager, sprite) else {
             return Ok(());
         };
-        let Some(sprite_pos) = storage.get::<components::Position>(entity_id).map(|x| x.0) else {
-            return Ok(());
-        };
-        let sprite_scale = storage
-            .get::<components::ScaleRatio>(entity_id)
-            .map(|x| x.0)
-            .unwrap_or(1.0);
-        let sprite_height_shift = storage
-            .get::<components::HeightShift>(entity_id)
-            .map(|x| x.0)
-            .unwrap_or(1.0);
         let vector = sprite_pos - self.player_pos;
         let delta = {
             let Vec2f { x: dx, y: dy } = vector;
@@ -260,11 +272,10 @@ impl RendererSystem {
 
     fn render_hud_weapon(
         &self,
-        storage: &ComponentStorage,
         asset_manager: &AssetManager,
+        sprite: &components::Sprite,
     ) -> EngineResult<()> {
-        let Some(texture_data) = self.sprite_view_data(storage, asset_manager, self.player_id)
-        else {
+        let Some(texture_data) = self.sprite_view_data(asset_manager, sprite) else {
             return Ok(());
         };
         let SizeU32 { width, height } = texture_data.size;
@@ -419,11 +430,9 @@ impl RendererSystem {
     // ------------------------------------------------------------------------------------------------------------
     fn sprite_view_data(
         &self,
-        storage: &ComponentStorage,
         asset_manager: &AssetManager,
-        entity_id: EntityID,
+        sprite: &components::Sprite,
     ) -> Option<SpriteViewData> {
-        let sprite = storage.get::<components::Sprite>(entity_id)?;
         match sprite.view {
             components::SpriteView::Texture { asset_id } => {
                 let info = *self.textures.get(asset_id)?;
@@ -449,7 +458,7 @@ impl RendererSystem {
                     height: size.height,
                 };
                 let elapsed = self.frames - frame_start;
-                let frame_duration = params.frame_duration as usize;
+                let frame_duration = self.step_rate.steps(params.frame_time());
                 let duration = frame_duration * params.frames_count;
                 let index = if elapsed / duration < times {
                     (elapsed / frame_duration) % params.frames_count
@@ -473,7 +482,7 @@ impl RendererSystem {
     }
     // ------------------------------------------------------------------------------------------------------------
     fn render_walls(&self, storage: &ComponentStorage) -> EngineResult<()> {
-        let Some(component_maze) = storage.get::<components::Maze>(self.maze_id) else {
+        let Some(component_maze) = storage.resource::<components::Maze>() else {
             return Ok(());
         };
         // dims
@@ -624,30 +633,23 @@ impl RendererSystem {
     }
 
     fn render_hud_minimap_objects(&self, storage: &ComponentStorage) -> EngineResult<()> {
-        let query = Query::new().with_component::<components::Position>();
-        let entities = storage.fetch_entities(&query);
-        for entity_id in entities {
-            self.render_hud_minimap_object(storage, entity_id)?;
+        let npcs = Query::new().with_component::<components::NpcTag>();
+        let objects =
+            storage.iter_filtered::<(&components::Position, Option<&components::Angle>)>(&npcs);
+        for (_, (position, angle)) in objects {
+            self.render_hud_minimap_object(position.0, angle.map(|x| x.0), Color::YELLOW);
+        }
+        // the player last, so it stays on top
+        if let Some(position) = storage.get::<components::Position>(self.player_id) {
+            let angle = storage
+                .get::<components::Angle>(self.player_id)
+                .map(|x| x.0);
+            self.render_hud_minimap_object(position.0, angle, Color::RED);
         }
         Ok(())
     }
 
-    fn render_hud_minimap_object(
-        &self,
-        storage: &ComponentStorage,
-        entity_id: EntityID,
-    ) -> EngineResult<()> {
-        let Some(pos) = storage.get::<components::Position>(entity_id).map(|x| x.0) else {
-            return Ok(());
-        };
-        let color = if entity_id == self.player_id {
-            Color::RED
-        } else if storage.has_component::<components::NpcTag>(entity_id) {
-            Color::YELLOW
-        } else {
-            // Color::GREEN
-            return Ok(());
-        };
+    fn render_hud_minimap_object(&self, pos: Vec2f, angle: Option<Float>, color: Color) {
         let (x, y) = (
             (pos.x * MAP_SCALE as Float) as i32,
             (pos.y * MAP_SCALE as Float) as i32,
@@ -664,8 +666,8 @@ impl RendererSystem {
         };
         layers.push_hud(effect);
 
-        let Some(angle) = storage.get::<components::Angle>(entity_id).map(|x| x.0) else {
-            return Ok(());
+        let Some(angle) = angle else {
+            return;
         };
         let length = 2.5 * MAP_SCALE as Float;
         let effect = RendererEffect::Line {
@@ -677,11 +679,10 @@ impl RendererSystem {
             ),
         };
         layers.push_hud(effect);
-        Ok(())
     }
 
     fn render_hud_maze(&self, storage: &ComponentStorage) -> EngineResult<()> {
-        let Some(maze_comp) = storage.get::<components::Maze>(self.maze_id) else {
+        let Some(maze_comp) = storage.resource::<components::Maze>() else {
             return Ok(());
         };
 
@@ -718,24 +719,28 @@ impl GameRendererSystem for RendererSystem {
         window_size: SizeU32,
     ) -> EngineResult<()> {
         self.update_storage_cache(storage)?;
+        self.step_rate = StepRate::of(storage);
         asset_manager.cache_textures_info(&mut self.textures)?;
-        // precalculated values
-        self.window_size = window_size;
-        self.rays_count = window_size.width >> 1;
-        self.ray_angle_step = FIELD_OF_VIEW / self.rays_count as Float;
-        self.scale = window_size.width as Float / self.rays_count as Float;
         self.wall_textures = components::WALL_TEXTURES
             .iter()
             .map(|name| self.textures.get(*name).copied())
             .collect();
-        self.ray_offsets = (0..self.rays_count)
-            .map(|ray| (ray as Float * self.ray_angle_step - HALF_FIELD_OF_VIEW).sin_cos())
-            .collect();
-        self.screen_distance = (window_size.width >> 1) as Float / HALF_FIELD_OF_VIEW.tan();
+        self.layout(window_size);
         info!("setup ok");
         Ok(())
     }
 
+    fn resize(
+        &mut self,
+        _storage: &ComponentStorage,
+        _asset_manager: &AssetManager,
+        window_size: SizeU32,
+    ) -> EngineResult<()> {
+        // the textures stay the same, only the ray fan follows the window
+        self.layout(window_size);
+        Ok(())
+    }
+
     fn render(
         &mut self,
         frames: usize,
@@ -768,6 +773,23 @@ impl GameRendererSystem for RendererSystem {
         self.render_hud_minimap(storage)?;
         Ok(self.layers.clone())
     }
+
+    fn on_scene_event(
+        &mut self,
+        _storage: &ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> EngineResult<()> {
+        // the player may be somewhere else entirely, which is no step to sway
+        // the weapon for
+        if matches!(event, SceneEvent::Enter | SceneEvent::Resume) {
+            self.weapon_sway_amount = 0.0;
+            self.weapon_sway_last_pos = None;
+            self.weapon_turn_lag = 0.0;
+            self.weapon_turn_last_angle = None;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -912,4 +934,27 @@ mod test {
         turn(&mut renderer, TURN_STEP, 1);
         assert!(renderer.weapon_turn_lag < 0.0);
     }
+
+    #[test]
+    fn the_ray_fan_follows_the_window() {
+        let mut renderer = RendererSystem::new();
+        renderer.layout(SizeU32 {
+            width: 1600,
+            height: 900,
+        });
+        assert_eq!(renderer.ray_offsets.len(), 800);
+        let distance = renderer.screen_distance;
+        renderer.layout(SizeU32 {
+            width: 800,
+            height: 450,
+        });
+        assert_eq!(renderer.rays_count, 400);
+        assert_eq!(renderer.ray_offsets.len(), 400);
+        assert!((renderer.screen_distance * 2.0 - distance).abs() < 1e-3);
+        // the fan still spans the field of view
+        let (last_sin, _) = renderer.ray_offsets[399];
+        let (first_sin, _) = renderer.ray_offsets[0];
+        assert!((first_sin + HALF_FIELD_OF_VIEW.sin()).abs() < 1e-3);
+        assert!(last_sin > 0.0);
+    }
 }
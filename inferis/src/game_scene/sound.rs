@@ -1,15 +1,22 @@
-use engine::{cleanup_component, systems::GameSoundSystem, Query};
+use engine::{
+    game_scene::{SceneEvent, SceneParameters},
+    systems::{GameSoundSystem, SoundEffect},
+    Query,
+};
 use log::{debug, info};
 
 use super::components;
 
+/// Plays the `SoundFx` that were set since it last ran. The component stays
+/// on the entity; setting it again, even to the same sound, plays it again.
 pub struct SoundSystem {
-    //
+    /// The first step whose sounds haven't been played yet
+    next_tick: usize,
 }
 
 impl SoundSystem {
     pub fn new() -> Self {
-        Self {}
+        Self { next_tick: 0 }
     }
 }
 
@@ -27,21 +34,34 @@ impl GameSoundSystem for SoundSystem {
         &mut self,
         storage: &mut engine::ComponentStorage,
         _asset_manager: &engine::AssetManager,
-    ) -> engine::EngineResult<Vec<engine::systems::SoundEffect>> {
+    ) -> engine::EngineResult<Vec<SoundEffect>> {
         let mut effects = Vec::with_capacity(20);
-        let query = Query::new().with_component::<components::SoundFx>();
-        let entities = storage.fetch_entities(&query);
-        for entity_id in entities {
-            let Some(sound) = storage.get::<components::SoundFx>(entity_id) else {
-                continue;
-            };
-            effects.push(engine::systems::SoundEffect::PlaySound {
+        // a restored game may have gone back in steps
+        let since = self.next_tick.min(storage.tick());
+        let query = Query::new().changed_since::<components::SoundFx>(since);
+        for (_, (sound,)) in storage.iter_filtered::<(&components::SoundFx,)>(&query) {
+            effects.push(SoundEffect::PlaySound {
                 asset_id: sound.asset_id.clone(),
                 loops: sound.loops,
             });
             debug!("queued sound {}", sound.asset_id);
         }
-        cleanup_component::<components::SoundFx>(storage)?;
+        // steps run before the sounds are collected, so every change stamped
+        // with the current tick is yet to come
+        self.next_tick = storage.tick();
         Ok(effects)
     }
+
+    fn on_scene_event(
+        &mut self,
+        _storage: &mut engine::ComponentStorage,
+        event: SceneEvent,
+        _params: &SceneParameters,
+    ) -> engine::EngineResult<Vec<SoundEffect>> {
+        // looped sounds would carry on under the menu
+        match event {
+            SceneEvent::Suspend | SceneEvent::Exit => Ok(vec![SoundEffect::StopAll]),
+            _ => Ok(Vec::new()),
+        }
+    }
 }
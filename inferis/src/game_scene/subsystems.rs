@@ -1,26 +1,28 @@
-use engine::{ray_cast, ComponentStorage, EngineResult, EntityID, Float, Query, Rectangle, Vec2f};
+use std::time::Duration;
 
-use crate::game_scene::components;
+use engine::{
+    ray_cast, time::Time, ComponentStorage, EngineResult, EntityID, Float, Rectangle, Vec2f,
+};
+
+use crate::game_scene::{components, events::ShotFired};
 
 use super::components::{ActorState, BoundingBox};
 
-/// Updates weapon state to new one if it reached frame deadline
+/// Updates weapon state to new one if it reached its deadline, or to
+/// recharging if it was `fired` this step
 /// if state doesn't changed functions returns None
 pub fn update_weapon_state(
-    frame: usize,
+    time: &Time,
     storage: &mut ComponentStorage,
     entity_id: EntityID,
+    fired: bool,
 ) -> Option<components::WeaponState> {
-    let has_shot = storage
-        .get::<components::Shot>(entity_id)
-        .map(|x| x.deadline > frame)
-        .unwrap_or(false);
     let mut weapon = storage.get_mut::<components::Weapon>(entity_id)?;
     use components::WeaponState::*;
     let new_state = match weapon.state {
         Undefined => Ready(usize::MAX),
-        Recharge(deadline) if deadline <= frame => Ready(usize::MAX),
-        Ready(_) if has_shot => Recharge(frame + weapon.recharge_time),
+        Recharge(deadline) if time.is_due(deadline) => Ready(usize::MAX),
+        Ready(_) if fired => Recharge(time.after(weapon.recharge_time)),
         state => state,
     };
     if new_state != weapon.state {
@@ -35,14 +37,14 @@ pub fn update_weapon_state(
 }
 
 pub fn updated_state(
-    frame: usize,
+    time: &Time,
     storage: &mut engine::ComponentStorage,
     entity_id: EntityID,
-    damage_duration: usize,
+    damage_duration: Duration,
 ) -> EngineResult<Option<components::ActorState>> {
-    let mut state = state_if_damaged(storage, entity_id, frame + damage_duration)?;
+    let mut state = state_if_damaged(storage, entity_id, time.after(damage_duration))?;
     if state.is_none() {
-        state = updated_state_if_time(frame, storage, entity_id)?;
+        state = updated_state_if_time(time, storage, entity_id)?;
     }
     Ok(state)
 }
@@ -75,7 +77,7 @@ fn state_if_damaged(
 }
 
 fn updated_state_if_time(
-    frame: usize,
+    time: &Time,
     storage: &mut engine::ComponentStorage,
     entity_id: EntityID,
 ) -> EngineResult<Option<components::ActorState>> {
@@ -87,12 +89,33 @@ fn updated_state_if_time(
     use components::ActorState::*;
     let result = match state {
         Undefined => Some(Idle(usize::MAX)),
-        Damaged(deadline) if deadline <= frame => Some(Idle(usize::MAX)),
+        Damaged(deadline) if time.is_due(deadline) => Some(Idle(usize::MAX)),
         _ => None,
     };
     Ok(result)
 }
 
+/// Sends the shot of the entity's weapon; the caller puts the weapon to
+/// recharge with [`update_weapon_state`]
+pub fn fire_weapon(
+    storage: &mut ComponentStorage,
+    shooter: EntityID,
+    position: Vec2f,
+    angle: Float,
+    deadline: usize,
+) -> EngineResult<()> {
+    let Some(damage) = storage.get::<components::Weapon>(shooter).map(|x| x.damage) else {
+        return Ok(());
+    };
+    storage.send(ShotFired {
+        shooter,
+        position,
+        angle,
+        damage,
+        deadline,
+    })
+}
+
 /// Checks if weapon is ready for shooting
 /// returns false if the Weapon component is missing
 pub fn can_shoot(storage: &ComponentStorage, entity_id: EntityID) -> bool {
@@ -102,25 +125,28 @@ pub fn can_shoot(storage: &ComponentStorage, entity_id: EntityID) -> bool {
     matches!(weapon.state, components::WeaponState::Ready(_)) && weapon.ammo_count > 0
 }
 
+/// What a ray cast from an entity runs into first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RayHit {
+    Wall,
+    Entity(EntityID),
+}
+
 pub fn ray_cast_from_entity(
     entity_id: EntityID,
     storage: &ComponentStorage,
-    maze_id: EntityID,
     position: Vec2f,
     angle: Float,
-) -> EngineResult<Option<EntityID>> {
-    let query = Query::new().with_component::<components::BoundingBox>();
-    let entities = storage.fetch_entities(&query);
-
-    // borrow the maze once: fetching the component inside the closure
+) -> EngineResult<Option<RayHit>> {
+    // borrow the maze once: fetching the resource inside the closure
     // costs a map lookup, RefCell borrow and downcast on every ray step
-    let maze = storage.get::<components::Maze>(maze_id);
+    let maze = storage.resource::<components::Maze>();
     let check_wall = |point: Vec2f| {
         if point.x < 0.0 || point.y < 0.0 {
             return None;
         }
         match &maze {
-            Some(maze) if maze.is_wall(point) => Some(maze_id),
+            Some(maze) if maze.is_wall(point) => Some(RayHit::Wall),
             _ => None,
         }
     };
@@ -138,40 +164,27 @@ pub fn ray_cast_from_entity(
     };
     let mut closest_entity = None;
 
-    if !entities.is_empty() {
-        let ray_dir = Vec2f::new(angle.cos(), angle.sin());
-        for target_id in entities {
-            // Only the shooter is excluded, so a soldier's shot can hit
-            // another soldier standing in the way. That is deliberate: now
-            // that soldiers patrol and reposition they cross each other's
-            // line of fire often, and letting them thin each other out
-            // rewards the player for choosing where to fight from.
-            if target_id == entity_id {
-                continue;
-            }
-            let Some(pos) = storage.get::<components::Position>(target_id).map(|x| x.0) else {
-                continue;
-            };
-            let Some(target_size) = storage.get::<BoundingBox>(target_id).map(|x| x.0) else {
-                continue;
-            };
-            let rect = Rectangle::with_pole(pos, target_size);
-            if let Some(t) = rect.ray_intersect(position, ray_dir) {
-                if t >= 0.0 && t < min_dist {
-                    min_dist = t;
-                    closest_entity = Some(target_id);
-                }
+    let ray_dir = Vec2f::new(angle.cos(), angle.sin());
+    let targets = storage.iter::<(&BoundingBox, &components::Position)>();
+    for (target_id, (target_size, pos)) in targets {
+        // Only the shooter is excluded, so a soldier's shot can hit
+        // another soldier standing in the way. That is deliberate: now
+        // that soldiers patrol and reposition they cross each other's
+        // line of fire often, and letting them thin each other out
+        // rewards the player for choosing where to fight from.
+        if target_id == entity_id {
+            continue;
+        }
+        let rect = Rectangle::with_pole(pos.0, target_size.0);
+        if let Some(t) = rect.ray_intersect(position, ray_dir) {
+            if t >= 0.0 && t < min_dist {
+                min_dist = t;
+                closest_entity = Some(target_id);
             }
         }
     }
 
-    if let Some(target) = closest_entity {
-        Ok(Some(target))
-    } else if ray_result.value.is_some() {
-        Ok(Some(maze_id))
-    } else {
-        Ok(None)
-    }
+    Ok(closest_entity.map(RayHit::Entity).or(ray_result.value))
 }
 
 pub fn get_actor_state(storage: &ComponentStorage, entity_id: EntityID) -> components::ActorState {
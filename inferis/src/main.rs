@@ -1,12 +1,20 @@
-use std::path::Path;
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use env_logger::Env;
-use log::error;
+use log::{error, info};
 
 use engine::{
-    assets::AssetSource, world::GameWorld, AudioSettings, EngineError, EngineResult,
-    EngineSettings, SizeU32, WindowSettings,
+    assets::AssetSource,
+    replay::{InputRecorder, InputReplay},
+    window::{Scaling, WindowMode},
+    world::GameWorld,
+    AudioSettings, EngineError, EngineResult, EngineSettings, Float, SizeU32, TimingSettings,
+    WindowSettings,
 };
+use game_scene::MouseLook;
 use resource::{FILE_ASSET_BUNDLE, FILE_ASSET_REGISTRY};
 mod game_scene;
 mod menu_scene;
@@ -14,6 +22,26 @@ mod resource;
 
 const WINDOW_TITLE: &str = "INFERIS";
 
+/// Writes the session's input to the given file
+const ENV_RECORD: &str = "INFERIS_RECORD";
+/// Plays the input recorded in the given file back
+const ENV_REPLAY: &str = "INFERIS_REPLAY";
+/// Fixes the seed of a session that is not a replay
+const ENV_SEED: &str = "INFERIS_SEED";
+/// Makes "New game" always generate the level with the given seed
+const ENV_LEVEL_SEED: &str = "INFERIS_LEVEL_SEED";
+/// Draws at the given resolution, `<width>x<height>`, scaled up to the window
+const ENV_RESOLUTION: &str = "INFERIS_RESOLUTION";
+/// Scales how far mouse motion turns the player, 1 by default
+const ENV_MOUSE_SENSITIVITY: &str = "INFERIS_MOUSE_SENSITIVITY";
+/// Set to anything, turns the player the other way for the same mouse motion
+const ENV_MOUSE_INVERT: &str = "INFERIS_MOUSE_INVERT";
+
+// the mouse settings a recording keeps, so its replay turns the player the
+// same way whatever the environment says
+const PARAM_MOUSE_SENSITIVITY: &str = "mouse_sensitivity";
+const PARAM_MOUSE_INVERT: &str = "mouse_invert";
+
 fn main() {
     // RUST_LOG overrides it: `RUST_LOG=warn`, `RUST_LOG=inferis::game_scene=debug`
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
@@ -26,12 +54,45 @@ fn main() {
 
 fn run() -> EngineResult<()> {
     let settings = engine_settings()?;
-    let menu_scene = menu_scene::compose_scene()?;
-    let game_scene = game_scene::compose_scene()?;
-    GameWorld::new()
+    let replay = std::env::var(ENV_REPLAY)
+        .ok()
+        .map(|path| InputReplay::open(&path))
+        .transpose()?;
+    let seed = match &replay {
+        Some(replay) => replay.seed(),
+        None => session_seed()?,
+    };
+    info!("seed {seed}");
+    let level_seed = env_seed(ENV_LEVEL_SEED)?;
+    let menu_scene = menu_scene::compose_scene(level_seed)?;
+    let mouse_look = mouse_look(replay.as_ref())?;
+    let game_scene = game_scene::compose_scene(seed, mouse_look)?;
+    let mut world = GameWorld::new()
         .with_scene(menu_scene)
-        .with_scene(game_scene)
-        .start(settings)
+        .with_scene(game_scene);
+    if let Some(replay) = replay {
+        world = world.with_replay(replay);
+    }
+    if let Ok(path) = std::env::var(ENV_RECORD) {
+        let sensitivity = mouse_look.sensitivity.to_string();
+        let invert = mouse_look.invert.to_string();
+        let params = [
+            (PARAM_MOUSE_SENSITIVITY, sensitivity.as_str()),
+            (PARAM_MOUSE_INVERT, invert.as_str()),
+        ];
+        world = world.with_recorder(InputRecorder::create_with(path, seed, &params)?);
+    }
+    world.start(settings)
+}
+
+fn session_seed() -> EngineResult<u64> {
+    if let Some(seed) = env_seed(ENV_SEED)? {
+        return Ok(seed);
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(now.as_nanos() as u64)
 }
 
 fn engine_settings() -> EngineResult<EngineSettings> {
@@ -43,8 +104,15 @@ fn engine_settings() -> EngineResult<EngineSettings> {
                 width: 1600,
                 height: 900,
             },
+            mode: WindowMode::Windowed,
+            resizable: true,
+            resolution: env_resolution(ENV_RESOLUTION)?,
+            // whole multiples keep a low resolution's pixels square
+            scaling: Scaling::Integer,
+            capture_mouse: true,
         },
         audio_setting: AudioSettings::default(),
+        timing: TimingSettings::default(),
     })
 }
 
@@ -59,3 +127,59 @@ fn asset_source() -> EngineResult<AssetSource> {
         "Resource bundle & registry are missing".to_string(),
     ))
 }
+
+/// From the recording when replaying one, or else from the environment
+fn mouse_look(replay: Option<&InputReplay>) -> EngineResult<MouseLook> {
+    let (name, sensitivity, invert) = match replay {
+        Some(replay) => (
+            PARAM_MOUSE_SENSITIVITY,
+            replay.param(PARAM_MOUSE_SENSITIVITY).map(str::to_string),
+            replay.param(PARAM_MOUSE_INVERT) == Some("true"),
+        ),
+        None => (
+            ENV_MOUSE_SENSITIVITY,
+            std::env::var(ENV_MOUSE_SENSITIVITY).ok(),
+            std::env::var_os(ENV_MOUSE_INVERT).is_some(),
+        ),
+    };
+    let mut mouse_look = MouseLook {
+        invert,
+        ..Default::default()
+    };
+    if let Some(value) = sensitivity {
+        mouse_look.sensitivity = value
+            .parse::<Float>()
+            .ok()
+            .filter(|x| x.is_finite() && *x > 0.0)
+            .ok_or_else(|| {
+                EngineError::ResourceParseError(format!("{name} is not a positive number: {value}"))
+            })?;
+    }
+    Ok(mouse_look)
+}
+
+fn env_resolution(name: &str) -> EngineResult<Option<SizeU32>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+    let size = value
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0);
+    match size {
+        Some((width, height)) => Ok(Some(SizeU32 { width, height })),
+        None => Err(EngineError::ResourceParseError(format!(
+            "{name} is not <width>x<height>: {value}"
+        ))),
+    }
+}
+
+fn env_seed(name: &str) -> EngineResult<Option<u64>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| EngineError::ResourceParseError(format!("{name} is not a number: {value}")))
+}
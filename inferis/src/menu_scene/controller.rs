@@ -1,7 +1,8 @@
 use engine::{
     prelude::Keycode,
     refresh_cached_entity,
-    systems::{GameControlSystem, InputEvent},
+    systems::{GameControlSystem, GameSystemCommand, InputEvent},
+    window::{WindowMode, WindowState},
     EngineResult, EntityID,
 };
 use log::{info, warn};
@@ -10,12 +11,14 @@ use super::components::{self, CursorTag};
 
 pub struct MenuControlSystem {
     cursor_id: EntityID,
+    command: Option<GameSystemCommand>,
 }
 
 impl MenuControlSystem {
     pub fn new() -> Self {
         Self {
             cursor_id: Default::default(),
+            command: None,
         }
     }
 
@@ -54,6 +57,14 @@ impl GameControlSystem for MenuControlSystem {
                 Keycode::UP => state.up_pressed = *pressed,
                 Keycode::DOWN => state.down_pressed = *pressed,
                 Keycode::RETURN => state.select_pressed = *pressed,
+                // switches between a window and borderless fullscreen, as
+                // in the game
+                Keycode::F11 if *pressed => {
+                    let mode = WindowState::of(storage)
+                        .mode
+                        .toggled(WindowMode::Borderless);
+                    self.command = Some(GameSystemCommand::SetWindowMode(mode));
+                }
                 _ => {
                     // no op
                 }
@@ -61,4 +72,8 @@ impl GameControlSystem for MenuControlSystem {
         }
         Ok(())
     }
+
+    fn command(&mut self) -> GameSystemCommand {
+        self.command.take().unwrap_or(GameSystemCommand::Nothing)
+    }
 }
@@ -1,12 +1,16 @@
+use std::time::Duration;
+
 use engine::{
     fetch_first,
     game_scene::SceneParameters,
     systems::{GameSystem, GameSystemCommand},
+    time::StepRate,
     ComponentStorage, EngineError, EngineResult, EntityID, Query,
 };
 
 use crate::resource::{
-    SCENE_GAME_PLAY, SCENE_PARAM_INVALIDATE, SCENE_PARAM_PAUSE, SCENE_PARAM_WIN,
+    FILE_SAVED_GAME, SCENE_GAME_PLAY, SCENE_PARAM_INVALIDATE, SCENE_PARAM_PAUSE,
+    SCENE_PARAM_RESTORE, SCENE_PARAM_SEED, SCENE_PARAM_TIME_PLAYED, SCENE_PARAM_WIN,
 };
 
 use super::{
@@ -14,9 +18,11 @@ use super::{
     components::{self, CursorTag, LabelTag, MenuAction, MenuItemTag, Position},
 };
 
-const INPUT_DELAY_FRAMES: usize = 10;
+const INPUT_DELAY: Duration = Duration::from_millis(167);
 
-pub struct HandleSystem {}
+pub struct HandleSystem {
+    level_seed: Option<u64>,
+}
 
 impl GameSystem for HandleSystem {
     fn setup(
@@ -54,7 +60,7 @@ impl GameSystem for HandleSystem {
             return Ok(GameSystemCommand::Nothing);
         }
         if input.select_pressed {
-            return Ok(on_select(storage, &entities, position));
+            return Ok(self.on_select(storage, &entities, position));
         }
         let mut new_selection: Option<usize> = None;
         if input.down_pressed {
@@ -76,7 +82,8 @@ impl GameSystem for HandleSystem {
                     "[v2.menu.handle] position component not found for menu item",
                 ))?;
             storage.set(cursor_id, Some(Position(pos)))?;
-            update_input_delay(storage, cursor_id, INPUT_DELAY_FRAMES)?;
+            let delay = StepRate::of(storage).steps(INPUT_DELAY);
+            update_input_delay(storage, cursor_id, delay)?;
         } else {
             update_input_delay(storage, cursor_id, 0)?;
         }
@@ -86,17 +93,39 @@ impl GameSystem for HandleSystem {
     fn on_scene_event(
         &mut self,
         storage: &mut ComponentStorage,
-        _event: engine::game_scene::SceneEvent,
+        event: engine::game_scene::SceneEvent,
         params: &engine::game_scene::SceneParameters,
     ) -> EngineResult<()> {
+        if event != engine::game_scene::SceneEvent::Enter {
+            return Ok(());
+        }
         let cursor_id = fetch_first::<CursorTag>(storage).ok_or(EngineError::unexpected_state(
             "[v2.menu.handle] cursor entity not found",
         ))?;
         storage.set(cursor_id, Some(components::ControllerState::default()))?;
 
+        // over a paused game "Continue" resumes it; otherwise it restores
+        // the saved one
         let is_paused = params.contains_key(SCENE_PARAM_PAUSE);
+        if is_paused {
+            storage.insert_resource(components::Paused);
+        } else {
+            storage.remove_resource::<components::Paused>();
+        }
+        let time_played = params
+            .get(SCENE_PARAM_TIME_PLAYED)
+            .and_then(|secs| secs.parse().ok());
+        match time_played {
+            Some(secs) => {
+                storage.insert_resource(components::TimePlayed(Duration::from_secs(secs)));
+            }
+            None => {
+                storage.remove_resource::<components::TimePlayed>();
+            }
+        }
         if let Some(continue_id) = continue_menu_item(storage) {
-            storage.set(continue_id, Some(components::Visible(is_paused)))?;
+            let can_continue = is_paused || has_saved_game();
+            storage.set(continue_id, Some(components::Visible(can_continue)))?;
             if let Some(pos) = storage.get::<Position>(continue_id).and_then(|x| {
                 if is_paused {
                     Some(x.0)
@@ -134,11 +163,56 @@ impl GameSystem for HandleSystem {
 }
 
 impl HandleSystem {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(level_seed: Option<u64>) -> Self {
+        Self { level_seed }
+    }
+
+    fn on_select(
+        &self,
+        storage: &engine::ComponentStorage,
+        entities: &[EntityID],
+        position: u8,
+    ) -> GameSystemCommand {
+        let Some(action) = selected_index(storage, entities, position)
+            .and_then(|idx| storage.get::<components::MenuAction>(entities[idx]))
+        else {
+            return GameSystemCommand::Nothing;
+        };
+        match *action {
+            components::MenuAction::NewGame => {
+                let mut params = SceneParameters::default();
+                params.insert(SCENE_PARAM_INVALIDATE.to_string(), "".to_string());
+                if let Some(seed) = self.level_seed {
+                    params.insert(SCENE_PARAM_SEED.to_string(), seed.to_string());
+                }
+                GameSystemCommand::SwitchScene {
+                    id: SCENE_GAME_PLAY,
+                    params,
+                }
+            }
+            components::MenuAction::Continue if storage.has_resource::<components::Paused>() => {
+                GameSystemCommand::PopScene {
+                    params: SceneParameters::default(),
+                }
+            }
+            components::MenuAction::Continue => {
+                let mut params = SceneParameters::default();
+                params.insert(SCENE_PARAM_RESTORE.to_string(), "".to_string());
+                GameSystemCommand::SwitchScene {
+                    id: SCENE_GAME_PLAY,
+                    params,
+                }
+            }
+            components::MenuAction::Exit => GameSystemCommand::Terminate,
+        }
     }
 }
 
+/// A game was saved on pause, in this session or an earlier one
+pub fn has_saved_game() -> bool {
+    std::path::Path::new(FILE_SAVED_GAME).exists()
+}
+
 fn continue_menu_item(storage: &mut engine::ComponentStorage) -> Option<EntityID> {
     let query = Query::new().with_component::<MenuItemTag>();
     let entities = storage.fetch_entities(&query);
@@ -204,30 +278,3 @@ fn next_item_index(
     let next = (index + 1) % entities.len();
     Some(next)
 }
-
-fn on_select(
-    storage: &engine::ComponentStorage,
-    entities: &[EntityID],
-    position: u8,
-) -> GameSystemCommand {
-    let Some(action) = selected_index(storage, entities, position)
-        .and_then(|idx| storage.get::<components::MenuAction>(entities[idx]))
-    else {
-        return GameSystemCommand::Nothing;
-    };
-    match *action {
-        components::MenuAction::NewGame => {
-            let mut params = SceneParameters::default();
-            params.insert(SCENE_PARAM_INVALIDATE.to_string(), "".to_string());
-            GameSystemCommand::SwitchScene {
-                id: SCENE_GAME_PLAY,
-                params,
-            }
-        }
-        components::MenuAction::Continue => GameSystemCommand::SwitchScene {
-            id: SCENE_GAME_PLAY,
-            params: SceneParameters::default(),
-        },
-        components::MenuAction::Exit => GameSystemCommand::Terminate,
-    }
-}
@@ -2,7 +2,7 @@ use components::MenuAction;
 use engine::{
     game_scene::GameScene, ComponentStorage, EngineResult, EntityBundle, EntityID, Query,
 };
-use handle::HandleSystem;
+use handle::{has_saved_game, HandleSystem};
 
 use crate::{
     menu_scene::{controller::MenuControlSystem, renderer::MenuRendererSystem},
@@ -43,6 +43,13 @@ mod components {
     }
 
     pub struct Delay(pub usize);
+
+    /// Resource present while the menu is over a paused game
+    pub struct Paused;
+
+    /// Resource with the game time the level was played for, when the menu
+    /// is over a paused or won game
+    pub struct TimePlayed(pub std::time::Duration);
 }
 
 fn compose_component_storage() -> EngineResult<ComponentStorage> {
@@ -59,31 +66,28 @@ fn compose_component_storage() -> EngineResult<ComponentStorage> {
     Ok(storage)
 }
 
-pub fn compose_scene() -> EngineResult<GameScene> {
+/// `level_seed`, if any, is the seed every new game is generated from.
+pub fn compose_scene(level_seed: Option<u64>) -> EngineResult<GameScene> {
     let mut storage = compose_component_storage()?;
-    storage.append(&menu_item(
+    // before any game is played, "Continue" resumes the saved one
+    storage.append(menu_item(
         0,
-        false,
+        has_saved_game(),
         MENU_LABEL_CONTINUE,
         MenuAction::Continue,
     ));
-    storage.append(&menu_item(
-        1,
-        true,
-        MENU_LABEL_NEW_GAME,
-        MenuAction::NewGame,
-    ));
-    storage.append(&menu_item(0xff, true, MENU_LABEL_EXIT, MenuAction::Exit));
-    storage.append(&cursor_entity(1));
+    storage.append(menu_item(1, true, MENU_LABEL_NEW_GAME, MenuAction::NewGame));
+    storage.append(menu_item(0xff, true, MENU_LABEL_EXIT, MenuAction::Exit));
+    storage.append(cursor_entity(1));
 
-    storage.append(&win_label_entity());
+    storage.append(win_label_entity());
     let mut scene = GameScene::new(
         SCENE_MAIN_MENU,
         storage,
         MenuControlSystem::new(),
         MenuRendererSystem::new(),
     );
-    scene.add_system(HandleSystem::new());
+    scene.add_system(HandleSystem::new(level_seed));
     Ok(scene)
 }
 
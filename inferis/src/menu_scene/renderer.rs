@@ -4,7 +4,7 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 use engine::{
     assets::TextureInfo,
     fetch_first,
-    prelude::Rect,
+    prelude::{BlendMode, Color, Rect},
     refresh_cached_entity,
     systems::{GameRendererSystem, RendererEffect, RendererLayers, RendererLayersPtr},
     EngineError, EngineResult, EntityID, SizeU32,
@@ -14,13 +14,23 @@ use crate::resource::MENU_BACKGROUND;
 
 use super::{
     active_menu_items,
-    components::{self, CursorTag, LabelTag, Position, Texture, Visible},
+    components::{self, CursorTag, LabelTag, Paused, Position, Texture, TimePlayed, Visible},
 };
 
 // layout constants
 const MENU_Y_OFFSET: i32 = 250;
 const MENU_SPACING: i32 = 35;
 const MENU_X_OFFSET: i32 = 50;
+const PAUSE_SHADE: Color = Color::RGBA(0, 0, 0, 170);
+// time played, drawn as seven-segment digits in the bottom left corner
+const CLOCK_DIGIT_WIDTH: i32 = 16;
+const CLOCK_DIGIT_HEIGHT: i32 = 28;
+const CLOCK_SEGMENT: i32 = 4;
+const CLOCK_SPACING: i32 = 8;
+const CLOCK_Y_OFFSET: i32 = 80;
+/// Segments lit for each digit, from the lowest bit: top, top right, bottom
+/// right, bottom, bottom left, top left, middle
+const CLOCK_DIGITS: [u8; 10] = [0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07, 0x7f, 0x6f];
 
 pub struct MenuRendererSystem {
     layers: RendererLayersPtr,
@@ -53,8 +63,19 @@ impl MenuRendererSystem {
         )
     }
 
-    fn render_background(&self) -> EngineResult<()> {
+    fn render_background(&self, storage: &engine::ComponentStorage) -> EngineResult<()> {
         let destination = Rect::new(0, 0, self.window_size.width, self.window_size.height);
+        if storage.has_resource::<Paused>() {
+            // the paused level shows through, dimmed
+            let effect = RendererEffect::Rectangle {
+                color: PAUSE_SHADE,
+                fill: true,
+                blend_mode: BlendMode::Blend,
+                rect: destination,
+            };
+            self.layers.borrow_mut().push_background(effect);
+            return Ok(());
+        }
         let Some(background) = self.textures.get(MENU_BACKGROUND) else {
             return Ok(());
         };
@@ -163,6 +184,64 @@ impl MenuRendererSystem {
         layers.push_hud(effect);
         Ok(())
     }
+
+    fn render_time_played(&self, storage: &engine::ComponentStorage) {
+        let Some(time_played) = storage.resource::<TimePlayed>().map(|x| x.0) else {
+            return;
+        };
+        let secs = time_played.as_secs();
+        let text = format!("{}:{:02}", secs / 60, secs % 60);
+        let y = self.window_size.height as i32 - CLOCK_Y_OFFSET;
+        self.layers
+            .borrow_mut()
+            .push_hud(RendererEffect::Rectangles {
+                color: Color::WHITE,
+                fill: true,
+                blend_mode: BlendMode::None,
+                rects: clock_rects(&text, MENU_X_OFFSET, y),
+            });
+    }
+}
+
+/// The segments drawing `text`, digits and colons, from its top left corner
+fn clock_rects(text: &str, x: i32, y: i32) -> Vec<Rect> {
+    const W: i32 = CLOCK_DIGIT_WIDTH;
+    const H: i32 = CLOCK_DIGIT_HEIGHT;
+    const T: i32 = CLOCK_SEGMENT;
+    let half = H / 2;
+    let rect = |x, y, w, h| Rect::new(x, y, w as u32, h as u32);
+    let mut rects = Vec::new();
+    let mut x = x;
+    for ch in text.chars() {
+        if ch == ':' {
+            rects.push(rect(x, y + half - 2 * T, T, T));
+            rects.push(rect(x, y + half + T, T, T));
+            x += T + CLOCK_SPACING;
+            continue;
+        }
+        let Some(digit) = ch.to_digit(10) else {
+            continue;
+        };
+        let segments = [
+            rect(x, y, W, T),
+            rect(x + W - T, y, T, half),
+            rect(x + W - T, y + half, T, half),
+            rect(x, y + H - T, W, T),
+            rect(x, y + half, T, half),
+            rect(x, y, T, half),
+            rect(x, y + half - T / 2, W, T),
+        ];
+        let lit = CLOCK_DIGITS[digit as usize];
+        rects.extend(
+            segments
+                .into_iter()
+                .enumerate()
+                .filter(|(bit, _)| lit & (1 << bit) != 0)
+                .map(|(_, segment)| segment),
+        );
+        x += W + CLOCK_SPACING;
+    }
+    rects
 }
 
 impl GameRendererSystem for MenuRendererSystem {
@@ -187,9 +266,23 @@ impl GameRendererSystem for MenuRendererSystem {
     ) -> engine::EngineResult<engine::systems::RendererLayersPtr> {
         self.update_storage_cache(storage)?;
         self.layers.borrow_mut().clear();
-        self.render_background()?;
+        self.render_background(storage)?;
         self.render_menu(storage)?;
         self.render_label(storage)?;
+        self.render_time_played(storage);
         Ok(self.layers.clone())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clock_lights_the_segments_of_each_digit() {
+        // 6 for a zero, 2 for the colon, 5 for a five
+        assert_eq!(clock_rects("0:05", 0, 0).len(), 6 + 2 + 6 + 5);
+        assert_eq!(clock_rects("8", 0, 0).len(), 7);
+        assert_eq!(clock_rects("1", 0, 0).len(), 2);
+    }
+}
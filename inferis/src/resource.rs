@@ -3,6 +3,8 @@ use engine::SceneID;
 // files
 pub const FILE_ASSET_REGISTRY: &str = "assets/asset_registry.txt";
 pub const FILE_ASSET_BUNDLE: &str = "inferis.bin";
+pub const FILE_SAVED_GAME: &str = "inferis.sav";
+pub const FILE_STORAGE_DUMP: &str = "inferis.dump.txt";
 
 // scenes
 pub const SCENE_GAME_PLAY: SceneID = 1;
@@ -11,6 +13,17 @@ pub const SCENE_MAIN_MENU: SceneID = 2;
 pub const SCENE_PARAM_INVALIDATE: &str = "invalidate";
 pub const SCENE_PARAM_PAUSE: &str = "pause";
 pub const SCENE_PARAM_WIN: &str = "win";
+/// Level seed for an `invalidate`, as a decimal `u64`
+pub const SCENE_PARAM_SEED: &str = "seed";
+/// Resume the game saved on pause instead of the one in memory
+pub const SCENE_PARAM_RESTORE: &str = "restore";
+/// Game time the level has been played for, in whole seconds
+pub const SCENE_PARAM_TIME_PLAYED: &str = "time_played";
+
+// prefabs
+pub const PREFAB_PLAYER: &str = "prefab_player";
+pub const PREFAB_NPC_SOLDIER: &str = "prefab_npc_soldier";
+pub const PREFAB_TORCH: &str = "prefab_torch";
 
 // world
 pub const WORLD_WALL1: &str = "wall1";